@@ -2,8 +2,11 @@ use std::path::PathBuf;
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use crate::model::{AddDirection, CalcMethod, ConstValue, ConvEnProfile, ConvZhProfile, Direction,
-                   FilenameExistPolicy, ModifyMode, MyTag, QueryResultPosition, SetWhen, TextConst};
+use crate::model::{AddDirection, ArtOperation, CalcMethod, ConstValue, ConvEnProfile, ConvZhProfile,
+                   DataFormat, Direction, FilenameExistPolicy, GroupBy, ModifyMode, MyTag, PictureType,
+                   PropOperation, PropsMode, QueryResultPosition, SanitizeProfile, SetWhen,
+                   TextConst};
+use crate::util::sanitize::DEFAULT_MAX_BYTES;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -16,20 +19,180 @@ pub struct App {
 #[command(author, version, about, long_about = None)]
 pub enum Command {
     View(ViewArgs),
+    Art(ArtArgs),
     Clear(ClearArgs),
     ConvEn(ConvEnArgs),
     ConvUtf8(ConvUtf8Args),
     ConvZh(ConvZhArgs),
     Exp(ExpArgs),
+    History(HistoryArgs),
     Imp(ImpArgs),
+    JoinValues(JoinValuesArgs),
     Lrc(LrcArgs),
     ModNum(ModNumArgs),
     ModTextConst(ModTexConstArgs),
     ModTextRegex(ModTextRegexArgs),
+    Organize(OrganizeArgs),
+    Prop(PropArgs),
     SetConst(SetConstArgs),
+    SetExpr(SetExprArgs),
     SetName(SetNameArgs),
     SetSeq(SetSeqArgs),
+    SplitValues(SplitValuesArgs),
+    Undo(UndoArgs),
     Ren(RenArgs),
+    Run(RunArgs),
+}
+
+impl Command {
+    /// Commands which could write files record a journal for `undo`, except in dry-run mode.
+    pub fn is_journaled(&self) -> bool {
+        match self {
+            Command::View(_) | Command::Exp(_) | Command::History(_) | Command::Run(_) => false,
+            Command::Art(args) => !args.dry_run,
+            Command::Clear(args) => !args.dry_run,
+            Command::ConvEn(args) => !args.global_opts.dry_run,
+            Command::ConvUtf8(args) => !args.global_opts.dry_run,
+            Command::ConvZh(args) => !args.global_opts.dry_run,
+            Command::Imp(args) => !args.dry_run,
+            Command::JoinValues(args) => !args.global_opts.dry_run,
+            Command::Lrc(args) => !args.dry_run,
+            Command::ModNum(args) => !args.dry_run,
+            Command::ModTextConst(args) => !args.global_opts.dry_run,
+            Command::ModTextRegex(args) => !args.global_opts.dry_run,
+            Command::Organize(args) => !args.dry_run,
+            Command::Prop(args) => !args.dry_run,
+            Command::SetConst(args) => !args.global_opts.dry_run,
+            Command::SetExpr(args) => !args.global_opts.dry_run,
+            Command::SetName(args) => !args.dry_run,
+            Command::SetSeq(args) => !args.global_opts.dry_run,
+            Command::SplitValues(args) => !args.global_opts.dry_run,
+            Command::Undo(args) => !args.dry_run,
+            Command::Ren(args) => !args.dry_run && !args.preview,
+        }
+    }
+
+    /// `--where` and the options work with it, for commands which support `--where`.
+    pub fn get_where_args(&mut self) -> Option<&mut WhereArgs> {
+        match self {
+            Command::History(_) | Command::Imp(_) | Command::SetSeq(_) | Command::Undo(_)
+            | Command::Run(_) => None,
+            Command::View(args) => Some(&mut args.where_args),
+            Command::Art(args) => Some(&mut args.where_args),
+            Command::Clear(args) => Some(&mut args.where_args),
+            Command::ConvEn(args) => Some(&mut args.global_opts.where_args),
+            Command::ConvUtf8(args) => Some(&mut args.global_opts.where_args),
+            Command::ConvZh(args) => Some(&mut args.global_opts.where_args),
+            Command::Exp(args) => Some(&mut args.where_args),
+            Command::JoinValues(args) => Some(&mut args.global_opts.where_args),
+            Command::Lrc(args) => Some(&mut args.where_args),
+            Command::ModNum(args) => Some(&mut args.where_args),
+            Command::ModTextConst(args) => Some(&mut args.global_opts.where_args),
+            Command::ModTextRegex(args) => Some(&mut args.global_opts.where_args),
+            Command::Organize(args) => Some(&mut args.where_args),
+            Command::Prop(args) => Some(&mut args.where_args),
+            Command::SetConst(args) => Some(&mut args.where_args),
+            Command::SetExpr(args) => Some(&mut args.where_args),
+            Command::SetName(args) => Some(&mut args.where_args),
+            Command::SplitValues(args) => Some(&mut args.global_opts.where_args),
+            Command::Ren(args) => Some(&mut args.where_args),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Run a preset in [presets] of configuration file, \
+more arguments are appended to the arguments of the preset.")]
+pub struct RunArgs {
+    #[arg(help = "Name of the preset.")]
+    pub preset: String,
+
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    #[arg(help = "More arguments, e.g. the path of your music file(s).")]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "List / Export / Embed / Remove pictures in tags.")]
+pub struct ArtArgs {
+    #[command(subcommand)]
+    pub operation: ArtOperationArgs,
+
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
+    pub dry_run: bool,
+
+    #[arg(short, long, default_value_t = false)]
+    #[arg(help = "Only show error in console, if it was set as true.")]
+    pub quiet: bool,
+
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "The path of your music file(s). It must point to a file or directory path.")]
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ArtOperationArgs {
+    #[command(long_about = "List pictures.")]
+    List,
+    #[command(long_about = "Export a picture to the directory of the music file.")]
+    Export {
+        #[arg(short, long, value_enum, default_value_t = PictureType::FrontCover)]
+        #[arg(help = "Picture type.")]
+        picture_type: PictureType,
+
+        #[arg(short, long, default_value = "cover")]
+        #[arg(help = "File stem of the exported picture, extension depends on its MIME type.")]
+        name: String,
+
+        #[arg(short = 'x', long, value_enum, default_value_t = FilenameExistPolicy::Skip)]
+        #[arg(help = "If output file exist, how to process.")]
+        filename_exist_policy: FilenameExistPolicy,
+    },
+    #[command(long_about = "Embed a picture, it will replace the picture in same type.")]
+    Embed {
+        #[arg(short, long, value_enum, default_value_t = PictureType::FrontCover)]
+        #[arg(help = "Picture type.")]
+        picture_type: PictureType,
+
+        #[arg(short, long)]
+        #[arg(value_hint = clap::ValueHint::FilePath)]
+        #[arg(help = "JPEG or PNG file. If not set it, `folder.jpg` (or `folder.png`) \
+        in the same directory of the music file will be used.")]
+        source_file: Option<PathBuf>,
+
+        #[arg(short, long, default_value = "")]
+        #[arg(help = "Picture description.")]
+        description: String,
+    },
+    #[command(long_about = "Remove pictures.")]
+    Remove {
+        #[arg(short, long, value_enum)]
+        #[arg(help = "Picture type, if not set, it will remove ALL pictures.")]
+        picture_type: Option<PictureType>,
+    },
+}
+
+impl From<ArtOperationArgs> for ArtOperation {
+    fn from(value: ArtOperationArgs) -> Self {
+        match value {
+            ArtOperationArgs::List => ArtOperation::List,
+            ArtOperationArgs::Export {
+                picture_type, name, filename_exist_policy
+            } => ArtOperation::Export { picture_type, name, filename_exist_policy },
+            ArtOperationArgs::Embed {
+                picture_type, source_file, description
+            } => ArtOperation::Embed { picture_type, source_file, description },
+            ArtOperationArgs::Remove {
+                picture_type
+            } => ArtOperation::Remove { picture_type },
+        }
+    }
 }
 
 #[derive(Debug, Args)]
@@ -39,12 +202,8 @@ pub struct ClearArgs {
     #[arg(help = "Process specified tags, if not set, it will NOT process any tag.")]
     pub tags: Vec<MyTag>,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(long, default_value_t = false)]
     #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
@@ -66,16 +225,34 @@ pub struct ExpArgs {
     #[arg(help = "Export properties or NOT (default).")]
     pub with_properties: bool,
 
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Export audio properties (bitrate, length, sample-rate, channels, \
+    bits-per-sample) or NOT (default).")]
+    pub with_audio_properties: bool,
+
     #[arg(short, long, value_delimiter = ',')]
     #[arg(help = "Process specified tags, if not set, it will process ALL tags.")]
     pub tags: Vec<MyTag>,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
+
+    #[arg(short, long, value_enum, default_value_t = DataFormat::Json)]
+    #[arg(help = "Format of output file. CSV / TSV has one column per tag, multiple values are \
+    joined by `multi_value_separator` in configuration file.")]
+    pub format: DataFormat,
+
+    #[arg(long, value_name = "KEY", value_delimiter = ',')]
+    #[arg(help = "Property columns for CSV / TSV format, the header is like `prop:KEY`.")]
+    pub prop_columns: Vec<String>,
+
+    #[arg(long)]
+    #[arg(help = "Delimiter for CSV / TSV format, default is `,` for CSV and tab for TSV.")]
+    pub delimiter: Option<char>,
+
+    #[arg(long, default_value_t = '"')]
+    #[arg(help = "Quote character for CSV / TSV format.")]
+    pub quote: char,
 
     #[arg(short = 'x', long, value_enum, default_value_t = FilenameExistPolicy::Skip)]
     #[arg(help = "If output file exist, how to process.")]
@@ -83,7 +260,7 @@ pub struct ExpArgs {
 
     #[arg(short, long)]
     #[arg(value_hint = clap::ValueHint::FilePath)]
-    #[arg(help = "Output file, it must be NOT exists!")]
+    #[arg(help = "Output file, it must be NOT exists! `-` for stdout.")]
     pub output_file: PathBuf,
 
     #[arg(value_hint = clap::ValueHint::FilePath)]
@@ -98,16 +275,17 @@ pub struct ViewArgs {
     #[arg(help = "Show properties or NOT (default).")]
     pub with_properties: bool,
 
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Show audio properties (bitrate, length, sample-rate, channels, \
+    bits-per-sample) or NOT (default).")]
+    pub with_audio_properties: bool,
+
     #[arg(short, long, value_delimiter = ',')]
     #[arg(help = "Process specified tags, if not set, it will process ALL tags.")]
     pub tags: Vec<MyTag>,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(value_hint = clap::ValueHint::FilePath)]
     #[arg(help = "The path of your music file(s). It must point to a file or directory path.")]
@@ -126,12 +304,8 @@ pub struct SetNameArgs {
     #[arg(help = "Template for parsing filename like \"${track-number} - ${title} - ${artist}\".")]
     pub template: String,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(long, default_value_t = false)]
     #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
@@ -160,17 +334,57 @@ pub struct SetConstArgs {
     #[arg(help = "How to modify the tag if tag has already exist, only worked for TEXT tags.")]
     pub modify_mode: ModifyMode,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[clap(flatten)]
     pub global_opts: GlobalAllTagsDefaultEmpty,
 }
 
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Set tags by an expression with other tags, properties and path.")]
+pub struct SetExprArgs {
+    #[arg(short, long)]
+    #[arg(help = "Expression like \"${pad(track-number, 2)}. ${trim(title)}\", \
+    the text in `${}` could be a tag, `prop('KEY')`, `filename` `stem` `ext` `dir` `parent-dir`, \
+    a 'string' or an integer, and functions `upper` `lower` `caps` `trim` `left` `right` `pad` \
+    `replace` `if` `coalesce`.")]
+    pub expression: String,
+
+    #[arg(short = 'w', long, value_enum, default_value_t = SetWhen::Always)]
+    #[arg(help = "When to set the tag.")]
+    pub set_when: SetWhen,
+
+    #[arg(short, long, value_enum, default_value_t = ModifyMode::Overwrite)]
+    #[arg(help = "How to modify the tag if tag has already exist, only worked for TEXT tags.")]
+    pub modify_mode: ModifyMode,
+
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
+
+    #[clap(flatten)]
+    pub global_opts: GlobalAllTagsDefaultEmpty,
+}
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Split text tags into multiple values by separator REGEX patterns.")]
+pub struct SplitValuesArgs {
+    #[arg(short, long)]
+    #[arg(help = "Separator REGEX pattern, it could be set multiple times. If not set, \
+    it will use `split_separators` in configuration file, default are `;` `/` `&` `feat.` `ft.`. \
+    (ref: https://docs.rs/fancy-regex/latest/fancy_regex/#syntax)")]
+    pub separator: Vec<String>,
+
+    #[arg(short, long, default_value_t = false)]
+    #[arg(help = "Separator match will be case insensitive, if it was set as true.")]
+    pub ignore_case: bool,
+
+    #[clap(flatten)]
+    pub global_opts: GlobalMultiValueTagsDefault,
+}
+
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true, long_about = "Set sequence value for tags.")]
 pub struct SetSeqArgs {
@@ -210,8 +424,10 @@ pub enum ConstValueArgs {
     #[command(arg_required_else_help = true)]
     #[command(long_about = "Set a text constant, only was applied to TEXt tags.")]
     Text {
-        #[arg(help = "Text value.")]
-        value: String,
+        #[arg(required = true)]
+        #[arg(help = "Text value. Multiple values could be set for multi-value tags, \
+        they only work with \"--modify-mode overwrite\".")]
+        values: Vec<String>,
     },
     #[command(long_about = "Set a numeric constant, only was applied to NUMERIC tags.")]
     Num {
@@ -238,8 +454,8 @@ pub enum ConstValueArgs {
 impl From<ConstValueArgs> for ConstValue {
     fn from(value: ConstValueArgs) -> Self {
         match value {
-            ConstValueArgs::Text { value } =>
-                ConstValue::Text { value },
+            ConstValueArgs::Text { values } =>
+                ConstValue::Text { values },
             ConstValueArgs::Num { value, padding } =>
                 ConstValue::Num { value, padding },
             ConstValueArgs::Date { value, format } =>
@@ -333,6 +549,39 @@ impl From<TextConstArgs> for TextConst {
     }
 }
 
+#[derive(Debug, Args)]
+#[command(long_about = "List runs which modified files, or show changes of a run.")]
+pub struct HistoryArgs {
+    #[arg(short = 'n', long, default_value_t = 20)]
+    #[arg(help = "Max count of runs to list, the latest is the first.")]
+    pub limit: usize,
+
+    #[arg(help = "Show changes of the run. If not set, it will list runs.")]
+    pub run_id: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Undo a run, restore old values of tags and properties, \
+reverse renames. It will refuse if any file was changed since the run.")]
+pub struct UndoArgs {
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
+    pub dry_run: bool,
+
+    #[arg(short, long, default_value_t = false)]
+    #[arg(help = "Only show error in console, if it was set as true.")]
+    pub quiet: bool,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Skip the files changed since the run and undo the others, \
+    instead of refusing the whole run.")]
+    pub skip_changed: bool,
+
+    #[arg(help = "Run id, it could be found by `history` command.")]
+    pub run_id: String,
+}
+
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true, long_about = "Import tags from file.")]
 pub struct ImpArgs {
@@ -346,14 +595,63 @@ pub struct ImpArgs {
 
     #[arg(short, long)]
     #[arg(value_hint = clap::ValueHint::FilePath)]
-    #[arg(help = "The base path for your JSON content `path` attribute, if it is a relative path.")]
+    #[arg(help = "The base path for your JSON content `path` attribute, if it is a relative path. \
+    It takes precedence over `root` in the document exported by `exp`.")]
     pub base_directory: Option<PathBuf>,
 
+    #[arg(short, long, value_enum, default_value_t = PropsMode::Merge)]
+    #[arg(help = "How to import `props` in JSON content. A property with empty array \
+    will be removed.")]
+    pub props_mode: PropsMode,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Validate all records at first (file exists, tag types, numeric ranges, date \
+    formats) and show all problems, nothing is written if any record is invalid, unless \
+    \"--continue-on-error\" is set.")]
+    pub validate_all: bool,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Skip the failed records instead of stopping at the first one, and show a \
+    summary of errors at last.")]
+    pub continue_on_error: bool,
+
+    #[arg(short, long, value_enum)]
+    #[arg(help = "Format of source file, if not set, it is guessed by extension `.csv` `.tsv` \
+    `.ndjson`, otherwise JSON. Columns of CSV / TSV are mapped by header, `path` is required.")]
+    pub format: Option<DataFormat>,
+
+    #[arg(long)]
+    #[arg(help = "Delimiter for CSV / TSV format, default is `,` for CSV and tab for TSV.")]
+    pub delimiter: Option<char>,
+
+    #[arg(long, default_value_t = '"')]
+    #[arg(help = "Quote character for CSV / TSV format.")]
+    pub quote: char,
+
+    #[arg(short, long, default_value = "utf-8")]
+    #[arg(help = "Encoding of CSV / TSV source file. eg. GBK Big5 shift_jis Windows-1252 ... \
+    (ref: https://docs.rs/encoding_rs/latest/encoding_rs/)")]
+    pub encoding_name: String,
+
     #[arg(value_hint = clap::ValueHint::FilePath)]
-    #[arg(help = "The source file you want to import.")]
+    #[arg(help = "The source file you want to import, `-` for stdin.")]
     pub source_file: PathBuf,
 }
 
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Join multiple values of text tags into one string, for players \
+which only show the first value.")]
+pub struct JoinValuesArgs {
+    #[arg(short, long)]
+    #[arg(help = "Separator between values, if not set, it will use `multi_value_separator` \
+    in configuration file, default is \"; \".")]
+    pub separator: Option<String>,
+
+    #[clap(flatten)]
+    pub global_opts: GlobalMultiValueTagsDefault,
+}
+
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true, long_about = "Import / Export lyric file for tags.")]
 pub struct LrcArgs {
@@ -370,12 +668,8 @@ pub struct LrcArgs {
     #[arg(help = "Only show error in console, if it was set as true.")]
     pub quiet: bool,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(short = 'x', long, value_enum, default_value_t = FilenameExistPolicy::Skip)]
     #[arg(help = "If output file exist, how to process. (Only worked in `-d export` mode.)")]
@@ -408,21 +702,87 @@ pub struct RenArgs {
     #[arg(help = "Only show error in console, if it was set as true.")]
     pub quiet: bool,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Only show a table of old and new filenames, but do NOT rename any file, \
+    if it was set as true.")]
+    pub preview: bool,
+
+    #[arg(short = 'x', long, value_enum, default_value_t = FilenameExistPolicy::Skip)]
+    #[arg(help = "If output file exist, how to process.")]
+    pub filename_exist_policy: FilenameExistPolicy,
+
+    #[arg(long, value_enum, default_value_t = SanitizeProfile::default())]
+    #[arg(help = "How to sanitize the names generated by template, default is `windows` on \
+    Windows, otherwise `posix`.")]
+    pub sanitize: SanitizeProfile,
+
+    #[arg(long, default_value_t = DEFAULT_MAX_BYTES)]
+    #[arg(help = "Max bytes of a filename or directory name, it will be truncated without \
+    breaking UTF-8 characters.")]
+    pub max_bytes: usize,
+
+    #[arg(long)]
+    #[arg(help = "Template for new filename like \"${track-number:02}. ${title}[ - ${artist}]\", \
+    a section in `[]` will be dropped if all tags in it are empty, `${album-artist|artist}` uses \
+    the first NOT empty one, `:02` pads it with `0`. Functions and path components are same as \
+    `set-expr`.")]
+    pub template: String,
+
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "The path of your music file(s). It must point to a file or directory path.")]
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Move / Copy files into directories by tags, under a destination root.")]
+pub struct OrganizeArgs {
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Only show how to move files, but do NOT write any file, if it was set as true.")]
+    pub dry_run: bool,
+
+    #[arg(short, long, default_value_t = false)]
+    #[arg(help = "Only show error in console, if it was set as true.")]
+    pub quiet: bool,
+
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(short = 'x', long, value_enum, default_value_t = FilenameExistPolicy::Skip)]
     #[arg(help = "If output file exist, how to process.")]
     pub filename_exist_policy: FilenameExistPolicy,
 
+    #[arg(long, value_enum, default_value_t = SanitizeProfile::default())]
+    #[arg(help = "How to sanitize the names generated by template, default is `windows` on \
+    Windows, otherwise `posix`.")]
+    pub sanitize: SanitizeProfile,
+
+    #[arg(long, default_value_t = DEFAULT_MAX_BYTES)]
+    #[arg(help = "Max bytes of a filename or directory name, it will be truncated without \
+    breaking UTF-8 characters.")]
+    pub max_bytes: usize,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Copy files instead of moving them, if it was set as true.")]
+    pub copy: bool,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Remove the source directories which became empty, if it was set as true.")]
+    pub remove_empty_dirs: bool,
+
     #[arg(long)]
-    #[arg(help = "Template for new filename like \"${track-number} - ${title} - ${artist}\".")]
+    #[arg(help = "Template for the relative path WITHOUT extension, like \
+    \"${album-artist|artist}/[${year} - ]${album-title}/[Disc ${disc-number}/]\
+    ${track-number:02} - ${title}\", `/` separates directories. The syntax is same as `ren`.")]
     pub template: String,
 
+    #[arg(short, long, value_hint = clap::ValueHint::DirPath)]
+    #[arg(help = "The destination root directory.")]
+    pub dest: PathBuf,
+
     #[arg(value_hint = clap::ValueHint::FilePath)]
     #[arg(help = "The path of your music file(s). It must point to a file or directory path.")]
     pub directory: PathBuf,
@@ -448,12 +808,8 @@ pub struct ModNumArgs {
     #[arg(help = "Process specified NUMERIC tags, if not set, it will process ALL NUMERIC tags.")]
     pub tags: Vec<NumericTagArgs>,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(long, default_value_t = false)]
     #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
@@ -502,6 +858,88 @@ pub struct ModTextRegexArgs {
     pub global_opts: GlobalTextTagsDefaultAll,
 }
 
+#[derive(Debug, Args)]
+#[command(arg_required_else_help = true)]
+#[command(long_about = "Set / Add / Remove / Rename-key properties, property key is case \
+insensitive. eg. BPM ISRC LABEL MUSICBRAINZ_ALBUMID ...")]
+pub struct PropArgs {
+    #[command(subcommand)]
+    pub operation: PropOperationArgs,
+
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
+    pub dry_run: bool,
+
+    #[arg(short, long, default_value_t = false)]
+    #[arg(help = "Only show error in console, if it was set as true.")]
+    pub quiet: bool,
+
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "The path of your music file(s). It must point to a file or directory path.")]
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PropOperationArgs {
+    #[command(arg_required_else_help = true)]
+    #[command(long_about = "Set values of a property, existing values will be replaced.")]
+    Set {
+        #[arg(help = "Property key.")]
+        key: String,
+
+        #[arg(required = true)]
+        #[arg(help = "Property value(s).")]
+        values: Vec<String>,
+    },
+    #[command(arg_required_else_help = true)]
+    #[command(long_about = "Add values to a property, existing values will be kept.")]
+    Add {
+        #[arg(help = "Property key.")]
+        key: String,
+
+        #[arg(required = true)]
+        #[arg(help = "Property value(s).")]
+        values: Vec<String>,
+    },
+    #[command(arg_required_else_help = true)]
+    #[command(long_about = "Remove values of a property.")]
+    Remove {
+        #[arg(help = "Property key.")]
+        key: String,
+
+        #[arg(help = "Property value(s) to be removed, if not set, it will remove the whole \
+        property.")]
+        values: Vec<String>,
+    },
+    #[command(arg_required_else_help = true)]
+    #[command(long_about = "Rename a property key, values will be kept.")]
+    RenameKey {
+        #[arg(help = "Original property key.")]
+        from: String,
+
+        #[arg(help = "New property key, it must NOT exist in file.")]
+        to: String,
+    },
+}
+
+impl From<PropOperationArgs> for PropOperation {
+    fn from(value: PropOperationArgs) -> Self {
+        match value {
+            PropOperationArgs::Set { key, values } =>
+                PropOperation::Set { key, values },
+            PropOperationArgs::Add { key, values } =>
+                PropOperation::Add { key, values },
+            PropOperationArgs::Remove { key, values } =>
+                PropOperation::Remove { key, values },
+            PropOperationArgs::RenameKey { from, to } =>
+                PropOperation::RenameKey { from, to },
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
 #[command(long_about = "Convert text tags in Chinese characters between Traditional / Simplified /\
@@ -540,6 +978,39 @@ pub struct ConvUtf8Args {
     pub global_opts: GlobalTextTagsDefaultAll,
 }
 
+/// `--where` and the options work with it, for commands which support `--where`.
+#[derive(Debug, Args)]
+pub struct WhereArgs {
+    #[arg(long = "where")]
+    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
+    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
+    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive, `REGEXP` \
+    `IREGEXP` match regular expression. `IS NULL` \
+    `IS EMPTY` `IS BLANK` predicates, also with `IS NOT`. `IN (...)` `BETWEEN x AND y`, \
+    also with `NOT`. Right side could be another tag with the same type. File fields `path` \
+    `filename` `ext` `dir` `size` `mtime` and audio properties `bitrate` `length` `sample-rate` \
+    `channels` `bits-per-sample` are also supported. `prop('KEY')` for a property, and \
+    `HAS_PROP('KEY')` predicate. Functions `lower()` `upper()` `trim()` `length()` `substr()` \
+    `coalesce()` `num()` `text()` `year()` `month()` could be used on either side. \
+    Note: `'` should be escaped as `''` like in SQL string.")]
+    pub where_clause: Option<String>,
+
+    #[arg(long, value_name = "NAME", value_delimiter = ',')]
+    #[arg(help = "Named `where` clauses in [filters] of configuration file, they are combined \
+    with \"--where\" by `AND`.")]
+    pub filter: Vec<String>,
+
+    #[arg(long = "where-group")]
+    #[arg(help = "`Where` clause for groups of files, only files of the matched groups will be \
+    processed. Tags and fields must be in aggregate functions `count()` `count_distinct(x)` \
+    `min(x)` `max(x)`, other functions and operators are same as \"--where\".")]
+    pub where_group: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = GroupBy::Dir)]
+    #[arg(help = "How to group files for \"--where-group\".")]
+    pub group_by: GroupBy,
+}
+
 #[derive(Debug, Args)]
 pub struct GlobalAllTagsDefaultEmpty {
     #[arg(short, long, value_delimiter = ',')]
@@ -565,12 +1036,31 @@ pub struct GlobalTextTagsDefaultAll {
     #[arg(help = "Process specified TEXT tags, if not set, it will process ALL TEXT tags.")]
     pub tags: Vec<TextTagArgs>,
 
-    #[arg(long = "where")]
-    #[arg(help = "`Where` clause for prediction. It is like SQL, supported `NOT` `AND` `OR` \
-    logic operators, `=` `<` `<=` `>` `>=` `!=` `<>` comparison operators, `LIKE` also is \
-    supported with `%` `_` wildcards, `ILIKE` is same but case insensitive. \
-    Note: `'` should be escaped as `''` like in SQL string.")]
-    pub where_clause: Option<String>,
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
+
+    #[arg(long, default_value_t = false)]
+    #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
+    pub dry_run: bool,
+
+    #[arg(short, long, default_value_t = false)]
+    #[arg(help = "Only show error in console, if it was set as true.")]
+    pub quiet: bool,
+
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "The path of your music file(s). It must point to a file or directory path.")]
+    pub directory: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalMultiValueTagsDefault {
+    #[arg(short, long, value_delimiter = ',')]
+    #[arg(help = "Process specified TEXT tags, if not set, it will process artist, album-artist, \
+    genre, composer.")]
+    pub tags: Vec<TextTagArgs>,
+
+    #[clap(flatten)]
+    pub where_args: WhereArgs,
 
     #[arg(long, default_value_t = false)]
     #[arg(help = "Only show how to modify tags, but do NOT write any file, if it was set as true.")]
@@ -596,6 +1086,22 @@ pub enum TextTagArgs {
     Comment,
     Copyright,
     Lyrics,
+    TitleSort,
+    ArtistSort,
+    AlbumSort,
+    AlbumArtistSort,
+    Isrc,
+    Label,
+    CatalogNumber,
+    Grouping,
+    Conductor,
+    MusicbrainzTrackId,
+    MusicbrainzAlbumId,
+    MusicbrainzArtistId,
+    MusicbrainzAlbumArtistId,
+    MusicbrainzReleaseGroupId,
+    MusicbrainzReleaseTrackId,
+    MusicbrainzWorkId,
 }
 
 impl From<TextTagArgs> for MyTag {
@@ -610,6 +1116,22 @@ impl From<TextTagArgs> for MyTag {
             TextTagArgs::Comment => MyTag::Comment,
             TextTagArgs::Copyright => MyTag::Copyright,
             TextTagArgs::Lyrics => MyTag::Lyrics,
+            TextTagArgs::TitleSort => MyTag::TitleSort,
+            TextTagArgs::ArtistSort => MyTag::ArtistSort,
+            TextTagArgs::AlbumSort => MyTag::AlbumSort,
+            TextTagArgs::AlbumArtistSort => MyTag::AlbumArtistSort,
+            TextTagArgs::Isrc => MyTag::Isrc,
+            TextTagArgs::Label => MyTag::Label,
+            TextTagArgs::CatalogNumber => MyTag::CatalogNumber,
+            TextTagArgs::Grouping => MyTag::Grouping,
+            TextTagArgs::Conductor => MyTag::Conductor,
+            TextTagArgs::MusicbrainzTrackId => MyTag::MusicbrainzTrackId,
+            TextTagArgs::MusicbrainzAlbumId => MyTag::MusicbrainzAlbumId,
+            TextTagArgs::MusicbrainzArtistId => MyTag::MusicbrainzArtistId,
+            TextTagArgs::MusicbrainzAlbumArtistId => MyTag::MusicbrainzAlbumArtistId,
+            TextTagArgs::MusicbrainzReleaseGroupId => MyTag::MusicbrainzReleaseGroupId,
+            TextTagArgs::MusicbrainzReleaseTrackId => MyTag::MusicbrainzReleaseTrackId,
+            TextTagArgs::MusicbrainzWorkId => MyTag::MusicbrainzWorkId,
         }
     }
 }
@@ -621,6 +1143,7 @@ pub enum NumericTagArgs {
     TrackTotal,
     DiscNumber,
     DiscTotal,
+    Bpm,
 }
 
 impl From<NumericTagArgs> for MyTag {
@@ -631,6 +1154,7 @@ impl From<NumericTagArgs> for MyTag {
             NumericTagArgs::TrackTotal => MyTag::TrackTotal,
             NumericTagArgs::DiscNumber => MyTag::DiscNumber,
             NumericTagArgs::DiscTotal => MyTag::DiscTotal,
+            NumericTagArgs::Bpm => MyTag::Bpm,
         }
     }
 }
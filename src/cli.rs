@@ -1,24 +1,38 @@
-use anyhow::Error;
+use std::iter;
+
+use anyhow::{anyhow, Error};
 use clap::Parser;
 use flexi_logger::{Age, Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, Logger, LoggerHandle, Naming, TS_DASHES_BLANK_COLONS_DOT_BLANK, WriteMode};
+use itertools::Itertools;
 use log::{debug, error, Record};
 
-use crate::args::{App, Command, LrcDirection};
-use crate::config::get_log_level;
+use crate::args::{App, ArtOperationArgs, Command, LrcDirection, RunArgs, WhereArgs};
+use crate::config::{get_filter, get_log_level, get_preset};
+use crate::journal;
+use crate::util::path::is_std_stream;
+use crate::util::sanitize::Sanitizer;
+use crate::where_clause::WhereClause;
 
-use crate::op::{Action, ClearAction, LrcExpAction, LrcImpAction};
+use crate::op::{Action, ArtAction, ClearAction, LrcExpAction, LrcImpAction, set_where_group};
 use crate::op::ConvEnAction;
 use crate::op::ConvUtf8Action;
 use crate::op::ConvZhAction;
-use crate::op::ExpAction;
-use crate::op::ImpAction;
+use crate::op::{ExpAction, ExpOptions};
+use crate::op::HistoryAction;
+use crate::op::{ImpAction, ImpOptions};
+use crate::op::JoinValuesAction;
 use crate::op::ModNumAction;
 use crate::op::ModTextConstAction;
 use crate::op::ModTextRegexAction;
+use crate::op::{OrganizeAction, OrganizeOptions};
+use crate::op::PropAction;
 use crate::op::SetConstAction;
+use crate::op::SetExprAction;
 use crate::op::SetNameAction;
 use crate::op::SetSeqAction;
+use crate::op::SplitValuesAction;
 use crate::op::RenAction;
+use crate::op::UndoAction;
 use crate::op::ViewAction;
 
 pub fn direct_format(
@@ -60,6 +74,7 @@ fn init_logger() -> Result<LoggerHandle, Error> {
         )
         .duplicate_to_stdout(Duplicate::Info)
         .format_for_stdout(direct_format)
+        .format_for_stderr(direct_format)
         .write_mode(WriteMode::BufferAndFlush)
         .start()?;
     Ok(logger)
@@ -94,13 +109,89 @@ pub fn entry() -> Result<(), Error> {
 }
 
 fn do_command(logger: &mut LoggerHandle) -> Result<(), Error> {
-    let mut action = get_action(logger)?;
-    action.do_any()
+    let mut app = match App::parse() {
+        App { command: Command::Run(args) } => expand_preset(args)?,
+        app => app,
+    };
+    apply_filters(&mut app.command)?;
+    if let Some(WhereArgs { where_group: Some(where_group), group_by, .. })
+        = app.command.get_where_args() {
+        set_where_group(where_group, *group_by)?;
+    }
+    if app.command.is_journaled() {
+        let run_id = journal::start(&get_command_line())?;
+        debug!("run_id: {}", run_id);
+    }
+
+    let result = get_action(app, logger)
+        .and_then(|mut action| action.do_any());
+    journal::finish();
+    result
+}
+
+/// Arguments of the preset, followed by more arguments of `run`.
+fn expand_preset(args: RunArgs) -> Result<App, Error> {
+    let (preset, location) = get_preset(&args.preset)?;
+    if preset.first().map_or(true, |command| command.eq("run")) {
+        return Err(anyhow!("Preset \"{}\" at {} must start with a command except \"run\".",
+            args.preset, location));
+    }
+
+    let command_line = iter::once(env!("CARGO_PKG_NAME").to_owned())
+        .chain(preset.iter().cloned())
+        .chain(args.args)
+        .collect::<Vec<_>>();
+    debug!("preset: {}, command line: {:?}", args.preset, command_line);
+    App::try_parse_from(command_line)
+        .map_err(|e| anyhow!("Preset \"{}\" at {} is invalid. (error: {})",
+            args.preset, location, e))
 }
 
-fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
-    let app = App::parse();
+/// Named filters of `--filter` are combined with `--where` by `AND`.
+fn apply_filters(command: &mut Command) -> Result<(), Error> {
+    let where_args = match command.get_where_args() {
+        Some(where_args) if !where_args.filter.is_empty() => where_args,
+        _ => return Ok(()),
+    };
+
+    let mut clauses = where_args.where_clause.iter().cloned().collect::<Vec<_>>();
+    for name in &where_args.filter {
+        let (filter, location) = get_filter(name)?;
+        WhereClause::new(filter)
+            .map_err(|e| anyhow!("Filter \"{}\" at {} is invalid. (error: {})",
+                name, location, e))?;
+        clauses.push(filter.to_owned());
+    }
+    let merged = clauses.iter().map(|c| format!("({})", c)).join(" AND ");
+    debug!("where: {}", merged);
+    where_args.where_clause = Some(merged);
+    Ok(())
+}
+
+fn get_command_line() -> String {
+    std::env::args()
+        .map(|arg| if arg.contains(char::is_whitespace) {
+            format!("{:?}", arg)
+        } else {
+            arg
+        })
+        .join(" ")
+}
+
+fn get_action(app: App, logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
     let action: Box<dyn Action> = match app.command {
+        Command::Art(args) => {
+            debug!("args: {:?}", args);
+            if let ArtOperationArgs::List = args.operation {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            } else if args.quiet {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
+            Box::new(ArtAction::new(&args.directory,
+                                    args.dry_run,
+                                    &args.where_args.where_clause,
+                                    args.operation.into())?)
+        }
         Command::Clear(args) => {
             debug!("args: {:?}", args);
             if args.quiet {
@@ -109,7 +200,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ClearAction::new(&args.directory,
                                       args.dry_run,
                                       &args.tags,
-                                      &args.where_clause)?)
+                                      &args.where_args.where_clause)?)
         }
         Command::ConvEn(args) => {
             debug!("args: {:?}", args);
@@ -121,7 +212,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ConvEnAction::new(&args.global_opts.directory,
                                        args.global_opts.dry_run,
                                        &tags,
-                                       &args.global_opts.where_clause,
+                                       &args.global_opts.where_args.where_clause,
                                        &args.profile)?)
         }
         Command::ConvZh(args) => {
@@ -134,7 +225,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ConvZhAction::new(&args.global_opts.directory,
                                        args.global_opts.dry_run,
                                        &tags,
-                                       &args.global_opts.where_clause,
+                                       &args.global_opts.where_args.where_clause,
                                        &args.profile)?)
         }
         Command::ConvUtf8(args) => {
@@ -147,7 +238,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ConvUtf8Action::new(&args.global_opts.directory,
                                          args.global_opts.dry_run,
                                          &tags,
-                                         &args.global_opts.where_clause,
+                                         &args.global_opts.where_args.where_clause,
                                          &args.encoding_name)?)
         }
         Command::ModNum(args) => {
@@ -160,7 +251,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ModNumAction::new(&args.directory,
                                        args.dry_run,
                                        &tags,
-                                       &args.where_clause,
+                                       &args.where_args.where_clause,
                                        &args.calc_method,
                                        args.operand,
                                        args.padding)?)
@@ -175,7 +266,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ModTextConstAction::new(&args.global_opts.directory,
                                              args.global_opts.dry_run,
                                              &tags,
-                                             &args.global_opts.where_clause,
+                                             &args.global_opts.where_args.where_clause,
                                              args.value.into())?)
         }
         Command::ModTextRegex(args) => {
@@ -188,21 +279,60 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(ModTextRegexAction::new(&args.global_opts.directory,
                                              args.global_opts.dry_run,
                                              &tags,
-                                             &args.global_opts.where_clause,
+                                             &args.global_opts.where_args.where_clause,
                                              &args.from,
                                              args.ignore_case,
                                              &args.to)?)
         }
-        Command::Ren(args) => {
+        Command::Prop(args) => {
             debug!("args: {:?}", args);
             if args.quiet {
                 logger.adapt_duplication_to_stdout(Duplicate::Error)?;
             }
+            Box::new(PropAction::new(&args.directory,
+                                     args.dry_run,
+                                     &args.where_args.where_clause,
+                                     args.operation.into())?)
+        }
+        Command::Organize(args) => {
+            debug!("args: {:?}", args);
+            if args.quiet {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
+            Box::new(OrganizeAction::new(&args.directory,
+                                         args.dry_run,
+                                         &args.where_args.where_clause,
+                                         &args.template,
+                                         &args.dest,
+                                         OrganizeOptions {
+                                             copy: args.copy,
+                                             remove_empty_dirs: args.remove_empty_dirs,
+                                             filename_exist_policy: args.filename_exist_policy,
+                                             sanitizer: Sanitizer::new(args.sanitize,
+                                                                       args.max_bytes),
+                                         })?)
+        }
+        Command::Ren(args) => {
+            debug!("args: {:?}", args);
+            if args.quiet || args.preview {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
             Box::new(RenAction::new(&args.directory,
                                     args.dry_run,
-                                    &args.where_clause,
+                                    args.preview,
+                                    &args.where_args.where_clause,
                                     &args.template,
-                                    args.filename_exist_policy)?)
+                                    args.filename_exist_policy,
+                                    Sanitizer::new(args.sanitize, args.max_bytes))?)
+        }
+        Command::Run(args) => {
+            return Err(anyhow!("Preset \"{}\" must be expanded before.", args.preset));
+        }
+        Command::History(args) => {
+            debug!("args: {:?}", args);
+            logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            Box::new(HistoryAction::new(&args.run_id,
+                                        args.limit)?)
         }
         Command::Imp(args) => {
             debug!("args: {:?}", args);
@@ -210,9 +340,31 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
                 logger.adapt_duplication_to_stdout(Duplicate::Error)?;
             }
             Box::new(ImpAction::new(&args.source_file,
+                                    ImpOptions {
+                                        format: args.format,
+                                        delimiter: args.delimiter,
+                                        quote: args.quote,
+                                        encoding_name: args.encoding_name.clone(),
+                                        props_mode: args.props_mode,
+                                        validate_all: args.validate_all,
+                                        continue_on_error: args.continue_on_error,
+                                    },
                                     &args.base_directory,
                                     args.dry_run)?)
         }
+        Command::JoinValues(args) => {
+            debug!("args: {:?}", args);
+            if args.global_opts.quiet {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
+            let tags = args.global_opts.tags.into_iter()
+                .map(|t| t.into()).collect::<Vec<_>>();
+            Box::new(JoinValuesAction::new(&args.global_opts.directory,
+                                           args.global_opts.dry_run,
+                                           &tags,
+                                           &args.global_opts.where_args.where_clause,
+                                           &args.separator)?)
+        }
         Command::Lrc(args) => {
             debug!("args: {:?}", args);
             if args.quiet {
@@ -222,12 +374,12 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
                 LrcDirection::Export => Box::new(LrcExpAction::new(&args.directory,
                                                                    &args.encoding_name,
                                                                    args.dry_run,
-                                                                   &args.where_clause,
+                                                                   &args.where_args.where_clause,
                                                                    args.filename_exist_policy)?),
                 LrcDirection::Import => Box::new(LrcImpAction::new(&args.directory,
                                                                    &args.encoding_name,
                                                                    args.dry_run,
-                                                                   &args.where_clause)?)
+                                                                   &args.where_args.where_clause)?)
             }
         }
         Command::SetConst(args) => {
@@ -238,11 +390,24 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(SetConstAction::new(&args.global_opts.directory,
                                          args.global_opts.dry_run,
                                          &args.global_opts.tags,
-                                         &args.where_clause,
+                                         &args.where_args.where_clause,
                                          args.value.into(),
                                          &args.set_when,
                                          &args.modify_mode)?)
         }
+        Command::SetExpr(args) => {
+            debug!("args: {:?}", args);
+            if args.global_opts.quiet {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
+            Box::new(SetExprAction::new(&args.global_opts.directory,
+                                        args.global_opts.dry_run,
+                                        &args.global_opts.tags,
+                                        &args.where_args.where_clause,
+                                        &args.expression,
+                                        &args.set_when,
+                                        &args.modify_mode)?)
+        }
         Command::SetName(args) => {
             debug!("args: {:?}", args);
             if args.quiet {
@@ -251,7 +416,7 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             Box::new(SetNameAction::new(&args.directory,
                                         args.dry_run,
                                         &args.set_when,
-                                        &args.where_clause,
+                                        &args.where_args.where_clause,
                                         &args.template)?)
         }
         Command::SetSeq(args) => {
@@ -268,13 +433,49 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
                                        &args.hyphen,
                                        &args.modify_mode)?)
         }
+        Command::SplitValues(args) => {
+            debug!("args: {:?}", args);
+            if args.global_opts.quiet {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
+            let tags = args.global_opts.tags.into_iter()
+                .map(|t| t.into()).collect::<Vec<_>>();
+            Box::new(SplitValuesAction::new(&args.global_opts.directory,
+                                            args.global_opts.dry_run,
+                                            &tags,
+                                            &args.global_opts.where_args.where_clause,
+                                            &args.separator,
+                                            args.ignore_case)?)
+        }
+        Command::Undo(args) => {
+            debug!("args: {:?}", args);
+            if args.quiet {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
+            Box::new(UndoAction::new(&args.run_id,
+                                     args.dry_run,
+                                     args.skip_changed)?)
+        }
         Command::Exp(args) => {
             debug!("args: {:?}", args);
-            logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            if is_std_stream(&args.output_file) {
+                // keep stdout for the output only
+                logger.adapt_duplication_to_stdout(Duplicate::None)?;
+                logger.adapt_duplication_to_stderr(Duplicate::Error)?;
+            } else {
+                logger.adapt_duplication_to_stdout(Duplicate::Error)?;
+            }
             Box::new(ExpAction::new(&args.directory,
                                     &args.tags,
-                                    &args.where_clause,
-                                    args.with_properties,
+                                    &args.where_args.where_clause,
+                                    ExpOptions {
+                                        with_properties: args.with_properties,
+                                        with_audio_properties: args.with_audio_properties,
+                                        format: args.format,
+                                        prop_columns: args.prop_columns.clone(),
+                                        delimiter: args.delimiter,
+                                        quote: args.quote,
+                                    },
                                     &args.output_file,
                                     args.filename_exist_policy)?)
         }
@@ -283,8 +484,9 @@ fn get_action(logger: &mut LoggerHandle) -> Result<Box<dyn Action>, Error> {
             logger.adapt_duplication_to_stdout(Duplicate::Error)?;
             Box::new(ViewAction::new(&args.directory,
                                      &args.tags,
-                                     &args.where_clause,
-                                     args.with_properties)?)
+                                     &args.where_args.where_clause,
+                                     args.with_properties,
+                                     args.with_audio_properties)?)
         }
     };
     Ok(action)
@@ -1,19 +1,39 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
 
+use anyhow::{anyhow, Error};
 use homedir::get_my_home;
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use toml::Spanned;
 
 lazy_static! {
-    static ref CONFIG: Config = toml::from_str(
-            &get_toml_content().unwrap_or_else(|| "".to_owned()))
+    static ref CONFIG_CONTENT: String = get_toml_content().unwrap_or_else(|| "".to_owned());
+    static ref CONFIG: Config = toml::from_str(&CONFIG_CONTENT)
         .unwrap_or_else(|_| Config::default());
 }
 
+const DEFAULT_MULTI_VALUE_SEPARATOR: &str = "; ";
+
+const DEFAULT_SPLIT_SEPARATORS: [&str; 4] = [
+    r"\s*;\s*",
+    r"\s+/\s+",
+    r"\s+&\s+",
+    r"\s+(?i:feat\.?|ft\.)\s+",
+];
+
 #[derive(Deserialize)]
 struct Config {
     log_level: Option<String>,
     tag_lib: Option<String>,
+    multi_value_separator: Option<String>,
+    split_separators: Option<Vec<String>>,
+    journal_dir: Option<String>,
+    filters: Option<BTreeMap<String, Spanned<String>>>,
+    presets: Option<BTreeMap<String, Spanned<Vec<String>>>>,
 }
 
 impl Default for Config {
@@ -21,13 +41,22 @@ impl Default for Config {
         Config {
             log_level: None,
             tag_lib: None,
+            multi_value_separator: None,
+            split_separators: None,
+            journal_dir: None,
+            filters: None,
+            presets: None,
         }
     }
 }
 
-fn get_toml_content() -> Option<String> {
+fn get_toml_path() -> PathBuf {
     let home = get_my_home().unwrap().unwrap().to_path_buf();
-    let toml_path = home.join(".music-tag-cli.toml");
+    home.join(".music-tag-cli.toml")
+}
+
+fn get_toml_content() -> Option<String> {
+    let toml_path = get_toml_path();
     if toml_path.exists() && toml_path.is_file() {
         fs::read_to_string(toml_path)
             .map_or_else(|_| None, |s| Some(s))
@@ -43,3 +72,67 @@ pub fn get_log_level() -> &'static Option<String> {
 pub fn get_tag_lab() -> &'static Option<String> {
     &CONFIG.tag_lib
 }
+
+/// Separator to join multiple values, for the formats which only hold a single string.
+pub fn get_multi_value_separator() -> &'static str {
+    CONFIG.multi_value_separator.as_deref()
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_MULTI_VALUE_SEPARATOR)
+}
+
+/// REGEX patterns to split a text tag into multiple values.
+pub fn get_split_separators() -> Vec<String> {
+    match &CONFIG.split_separators {
+        Some(separators) if !separators.is_empty() => separators.clone(),
+        _ => DEFAULT_SPLIT_SEPARATORS.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// Directory of the journal files, default is `~/.music-tag-cli/journal`.
+pub fn get_journal_dir() -> Result<PathBuf, Error> {
+    match &CONFIG.journal_dir {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => get_my_home()
+            .ok()
+            .flatten()
+            .map(|home| home.join(".music-tag-cli").join("journal"))
+            .ok_or(anyhow!("Could NOT get home directory for journal.")),
+    }
+}
+
+/// `--where` clause of a named filter in `[filters]`, and where it is in configuration file.
+pub fn get_filter(name: &str) -> Result<(&'static str, String), Error> {
+    let filters = get_section(&CONFIG.filters, "filters")?;
+    filters.get(name)
+        .map(|f| (f.get_ref().as_str(), get_location(f.span())))
+        .ok_or_else(|| anyhow!("Filter \"{}\" is NOT found in [filters] of {:?}, \
+            available filters: {}.", name, get_toml_path(), filters.keys().join(", ")))
+}
+
+/// Arguments of a named preset in `[presets]`, and where it is in configuration file.
+pub fn get_preset(name: &str) -> Result<(&'static [String], String), Error> {
+    let presets = get_section(&CONFIG.presets, "presets")?;
+    presets.get(name)
+        .map(|p| (p.get_ref().as_slice(), get_location(p.span())))
+        .ok_or_else(|| anyhow!("Preset \"{}\" is NOT found in [presets] of {:?}, \
+            available presets: {}.", name, get_toml_path(), presets.keys().join(", ")))
+}
+
+fn get_section<T>(section: &'static Option<BTreeMap<String, T>>,
+                  section_name: &str) -> Result<&'static BTreeMap<String, T>, Error> {
+    // the configuration file is ignored silently if it is invalid, but not for named sections
+    if let Err(e) = toml::from_str::<Config>(&CONFIG_CONTENT) {
+        return Err(anyhow!("Configuration file {:?} is invalid. (error: {})", get_toml_path(), e));
+    }
+    section.as_ref()
+        .ok_or_else(|| anyhow!("There is no [{}] in configuration file {:?}.",
+            section_name, get_toml_path()))
+}
+
+/// `path:line:column` of the span in configuration file.
+fn get_location(span: Range<usize>) -> String {
+    let before = &CONFIG_CONTENT[..span.start];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |s| s.chars().count()) + 1;
+    format!("{}:{}:{}", get_toml_path().display(), line, column)
+}
@@ -0,0 +1,719 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use titlecase::titlecase;
+
+use crate::config::get_multi_value_separator;
+use crate::model::MyTag;
+use crate::op::ReadTag;
+
+/// A template like `${track-number:02}. ${upper(title)}[ - ${artist}]`, the text outside `${ }`
+/// is kept as is, each `${ }` holds a field which is evaluated per file. A section in `[ ]` is
+/// dropped if none of the fields in it has a value. `\[` `\]` `\$` are escaped characters.
+/// `/` separates the components of a path, the value of a field never adds a component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Field),
+    Section(Vec<Part>),
+    Separator,
+}
+
+/// `${album-artist|artist:02}`, the first NOT empty alternative, padded to the width.
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    alternatives: Vec<Expr>,
+    width: Option<(usize, char)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Str(String),
+    Num(usize),
+    Tag(&'static MyTag),
+    Prop(String),
+    Path(PathField),
+    Func(Func, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathField {
+    /// File name with extension.
+    Filename,
+    /// File name without extension.
+    Stem,
+    Ext,
+    /// Name of the directory which contains the file.
+    Dir,
+    /// Name of the parent directory of `Dir`.
+    ParentDir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    Upper,
+    Lower,
+    Trim,
+    Caps,
+    Left,
+    Right,
+    Pad,
+    Replace,
+    If,
+    Coalesce,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "upper" => Some(Func::Upper),
+            "lower" => Some(Func::Lower),
+            "trim" => Some(Func::Trim),
+            "caps" => Some(Func::Caps),
+            "left" => Some(Func::Left),
+            "right" => Some(Func::Right),
+            "pad" => Some(Func::Pad),
+            "replace" => Some(Func::Replace),
+            "if" => Some(Func::If),
+            "coalesce" => Some(Func::Coalesce),
+            _ => None,
+        }
+    }
+
+    /// Min and max count of arguments.
+    fn get_arity(&self) -> (usize, usize) {
+        match self {
+            Func::Upper | Func::Lower | Func::Trim | Func::Caps => (1, 1),
+            Func::Left | Func::Right => (2, 2),
+            Func::Pad => (2, 3),
+            Func::Replace => (3, 3),
+            Func::If => (2, 3),
+            Func::Coalesce => (1, usize::MAX),
+        }
+    }
+}
+
+impl PathField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "filename" => Some(PathField::Filename),
+            "stem" => Some(PathField::Stem),
+            "ext" => Some(PathField::Ext),
+            "dir" => Some(PathField::Dir),
+            "parent-dir" => Some(PathField::ParentDir),
+            _ => None,
+        }
+    }
+
+    fn get_value(&self, path: &Path) -> String {
+        let value = match self {
+            PathField::Filename => path.file_name(),
+            PathField::Stem => path.file_stem(),
+            PathField::Ext => path.extension(),
+            PathField::Dir => path.parent().and_then(|p| p.file_name()),
+            PathField::ParentDir => path.parent()
+                .and_then(|p| p.parent())
+                .and_then(|p| p.file_name()),
+        };
+        value.map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+    }
+}
+
+impl Template {
+    pub fn new(input: &str) -> Result<Self, Error> {
+        Parser::new(input).parse_template()
+            .map_err(|(pos, msg)| anyhow!("Template error at position {}: {}. (template: \"{}\")",
+                pos, msg, input))
+    }
+
+    /// All tags referenced by the template, without duplicates.
+    pub fn get_tags(&self) -> Vec<&'static MyTag> {
+        let mut result = Vec::new();
+        collect_tags(&self.parts, &mut result);
+        result
+    }
+
+    pub fn has_field(&self) -> bool {
+        has_field(&self.parts)
+    }
+
+    pub fn eval(&self, t: &dyn ReadTag) -> Result<String, Error> {
+        self.eval_path(t).map(|(components, _)| components.join(SEPARATOR))
+    }
+
+    /// Same as `eval`, but it is `None` if none of the fields has a value.
+    pub fn eval_any(&self, t: &dyn ReadTag) -> Result<Option<String>, Error> {
+        self.eval_path(t).map(|(components, any)| if any {
+            Some(components.join(SEPARATOR))
+        } else {
+            None
+        })
+    }
+
+    /// Components of the path, and whether any field has a value.
+    pub fn eval_path(&self, t: &dyn ReadTag) -> Result<(Vec<String>, bool), Error> {
+        let mut components = vec![String::new()];
+        let any = eval_parts(&self.parts, t, &mut components)?;
+        Ok((components, any))
+    }
+}
+
+const SEPARATOR: &str = "/";
+
+fn collect_tags(parts: &[Part], result: &mut Vec<&'static MyTag>) {
+    for part in parts {
+        match part {
+            Part::Text(_) | Part::Separator => (),
+            Part::Field(f) => f.alternatives.iter().for_each(|e| e.collect_tags(result)),
+            Part::Section(inner) => collect_tags(inner, result),
+        }
+    }
+}
+
+fn has_field(parts: &[Part]) -> bool {
+    parts.iter().any(|p| match p {
+        Part::Text(_) | Part::Separator => false,
+        Part::Field(_) => true,
+        Part::Section(inner) => has_field(inner),
+    })
+}
+
+/// Append the value to the last one of `components`, return whether any field has a value.
+fn eval_parts(parts: &[Part],
+              t: &dyn ReadTag,
+              components: &mut Vec<String>) -> Result<bool, Error> {
+    let mut any = false;
+    for part in parts {
+        match part {
+            Part::Text(s) => push_str(components, s),
+            Part::Field(f) => {
+                let value = f.eval(t)?;
+                any = any || !value.is_empty();
+                push_str(components, &value);
+            }
+            Part::Section(inner) => {
+                let mut inner_components = vec![String::new()];
+                if eval_parts(inner, t, &mut inner_components)? {
+                    any = true;
+                    let mut it = inner_components.into_iter();
+                    if let Some(first) = it.next() {
+                        push_str(components, &first);
+                    }
+                    components.extend(it);
+                }
+            }
+            Part::Separator => components.push(String::new()),
+        }
+    }
+    Ok(any)
+}
+
+fn push_str(components: &mut [String], s: &str) {
+    if let Some(last) = components.last_mut() {
+        last.push_str(s);
+    }
+}
+
+impl Field {
+    fn eval(&self, t: &dyn ReadTag) -> Result<String, Error> {
+        for e in &self.alternatives {
+            let value = e.eval(t)?;
+            if !value.is_empty() {
+                return Ok(match self.width {
+                    Some((width, fill)) => pad(&value, width, fill),
+                    None => value,
+                });
+            }
+        }
+        Ok(String::new())
+    }
+}
+
+impl Expr {
+    fn collect_tags(&self, result: &mut Vec<&'static MyTag>) {
+        match self {
+            Expr::Tag(tag) if !result.contains(tag) => result.push(tag),
+            Expr::Func(_, args) => args.iter().for_each(|a| a.collect_tags(result)),
+            _ => (),
+        }
+    }
+
+    pub fn eval(&self, t: &dyn ReadTag) -> Result<String, Error> {
+        match self {
+            Expr::Str(s) => Ok(s.to_owned()),
+            Expr::Num(n) => Ok(n.to_string()),
+            Expr::Tag(tag) => Ok(get_tag_text(t, tag)),
+            Expr::Prop(key) => Ok(t.get_property(key)
+                .map(|values| values.join(get_multi_value_separator()))
+                .unwrap_or_default()),
+            Expr::Path(field) => Ok(field.get_value(t.get_path())),
+            Expr::Func(func, args) => eval_func(func, args, t),
+        }
+    }
+}
+
+fn get_tag_text(t: &dyn ReadTag, tag: &MyTag) -> String {
+    if tag.is_numeric() {
+        t.get_numeric_tag_string(tag)
+    } else if tag.is_text() {
+        let values = t.get_text_tag_values(tag);
+        if values.len() > 1 {
+            Some(values.join(get_multi_value_separator()))
+        } else {
+            t.get_text_tag(tag)
+        }
+    } else {
+        t.get_text_tag(tag)
+    }.unwrap_or_default()
+}
+
+fn eval_func(func: &Func, args: &[Expr], t: &dyn ReadTag) -> Result<String, Error> {
+    match func {
+        Func::If => {
+            if !args[0].eval(t)?.is_empty() {
+                args[1].eval(t)
+            } else {
+                args.get(2).map_or_else(|| Ok(String::new()), |e| e.eval(t))
+            }
+        }
+        Func::Coalesce => {
+            for arg in args {
+                let value = arg.eval(t)?;
+                if !value.is_empty() {
+                    return Ok(value);
+                }
+            }
+            Ok(String::new())
+        }
+        _ => {
+            let values = args.iter()
+                .map(|a| a.eval(t))
+                .collect::<Result<Vec<_>, Error>>()?;
+            apply_func(func, &values)
+        }
+    }
+}
+
+fn apply_func(func: &Func, values: &[String]) -> Result<String, Error> {
+    let s = &values[0];
+    match func {
+        Func::Upper => Ok(s.to_uppercase()),
+        Func::Lower => Ok(s.to_lowercase()),
+        Func::Trim => Ok(s.trim().to_owned()),
+        Func::Caps => Ok(titlecase(s)),
+        Func::Left => {
+            let n = get_count(func, &values[1])?;
+            Ok(s.chars().take(n).collect())
+        }
+        Func::Right => {
+            let n = get_count(func, &values[1])?;
+            let len = s.chars().count();
+            Ok(s.chars().skip(len.saturating_sub(n)).collect())
+        }
+        Func::Pad => {
+            let n = get_count(func, &values[1])?;
+            let fill = values.get(2)
+                .and_then(|f| f.chars().next())
+                .unwrap_or('0');
+            Ok(pad(s, n, fill))
+        }
+        Func::Replace => Ok(s.replace(&values[1], &values[2])),
+        Func::If | Func::Coalesce => unreachable!(),
+    }
+}
+
+fn pad(s: &str, width: usize, fill: char) -> String {
+    let len = s.chars().count();
+    std::iter::repeat(fill).take(width.saturating_sub(len)).collect::<String>() + s
+}
+
+fn get_count(func: &Func, value: &str) -> Result<usize, Error> {
+    value.parse::<usize>()
+        .map_err(|_| anyhow!("{}() needs a count, but got \"{}\".",
+            format!("{:?}", func).to_lowercase(), value))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+impl Parser {
+    fn new(input: &str) -> Self {
+        Self { chars: input.chars().collect(), pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ParseResult<()> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err((self.pos, format!("expect '{}'", c)))
+        }
+    }
+
+    fn parse_template(&mut self) -> ParseResult<Template> {
+        let parts = self.parse_parts(None)?;
+        Ok(Template { parts })
+    }
+
+    /// Parts until the end, or until `]` if it is in a section started at `section_start`.
+    fn parse_parts(&mut self, section_start: Option<usize>) -> ParseResult<Vec<Part>> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            let next = self.chars.get(self.pos + 1).copied();
+            match c {
+                '\\' if matches!(next, Some('[') | Some(']') | Some('$') | Some('\\')) => {
+                    text.push(next.unwrap());
+                    self.pos += 2;
+                }
+                '$' if next == Some('{') => {
+                    push_text(&mut parts, &mut text);
+                    self.pos += 2;
+                    parts.push(Part::Field(self.parse_field()?));
+                }
+                '/' => {
+                    push_text(&mut parts, &mut text);
+                    parts.push(Part::Separator);
+                    self.pos += 1;
+                }
+                '[' => {
+                    push_text(&mut parts, &mut text);
+                    let start = self.pos;
+                    self.pos += 1;
+                    let section = self.parse_parts(Some(start))?;
+                    if section.iter().any(|p| matches!(p, Part::Field(_) | Part::Section(_))) {
+                        parts.push(Part::Section(section));
+                    } else {
+                        // without any field, e.g. "[Live]", it is the literal text
+                        text.push('[');
+                        for part in section {
+                            match part {
+                                Part::Text(s) => text.push_str(&s),
+                                _ => {
+                                    push_text(&mut parts, &mut text);
+                                    parts.push(part);
+                                }
+                            }
+                        }
+                        text.push(']');
+                    }
+                }
+                ']' if section_start.is_some() => {
+                    self.pos += 1;
+                    push_text(&mut parts, &mut text);
+                    return Ok(parts);
+                }
+                ']' => return Err((self.pos, "']' without '[', it could be escaped as '\\]'"
+                    .to_owned())),
+                _ => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        match section_start {
+            Some(start) => Err((start, "'[' is not closed, it could be escaped as '\\['".to_owned())),
+            None => {
+                push_text(&mut parts, &mut text);
+                Ok(parts)
+            }
+        }
+    }
+
+    /// `expr ( '|' expr )* ( ':' width )? '}'`, `width` with a leading `0` pads with `0`.
+    fn parse_field(&mut self) -> ParseResult<Field> {
+        let mut alternatives = vec![self.parse_expr()?];
+        self.skip_whitespace();
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.parse_expr()?);
+            self.skip_whitespace();
+        }
+
+        let mut width = None;
+        if self.peek() == Some(':') {
+            self.pos += 1;
+            self.skip_whitespace();
+            let fill = if self.peek() == Some('0') { '0' } else { ' ' };
+            match self.peek() {
+                Some(c) if c.is_ascii_digit() => width = Some((self.parse_num()?, fill)),
+                _ => return Err((self.pos, "expect a width after ':'".to_owned())),
+            }
+        }
+        self.expect('}')?;
+        Ok(Field { alternatives, width })
+    }
+
+    fn parse_expr(&mut self) -> ParseResult<Expr> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek() {
+            Some('\'') => self.parse_str().map(Expr::Str),
+            Some(c) if c.is_ascii_digit() => self.parse_num().map(Expr::Num),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.parse_ident();
+                self.skip_whitespace();
+                if self.peek() == Some('(') {
+                    self.pos += 1;
+                    self.parse_call(start, &name)
+                } else if let Some(field) = PathField::from_name(&name) {
+                    Ok(Expr::Path(field))
+                } else {
+                    MyTag::from_str(&name)
+                        .map(Expr::Tag)
+                        .map_err(|_| (start, format!("\"{}\" is not a known tag or field", name)))
+                }
+            }
+            Some(c) => Err((start, format!("unexpected '{}'", c))),
+            None => Err((start, "unexpected end".to_owned())),
+        }
+    }
+
+    fn parse_call(&mut self, start: usize, name: &str) -> ParseResult<Expr> {
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(')') {
+            self.pos += 1;
+        } else {
+            loop {
+                args.push(self.parse_expr()?);
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(',') => self.pos += 1,
+                    Some(')') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err((self.pos, "expect ',' or ')'".to_owned())),
+                }
+            }
+        }
+
+        if name == "prop" {
+            return match args.as_slice() {
+                [Expr::Str(key)] => Ok(Expr::Prop(key.to_owned())),
+                _ => Err((start, "prop() needs one quoted key".to_owned())),
+            };
+        }
+
+        let func = Func::from_name(name)
+            .ok_or((start, format!("\"{}\" is not a known function", name)))?;
+        let (min, max) = func.get_arity();
+        if args.len() < min || args.len() > max {
+            return Err((start, format!("wrong count of arguments for {}()", name)));
+        }
+        Ok(Expr::Func(func, args))
+    }
+
+    fn parse_ident(&mut self) -> String {
+        let mut result = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                result.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        result
+    }
+
+    fn parse_num(&mut self) -> ParseResult<usize> {
+        let start = self.pos;
+        let mut s = String::new();
+        while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+            s.push(c);
+            self.pos += 1;
+        }
+        s.parse::<usize>().map_err(|e| (start, e.to_string()))
+    }
+
+    /// String in single quotes, `'` should be escaped as `''` like in `where` clause.
+    fn parse_str(&mut self) -> ParseResult<String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                Some('\'') if self.chars.get(self.pos + 1) == Some(&'\'') => {
+                    result.push('\'');
+                    self.pos += 2;
+                }
+                Some('\'') => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some(c) => {
+                    result.push(c);
+                    self.pos += 1;
+                }
+                None => return Err((start, "string is not closed".to_owned())),
+            }
+        }
+    }
+}
+
+fn push_text(parts: &mut Vec<Part>, text: &mut String) {
+    if !text.is_empty() {
+        parts.push(Part::Text(std::mem::take(text)));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use anyhow::{anyhow, Error};
+
+    use crate::model::{AudioProperty, MyPicture, MyTag};
+    use crate::op::ReadTag;
+
+    use super::Template;
+
+    struct MockTagImpl {
+        path: PathBuf,
+    }
+
+    impl ReadTag for MockTagImpl {
+        fn get_path(&self) -> &Path {
+            &self.path
+        }
+
+        fn get_text_tag(&self, key: &MyTag) -> Option<String> {
+            match key {
+                MyTag::Title => Some("  Take Me Home ".to_owned()),
+                MyTag::Artist => Some("John Denver".to_owned()),
+                MyTag::Genre => Some("Pop".to_owned()),
+                _ => None,
+            }
+        }
+
+        fn get_text_tag_values(&self, key: &MyTag) -> Vec<String> {
+            match key {
+                MyTag::Genre => vec!["Pop".to_owned(), "Folk".to_owned()],
+                _ => self.get_text_tag(key).into_iter().collect(),
+            }
+        }
+
+        fn get_numeric_tag(&self, key: &MyTag) -> Option<u32> {
+            self.get_numeric_tag_string(key).and_then(|s| s.parse().ok())
+        }
+
+        fn get_numeric_tag_string(&self, key: &MyTag) -> Option<String> {
+            match key {
+                MyTag::TrackNumber => Some("3".to_owned()),
+                _ => None,
+            }
+        }
+
+        fn get_property_keys(&self) -> Result<Vec<String>, Error> {
+            Ok(vec!["MOOD".to_owned()])
+        }
+
+        fn get_property(&self, key: &str) -> Result<Vec<String>, Error> {
+            match key {
+                "MOOD" => Ok(vec!["Calm".to_owned()]),
+                _ => Err(anyhow!("No property")),
+            }
+        }
+
+        fn get_pictures(&self) -> Result<Vec<MyPicture>, Error> {
+            Ok(vec![])
+        }
+
+        fn get_audio_property(&self, _key: &AudioProperty) -> Option<u32> {
+            None
+        }
+    }
+
+    fn eval(template: &str) -> String {
+        let mock = MockTagImpl { path: PathBuf::from("/music/John Denver/Poems/03 take.flac") };
+        Template::new(template).unwrap().eval(&mock).unwrap()
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval("${pad(track-number, 2)}. ${trim(title)}"), "03. Take Me Home");
+        assert_eq!(eval("${upper(artist)} - ${lower(left(trim(title), 4))}"), "JOHN DENVER - take");
+        assert_eq!(eval("${right(artist, 6)}|${pad(track-number, 3, '-')}"), "Denver|--3");
+        assert_eq!(eval("${album-artist}"), "");
+        assert_eq!(eval("${coalesce(album-artist, composer, artist)}"), "John Denver");
+        assert_eq!(eval("${if(album-artist, 'VA', 'Single')}"), "Single");
+        assert_eq!(eval("${if(artist, 'It''s ')}${genre}"), "It's Pop; Folk");
+        assert_eq!(eval("${replace(artist, ' ', '_')}"), "John_Denver");
+        assert_eq!(eval("${prop('MOOD')}${prop('NONE')}"), "Calm");
+        assert_eq!(eval("${parent-dir}/${dir}/${stem}.${ext}"), "John Denver/Poems/03 take.flac");
+        assert_eq!(eval("${filename}"), "03 take.flac");
+        assert_eq!(eval("no expression"), "no expression");
+        assert_eq!(eval("${caps(lower(artist))}"), "John Denver");
+    }
+
+    #[test]
+    fn test_eval_field() {
+        assert_eq!(eval("${track-number:02}. ${album-artist|composer|artist}"), "03. John Denver");
+        assert_eq!(eval("[${track-number:03}. ]${artist}[ - ${album-title}]"), "003. John Denver");
+        assert_eq!(eval("${artist:13}|${album-title:3}"), "  John Denver|");
+        assert_eq!(eval("[${album-title}[ (${year})]]${artist}"), "John Denver");
+        assert_eq!(eval("[${artist}[ (${year})]]"), "John Denver");
+        assert_eq!(eval("\\[${track-number}\\] \\${artist}"), "[3] ${artist}");
+        assert_eq!(eval("${artist} [Live]"), "John Denver [Live]");
+        assert_eq!(eval("[${album-title} ]${artist}[ [Live]]"), "John Denver[ [Live]]");
+
+        let mock = MockTagImpl { path: PathBuf::from("a.flac") };
+        let t = Template::new("${album-title} - [${year}]").unwrap();
+        assert_eq!(t.eval_any(&mock).unwrap(), None);
+        assert_eq!(t.eval(&mock).unwrap(), " - ");
+    }
+
+    #[test]
+    fn test_eval_path() {
+        let mock = MockTagImpl { path: PathBuf::from("/music/John Denver/Poems/03 take.flac") };
+        let t = Template::new("${artist}/[${year} - ]${dir}/[Disc ${disc-number}/]\
+        ${track-number:02} - ${replace(artist, ' ', '/')}").unwrap();
+        let (components, any) = t.eval_path(&mock).unwrap();
+        assert!(any);
+        assert_eq!(components, vec!["John Denver", "Poems", "03 - John/Denver"]);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(Template::new("${unknown-tag}").is_err());
+        assert!(Template::new("${upper(title, artist)}").is_err());
+        assert!(Template::new("${title").is_err());
+        assert!(Template::new("${'abc}").is_err());
+        assert!(Template::new("${prop(title)}").is_err());
+        assert!(Template::new("${foo(title)}").is_err());
+        assert!(Template::new("[${title}").is_err());
+        assert!(Template::new("${title}]").is_err());
+        assert!(Template::new("${track-number:}").is_err());
+    }
+
+    #[test]
+    fn test_get_tags() {
+        let t = Template::new("${album-artist|artist} - [${title} ${artist}]${stem}").unwrap();
+        assert_eq!(t.get_tags(), vec![&MyTag::AlbumArtist, &MyTag::Artist, &MyTag::Title]);
+        assert!(t.has_field());
+        assert!(!Template::new("abc").unwrap().has_field());
+    }
+}
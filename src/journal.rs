@@ -0,0 +1,393 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{anyhow, Error};
+use chrono::Local;
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_journal_dir;
+use crate::model::{MyPicture, PictureType};
+
+const JOURNAL_EXT: &str = "jsonl";
+
+lazy_static! {
+    static ref JOURNAL: Mutex<Option<Journal>> = Mutex::new(None);
+}
+
+/// One line of the journal file, the first line is always `Start`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum JournalEntry {
+    Start {
+        run_id: String,
+        time: String,
+        command: String,
+    },
+    /// Values of numeric tags are kept as string, so the padding could be restored.
+    Tag {
+        path: PathBuf,
+        tag: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    Prop {
+        path: PathBuf,
+        key: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    /// Data of the old pictures is kept in files of directory `<run_id>` next to the journal,
+    /// only types of the new pictures are recorded.
+    Pictures {
+        path: PathBuf,
+        old: Vec<JournalPicture>,
+        new: Vec<String>,
+    },
+    /// State of the file after it was saved, to check whether it was changed later.
+    Saved {
+        path: PathBuf,
+        size: u64,
+        mtime: u64,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Undone {
+        run_id: String,
+        time: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalPicture {
+    pub picture_type: String,
+    pub mime_type: String,
+    pub description: String,
+    /// File name in the pictures directory of the run.
+    pub file: String,
+}
+
+struct Journal {
+    run_id: String,
+    path: PathBuf,
+    writer: LineWriter<File>,
+    count: usize,
+    picture_count: usize,
+}
+
+/// Start the journal of current run, all changes will be recorded until `finish`.
+pub fn start(command: &str) -> Result<String, Error> {
+    let dir = get_journal_dir()?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| anyhow!("Could NOT create journal directory {:?}. (error: {})", dir, e))?;
+
+    let now = Local::now();
+    let run_id = now.format("%Y%m%d-%H%M%S-%3f").to_string();
+    let path = get_journal_path(&dir, &run_id);
+    let f = OpenOptions::new().create_new(true).write(true).open(&path)
+        .map_err(|e| anyhow!("Could NOT create journal {:?}. (error: {})", path, e))?;
+
+    let mut journal = Journal {
+        run_id: run_id.clone(),
+        path,
+        writer: LineWriter::new(f),
+        count: 0,
+        picture_count: 0,
+    };
+    write_entry(&mut journal.writer, &JournalEntry::Start {
+        run_id: run_id.clone(),
+        time: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        command: command.to_owned(),
+    })?;
+    debug!("journal: {:?}", &journal.path);
+
+    *JOURNAL.lock().unwrap() = Some(journal);
+    Ok(run_id)
+}
+
+/// Stop recording, the journal file will be removed if nothing was changed.
+pub fn finish() {
+    if let Some(journal) = JOURNAL.lock().unwrap().take() {
+        if journal.count == 0 {
+            let _ = fs::remove_file(&journal.path);
+            let _ = fs::remove_dir_all(get_pictures_dir(&journal.path));
+        }
+    }
+}
+
+pub fn is_recording() -> bool {
+    JOURNAL.lock().unwrap().is_some()
+}
+
+pub fn get_run_id() -> Option<String> {
+    JOURNAL.lock().unwrap().as_ref().map(|j| j.run_id.clone())
+}
+
+pub fn record_tag(path: &Path, tag: &str, old: Vec<String>, new: Vec<String>) {
+    if old != new {
+        record(JournalEntry::Tag { path: to_absolute(path), tag: tag.to_owned(), old, new });
+    }
+}
+
+pub fn record_prop(path: &Path, key: &str, old: Vec<String>, new: Vec<String>) {
+    if old != new {
+        record(JournalEntry::Prop { path: to_absolute(path), key: key.to_owned(), old, new });
+    }
+}
+
+pub fn record_pictures(path: &Path, old: &[MyPicture], new: &[MyPicture]) {
+    if old == new {
+        return;
+    }
+
+    let saved = match JOURNAL.lock().unwrap().as_mut() {
+        Some(journal) => {
+            let dir = get_pictures_dir(&journal.path);
+            save_pictures(&dir, &mut journal.picture_count, old)
+        }
+        None => return,
+    };
+    match saved {
+        Ok(old) => record(JournalEntry::Pictures {
+            path: to_absolute(path),
+            old,
+            new: new.iter().map(|p| p.picture_type.to_string()).collect(),
+        }),
+        Err(e) => warn!("Could NOT save pictures of file {:?} into journal. (error: {})", path, e),
+    }
+}
+
+pub fn record_saved(path: &Path) {
+    if !is_recording() {
+        return;
+    }
+
+    match get_file_state(path) {
+        Ok((size, mtime)) => record(JournalEntry::Saved { path: to_absolute(path), size, mtime }),
+        Err(e) => warn!("Could NOT get state of file {:?}. (error: {})", path, e),
+    }
+}
+
+pub fn record_rename(from: &Path, to: &Path) {
+    record(JournalEntry::Rename { from: to_absolute(from), to: to_absolute(to) });
+}
+
+fn record(entry: JournalEntry) {
+    if let Some(journal) = JOURNAL.lock().unwrap().as_mut() {
+        match write_entry(&mut journal.writer, &entry) {
+            Ok(_) => journal.count += 1,
+            Err(e) => warn!("Could NOT write journal {:?}. (error: {})", &journal.path, e),
+        }
+    }
+}
+
+fn write_entry<W: Write>(writer: &mut W, entry: &JournalEntry) -> Result<(), Error> {
+    writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Size and modified time (in milliseconds) of the file.
+pub fn get_file_state(path: &Path) -> Result<(u64, u64), Error> {
+    let metadata = fs::metadata(path)?;
+    let mtime = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_millis() as u64;
+    Ok((metadata.len(), mtime))
+}
+
+fn to_absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|d| d.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+fn get_journal_path(dir: &Path, run_id: &str) -> PathBuf {
+    dir.join(format!("{}.{}", run_id, JOURNAL_EXT))
+}
+
+/// Directory of picture files, e.g. `20240601-120000-000/` next to `20240601-120000-000.jsonl`.
+fn get_pictures_dir(journal_path: &Path) -> PathBuf {
+    journal_path.with_extension("")
+}
+
+/// Save data of each picture into a file named by `count`, which is increased.
+fn save_pictures(dir: &Path,
+                 count: &mut usize,
+                 pictures: &[MyPicture]) -> Result<Vec<JournalPicture>, Error> {
+    fs::create_dir_all(dir)?;
+    let mut result = Vec::new();
+    for picture in pictures {
+        *count += 1;
+        let file = format!("{}.bin", count);
+        fs::write(dir.join(&file), &picture.data)?;
+        result.push(JournalPicture {
+            picture_type: picture.picture_type.to_string(),
+            mime_type: picture.mime_type.clone(),
+            description: picture.description.clone(),
+            file,
+        });
+    }
+    Ok(result)
+}
+
+fn load_pictures(dir: &Path, pictures: &[JournalPicture]) -> Result<Vec<MyPicture>, Error> {
+    pictures.iter()
+        .map(|p| {
+            let picture_type = PictureType::from_str(&p.picture_type)
+                .ok_or(anyhow!("unknown picture type: {}", p.picture_type))?;
+            let path = dir.join(&p.file);
+            let data = fs::read(&path)
+                .map_err(|e| anyhow!("Could NOT read picture {:?}. (error: {})", path, e))?;
+            Ok(MyPicture {
+                picture_type,
+                mime_type: p.mime_type.clone(),
+                description: p.description.clone(),
+                data,
+            })
+        })
+        .collect()
+}
+
+/// Ids of all runs in journal directory, the oldest is the first.
+pub fn get_run_ids() -> Result<Vec<String>, Error> {
+    let dir = get_journal_dir()?;
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut run_ids = fs::read_dir(&dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file()
+            && p.extension().is_some_and(|ext| ext == JOURNAL_EXT))
+        .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect::<Vec<_>>();
+    run_ids.sort();
+    Ok(run_ids)
+}
+
+pub fn read_entries(run_id: &str) -> Result<Vec<JournalEntry>, Error> {
+    let path = get_journal_path(&get_journal_dir()?, run_id);
+    if !path.is_file() {
+        return Err(anyhow!("Run {} NOT found in journal.", run_id));
+    }
+
+    let reader = BufReader::new(File::open(&path)?);
+    parse_entries(reader)
+        .map_err(|e| anyhow!("Journal {:?} is broken. (error: {})", path, e))
+}
+
+fn parse_entries<R: BufRead>(reader: R) -> Result<Vec<JournalEntry>, Error> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+/// Old pictures of run `run_id`, with data read from the pictures directory.
+pub fn read_pictures(run_id: &str, pictures: &[JournalPicture]) -> Result<Vec<MyPicture>, Error> {
+    let path = get_journal_path(&get_journal_dir()?, run_id);
+    load_pictures(&get_pictures_dir(&path), pictures)
+}
+
+/// Mark run `run_id` as undone by run `by_run_id`.
+pub fn mark_undone(run_id: &str, by_run_id: &str) -> Result<(), Error> {
+    let path = get_journal_path(&get_journal_dir()?, run_id);
+    let mut f = OpenOptions::new().append(true).open(&path)?;
+    write_entry(&mut f, &JournalEntry::Undone {
+        run_id: by_run_id.to_owned(),
+        time: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use crate::model::{MyPicture, PictureType};
+
+    use super::{JournalEntry, JournalPicture, load_pictures, parse_entries, save_pictures,
+                write_entry};
+
+    #[test]
+    fn test_entries() {
+        let entries = vec![
+            JournalEntry::Start {
+                run_id: "20240601-120000-000".to_owned(),
+                time: "2024-06-01 12:00:00".to_owned(),
+                command: "music-tag-cli clear -t comment .".to_owned(),
+            },
+            JournalEntry::Tag {
+                path: PathBuf::from("/music/a.flac"),
+                tag: "comment".to_owned(),
+                old: vec!["Some comment".to_owned()],
+                new: vec![],
+            },
+            JournalEntry::Pictures {
+                path: PathBuf::from("/music/a.flac"),
+                old: vec![JournalPicture {
+                    picture_type: "front-cover".to_owned(),
+                    mime_type: "image/png".to_owned(),
+                    description: "".to_owned(),
+                    file: "1.bin".to_owned(),
+                }],
+                new: vec![],
+            },
+            JournalEntry::Saved { path: PathBuf::from("/music/a.flac"), size: 1024, mtime: 1 },
+        ];
+
+        let mut buf = Vec::new();
+        for entry in &entries {
+            write_entry(&mut buf, entry).unwrap();
+        }
+        let s = String::from_utf8(buf).unwrap();
+        assert!(s.lines().nth(1).unwrap().starts_with("{\"type\":\"tag\","));
+        assert_eq!(parse_entries(Cursor::new(s)).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_pictures() {
+        let dir = env::temp_dir().join("music-tag-cli-journal-pictures");
+        let _ = fs::remove_dir_all(&dir);
+        let pictures = vec![
+            MyPicture {
+                picture_type: PictureType::FrontCover,
+                mime_type: "image/png".to_owned(),
+                description: "front".to_owned(),
+                data: vec![0x89, b'P', b'N', b'G'],
+            },
+            MyPicture {
+                picture_type: PictureType::BackCover,
+                mime_type: "image/jpeg".to_owned(),
+                description: "".to_owned(),
+                data: vec![0xff, 0xd8, 0xff],
+            },
+        ];
+
+        let mut count = 1;
+        let saved = save_pictures(&dir, &mut count, &pictures).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(saved[0].picture_type, "front-cover");
+        assert_eq!(saved[1].file, "3.bin");
+        assert_eq!(load_pictures(&dir, &saved).unwrap(), pictures);
+
+        fs::remove_file(dir.join("3.bin")).unwrap();
+        assert!(load_pictures(&dir, &saved).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -7,6 +7,8 @@ fn main() -> Result<(), Error> {
 mod args;
 mod cli;
 mod config;
+mod expr;
+mod journal;
 mod model;
 mod op;
 mod util;
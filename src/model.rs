@@ -1,7 +1,10 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::{anyhow, Error};
 use clap::ValueEnum;
 use lazy_static::lazy_static;
 use strum_macros::{Display as EnumDisplay};
+use crate::config::get_multi_value_separator;
 use crate::util::numeric::decimal_to_padding_string;
 
 pub const DEFAULT_PADDING: usize = 2;
@@ -35,6 +38,24 @@ pub enum MyTag {
     Comment,
     Copyright,
     Lyrics,
+    TitleSort,
+    ArtistSort,
+    AlbumSort,
+    AlbumArtistSort,
+    Bpm,
+    Isrc,
+    Label,
+    CatalogNumber,
+    Grouping,
+    Conductor,
+    OriginalDate,
+    MusicbrainzTrackId,
+    MusicbrainzAlbumId,
+    MusicbrainzArtistId,
+    MusicbrainzAlbumArtistId,
+    MusicbrainzReleaseGroupId,
+    MusicbrainzReleaseTrackId,
+    MusicbrainzWorkId,
 }
 
 impl MyTag {
@@ -59,6 +80,11 @@ impl MyTag {
         }
     }
 
+    /// Sort names, identifiers and so on, which are stored as properties by TagLib.
+    pub fn is_extended(&self) -> bool {
+        EXTENDED_TAGS.contains(self)
+    }
+
     fn data_type(&self) -> MyTagType {
         match self {
             MyTag::Title => MyTagType::Text,
@@ -76,6 +102,24 @@ impl MyTag {
             MyTag::Comment => MyTagType::Text,
             MyTag::Copyright => MyTagType::Text,
             MyTag::Lyrics => MyTagType::Text,
+            MyTag::TitleSort => MyTagType::Text,
+            MyTag::ArtistSort => MyTagType::Text,
+            MyTag::AlbumSort => MyTagType::Text,
+            MyTag::AlbumArtistSort => MyTagType::Text,
+            MyTag::Bpm => MyTagType::Numeric,
+            MyTag::Isrc => MyTagType::Text,
+            MyTag::Label => MyTagType::Text,
+            MyTag::CatalogNumber => MyTagType::Text,
+            MyTag::Grouping => MyTagType::Text,
+            MyTag::Conductor => MyTagType::Text,
+            MyTag::OriginalDate => MyTagType::Date,
+            MyTag::MusicbrainzTrackId => MyTagType::Text,
+            MyTag::MusicbrainzAlbumId => MyTagType::Text,
+            MyTag::MusicbrainzArtistId => MyTagType::Text,
+            MyTag::MusicbrainzAlbumArtistId => MyTagType::Text,
+            MyTag::MusicbrainzReleaseGroupId => MyTagType::Text,
+            MyTag::MusicbrainzReleaseTrackId => MyTagType::Text,
+            MyTag::MusicbrainzWorkId => MyTagType::Text,
         }
     }
 
@@ -90,7 +134,7 @@ impl MyTag {
 }
 
 lazy_static! {
-    pub static ref ALL_TAGS: Vec<MyTag> = vec![
+    static ref BASIC_TAGS: Vec<MyTag> = vec![
         MyTag::Title,
         MyTag::Artist,
         MyTag::AlbumTitle,
@@ -108,11 +152,45 @@ lazy_static! {
         MyTag::Lyrics,
     ];
 
-    pub static ref TEXT_TAGS: Vec<MyTag> = ALL_TAGS.iter()
+    static ref EXTENDED_TAGS: Vec<MyTag> = vec![
+        MyTag::TitleSort,
+        MyTag::ArtistSort,
+        MyTag::AlbumSort,
+        MyTag::AlbumArtistSort,
+        MyTag::Bpm,
+        MyTag::Isrc,
+        MyTag::Label,
+        MyTag::CatalogNumber,
+        MyTag::Grouping,
+        MyTag::Conductor,
+        MyTag::OriginalDate,
+        MyTag::MusicbrainzTrackId,
+        MyTag::MusicbrainzAlbumId,
+        MyTag::MusicbrainzArtistId,
+        MyTag::MusicbrainzAlbumArtistId,
+        MyTag::MusicbrainzReleaseGroupId,
+        MyTag::MusicbrainzReleaseTrackId,
+        MyTag::MusicbrainzWorkId,
+    ];
+
+    pub static ref ALL_TAGS: Vec<MyTag> = BASIC_TAGS.iter()
+                                .chain(EXTENDED_TAGS.iter())
+                                .copied()
+                                .collect::<Vec<MyTag>>();
+
+    // extended tags (sort names, identifiers, ...) are NOT included by default
+    pub static ref TEXT_TAGS: Vec<MyTag> = BASIC_TAGS.iter()
                                 .filter(|e| e.is_text())
                                 .copied()
                                 .collect::<Vec<MyTag>>();
 
+    pub static ref MULTI_VALUE_TAGS: Vec<MyTag> = vec![
+        MyTag::Artist,
+        MyTag::AlbumArtist,
+        MyTag::Genre,
+        MyTag::Composer,
+    ];
+
     static ref NUMERIC_TAGS: Vec<MyTag> = ALL_TAGS.iter()
                                 .filter(|e| e.is_numeric())
                                 .copied()
@@ -133,6 +211,85 @@ pub enum FilenameExistPolicy {
     Overwrite,
 }
 
+#[derive(Copy, Clone, PartialEq, ValueEnum, Debug)]
+pub enum GroupBy {
+    /// Files in the same directory.
+    Dir,
+    /// Files with the same album-artist and album-title, files without album-title are grouped
+    /// by directory.
+    Album,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum, Debug)]
+pub enum DataFormat {
+    /// JSON array, one element per file.
+    Json,
+    /// Comma-separated values, the first row is header, one row per file.
+    Csv,
+    /// Tab-separated values, the first row is header, one row per file.
+    Tsv,
+    /// Newline-delimited JSON, one line per file with its absolute path, no header.
+    Ndjson,
+}
+
+impl DataFormat {
+    /// Guess format by extension of the file, `.csv`, `.tsv` (or `.tab`) and `.ndjson` (or
+    /// `.jsonl`), others are JSON.
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "csv" => DataFormat::Csv,
+            "tsv" | "tab" => DataFormat::Tsv,
+            "ndjson" | "jsonl" => DataFormat::Ndjson,
+            _ => DataFormat::Json,
+        }
+    }
+
+    pub fn is_csv(&self) -> bool {
+        matches!(self, DataFormat::Csv | DataFormat::Tsv)
+    }
+
+    pub fn get_delimiter(&self) -> u8 {
+        match self {
+            DataFormat::Tsv => b'\t',
+            _ => b',',
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum, Debug)]
+pub enum SanitizeProfile {
+    /// Replace `/` and NUL only.
+    Posix,
+    /// Also replace `< > : " \ | ? *` and control characters, trim trailing dots and spaces,
+    /// avoid reserved names like `CON`. It is safe for Windows and SMB shares.
+    Windows,
+    /// Same as `windows`, and transliterate non-ASCII characters, e.g. `é` as `e`.
+    Ascii,
+}
+
+impl Default for SanitizeProfile {
+    fn default() -> Self {
+        if cfg!(windows) {
+            SanitizeProfile::Windows
+        } else {
+            SanitizeProfile::Posix
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum, Debug)]
+pub enum PropsMode {
+    /// Set properties from JSON, other properties in file will be kept.
+    Merge,
+    /// Replace all properties in file, those NOT in JSON will be removed.
+    Replace,
+    /// Only set properties which are missing in file.
+    FillMissing,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum ConvEnProfile {
     Lowercase,
@@ -184,7 +341,7 @@ pub(crate) enum QueryResultPosition {
 #[derive(Debug, Clone)]
 pub enum ConstValue {
     Text {
-        value: String,
+        values: Vec<String>,
     },
     Num {
         value: u32,
@@ -199,7 +356,7 @@ pub enum ConstValue {
 impl ConstValue {
     pub fn get_text_value(&self) -> String {
         match self {
-            ConstValue::Text { value } => value.clone(),
+            ConstValue::Text { values } => values.join(get_multi_value_separator()),
             ConstValue::Date { value, .. } => value.clone(),
             ConstValue::Num { value, padding } => {
                 decimal_to_padding_string(*value, *padding)
@@ -231,6 +388,115 @@ pub enum TextConst {
     },
 }
 
+#[derive(Debug, Copy, Clone, ValueEnum, Eq, Hash, PartialEq, EnumDisplay)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PictureType {
+    Other,
+    FileIcon,
+    OtherFileIcon,
+    FrontCover,
+    BackCover,
+    LeafletPage,
+    Media,
+    LeadArtist,
+    Artist,
+    Conductor,
+    Band,
+    Composer,
+    Lyricist,
+    RecordingLocation,
+    DuringRecording,
+    DuringPerformance,
+    MovieScreenCapture,
+    ColouredFish,
+    Illustration,
+    BandLogo,
+    PublisherLogo,
+}
+
+impl PictureType {
+    pub fn from_str(input: &str) -> Option<Self> {
+        Self::value_variants().iter()
+            .find(|p| p.to_string().eq(input))
+            .copied()
+    }
+}
+
+/// Audio properties of the file, they could NOT be modified.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, EnumDisplay)]
+#[strum(serialize_all = "kebab-case")]
+pub enum AudioProperty {
+    /// Average bitrate in kb/s.
+    Bitrate,
+    /// Length in seconds.
+    Length,
+    /// Sample rate in Hz.
+    SampleRate,
+    Channels,
+    BitsPerSample,
+}
+
+pub const ALL_AUDIO_PROPERTIES: [AudioProperty; 5] = [
+    AudioProperty::Bitrate,
+    AudioProperty::Length,
+    AudioProperty::SampleRate,
+    AudioProperty::Channels,
+    AudioProperty::BitsPerSample,
+];
+
+impl AudioProperty {
+    pub fn from_str(input: &str) -> Option<Self> {
+        ALL_AUDIO_PROPERTIES.iter()
+            .find(|p| p.to_string().eq(input))
+            .copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MyPicture {
+    pub picture_type: PictureType,
+    pub mime_type: String,
+    pub description: String,
+    pub data: Vec<u8>,
+}
+
+pub enum ArtOperation {
+    List,
+    Export {
+        picture_type: PictureType,
+        name: String,
+        filename_exist_policy: FilenameExistPolicy,
+    },
+    Embed {
+        picture_type: PictureType,
+        source_file: Option<PathBuf>,
+        description: String,
+    },
+    Remove {
+        picture_type: Option<PictureType>,
+    },
+}
+
+#[derive(Debug)]
+pub enum PropOperation {
+    Set {
+        key: String,
+        values: Vec<String>,
+    },
+    Add {
+        key: String,
+        values: Vec<String>,
+    },
+    Remove {
+        key: String,
+        values: Vec<String>,
+    },
+    RenameKey {
+        from: String,
+        to: String,
+    },
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum ConvZhProfile {
     /// Traditional Chinese (Hong Kong Standard) to Simplified Chinese
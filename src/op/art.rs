@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use log::{debug, error, info};
+
+use crate::model::{ArtOperation, FilenameExistPolicy, MyPicture, MyTag, PictureType};
+use crate::op::{check_where, get_file_iterator, get_new_path, get_where};
+use crate::op::{Action, WalkAction};
+use crate::op::tag_impl::{ReadTag, ReadWriteTag, TagImpl};
+use crate::where_clause::WhereClause;
+
+const MAX_FILE_LENGTH: u64 = 16 * 1024 * 1024;
+const SIBLING_STEM: &str = "folder";
+
+pub struct ArtAction {
+    it: Box<dyn Iterator<Item=PathBuf>>,
+    dry_run: bool,
+    tags: Vec<MyTag>,
+    where_clause: Option<WhereClause>,
+    operation: ArtOperation,
+    // for cache
+    source_picture: Option<(Vec<u8>, String)>,
+    // state
+    exported_dirs: HashSet<PathBuf>,
+}
+
+impl ArtAction {
+    pub fn new<P>(dir: P,
+                  dry_run: bool,
+                  where_string: &Option<String>,
+                  operation: ArtOperation) -> Result<Self, Error>
+        where P: AsRef<Path>
+    {
+        let it = get_file_iterator(dir.as_ref())?;
+        let where_clause = get_where(where_string)?;
+        let source_picture = Self::check(&operation)?;
+        Ok(Self {
+            it,
+            dry_run,
+            tags: vec![],
+            where_clause,
+            operation,
+            source_picture,
+            exported_dirs: HashSet::new(),
+        })
+    }
+
+    fn check(operation: &ArtOperation) -> Result<Option<(Vec<u8>, String)>, Error> {
+        match operation {
+            ArtOperation::Export { name, .. } => {
+                if name.is_empty() || name.contains(std::path::MAIN_SEPARATOR) {
+                    Err(anyhow!("Parameter: name \"{}\" is NOT a valid file stem.", name))
+                } else {
+                    Ok(None)
+                }
+            }
+            ArtOperation::Embed { source_file: Some(source_file), .. } => {
+                read_picture_file(source_file).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn list(&self, t: &dyn ReadTag) -> Result<bool, Error> {
+        let pictures = t.get_pictures()?;
+        let mut w = Vec::new();
+        output_text(&mut w, &pictures, t.get_path())?;
+
+        let stdout = stdout();
+        let mut writer = stdout.lock();
+        write!(writer, "{}", String::from_utf8(w)?)?;
+        Ok(true)
+    }
+
+    fn export(&mut self,
+              t: &dyn ReadTag,
+              picture_type: PictureType,
+              name: &str,
+              filename_exist_policy: FilenameExistPolicy) -> Result<bool, Error> {
+        let path = t.get_path();
+        let dir = get_parent(path);
+        if self.exported_dirs.contains(&dir) {
+            debug!("picture in directory {:?} has already been exported.", &dir);
+            return Ok(false);
+        }
+
+        let pictures = t.get_pictures()?;
+        let picture = match pictures.iter().find(|p| p.picture_type == picture_type) {
+            Some(p) => p,
+            None => {
+                info!("file {:?} has NOT any picture of {}.", path, picture_type);
+                return Ok(false);
+            }
+        };
+
+        let output_path = dir.join(format!("{}.{}", name, get_ext_by_mime(&picture.mime_type)));
+        let output_path = match get_new_path(&output_path, filename_exist_policy) {
+            Some(p) => p,
+            None => return Ok(false),
+        };
+
+        if !self.dry_run {
+            fs::write(&output_path, &picture.data)?;
+        }
+        info!("Save picture file {:?} ok.", &output_path);
+        self.exported_dirs.insert(dir);
+        Ok(true)
+    }
+
+    fn embed(&self,
+             t: &mut dyn ReadWriteTag,
+             picture_type: PictureType,
+             description: &str) -> Result<bool, Error> {
+        let (data, mime_type) = match &self.source_picture {
+            Some((data, mime_type)) => (data.clone(), mime_type.clone()),
+            None => {
+                match find_sibling_picture(&get_parent(t.get_path())) {
+                    Some(p) => read_picture_file(&p)?,
+                    None => {
+                        error!("Could NOT find {}.jpg or {}.png for file {:?}",
+                            SIBLING_STEM, SIBLING_STEM, t.get_path());
+                        return Ok(false);
+                    }
+                }
+            }
+        };
+
+        let mut pictures = t.get_pictures()?;
+        if pictures.iter()
+            .any(|p| p.picture_type == picture_type && p.data == data) {
+            debug!("file {:?} has already embedded the same picture.", t.get_path());
+            return Ok(false);
+        }
+
+        pictures.retain(|p| p.picture_type != picture_type);
+        pictures.insert(0, MyPicture {
+            picture_type,
+            mime_type,
+            description: description.to_owned(),
+            data,
+        });
+        t.write_pictures(&pictures);
+        t.save()?;
+        Ok(true)
+    }
+
+    fn remove(&self,
+              t: &mut dyn ReadWriteTag,
+              picture_type: &Option<PictureType>) -> Result<bool, Error> {
+        let pictures = t.get_pictures()?;
+        let remained = match picture_type {
+            Some(picture_type) => pictures.iter()
+                .filter(|p| &p.picture_type != picture_type)
+                .cloned()
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        if remained.len() != pictures.len() {
+            t.write_pictures(&remained);
+            t.save()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl Action for ArtAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        self.do_all()
+    }
+}
+
+impl WalkAction for ArtAction {
+    fn get_iterator(&mut self) -> &mut dyn Iterator<Item=PathBuf> {
+        &mut self.it
+    }
+
+    fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
+        let mut tag_impl = TagImpl::new(&path, self.dry_run)?;
+        if !check_where(&self.where_clause, &tag_impl)? {
+            return Ok(false);
+        }
+
+        match &self.operation {
+            ArtOperation::List => self.list(&tag_impl),
+            ArtOperation::Export { picture_type, name, filename_exist_policy } => {
+                let (picture_type, name, filename_exist_policy) =
+                    (*picture_type, name.clone(), *filename_exist_policy);
+                self.export(&tag_impl, picture_type, &name, filename_exist_policy)
+            }
+            ArtOperation::Embed { picture_type, description, .. } =>
+                self.embed(&mut tag_impl, *picture_type, description),
+            ArtOperation::Remove { picture_type } =>
+                self.remove(&mut tag_impl, picture_type),
+        }
+    }
+
+    fn get_where(&self) -> &Option<WhereClause> {
+        &self.where_clause
+    }
+
+    fn tags(&self) -> &Vec<MyTag> {
+        &self.tags
+    }
+}
+
+fn output_text<W, P>(writer: &mut W,
+                     pictures: &[MyPicture],
+                     path: P) -> Result<(), Error>
+    where W: Write,
+          P: AsRef<Path>
+{
+    let len = pictures.len();
+    if len == 1 {
+        writeln!(writer, "-- PICTURES for {:?} {} picture --", path.as_ref(), len)?;
+    } else {
+        writeln!(writer, "-- PICTURES for {:?} {} pictures --", path.as_ref(), len)?;
+    }
+
+    for p in pictures {
+        writeln!(writer, "{} - {}, {} bytes, {:?}",
+                 p.picture_type, p.mime_type, p.data.len(), p.description)?;
+    }
+    Ok(())
+}
+
+fn get_parent(path: &Path) -> PathBuf {
+    path.parent().map_or_else(PathBuf::new, PathBuf::from)
+}
+
+fn find_sibling_picture(dir: &Path) -> Option<PathBuf> {
+    ["jpg", "jpeg", "png"].iter()
+        .map(|ext| dir.join(format!("{}.{}", SIBLING_STEM, ext)))
+        .find(|p| p.is_file())
+}
+
+fn read_picture_file(path: &Path) -> Result<(Vec<u8>, String), Error> {
+    let mime_type = path.extension()
+        .and_then(|ext| get_mime_by_ext(&ext.to_string_lossy()))
+        .ok_or_else(|| anyhow!("Unsupported picture file {:?}, it must be JPEG or PNG.", path))?;
+
+    let metadata = fs::metadata(path)
+        .map_err(|e| anyhow!("Could NOT read picture file {:?}. (error: {})", path, e))?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_LENGTH {
+        return Err(anyhow!("Picture file {:?} is NOT a file or it is too big!", path));
+    }
+
+    let data = fs::read(path)?;
+    Ok((data, mime_type.to_owned()))
+}
+
+fn get_mime_by_ext(ext: &str) -> Option<&'static str> {
+    match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        _ => None,
+    }
+}
+
+fn get_ext_by_mime(mime_type: &str) -> &'static str {
+    match mime_type.to_lowercase().as_str() {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        "image/webp" => "webp",
+        _ => "jpg",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_ext_by_mime, get_mime_by_ext};
+
+    #[test]
+    fn test_get_mime_by_ext() {
+        assert_eq!(get_mime_by_ext("jpg"), Some("image/jpeg"));
+        assert_eq!(get_mime_by_ext("JPEG"), Some("image/jpeg"));
+        assert_eq!(get_mime_by_ext("png"), Some("image/png"));
+        assert_eq!(get_mime_by_ext("txt"), None);
+    }
+
+    #[test]
+    fn test_get_ext_by_mime() {
+        assert_eq!(get_ext_by_mime("image/jpeg"), "jpg");
+        assert_eq!(get_ext_by_mime("image/PNG"), "png");
+        assert_eq!(get_ext_by_mime("image/jpg"), "jpg");
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON document written by `exp`, the legacy JSON array is version 1.
+pub(super) const SCHEMA_VERSION: u32 = 2;
+
+/// Field of the document which holds the records, it must be the last one.
+pub(super) const RECORDS_FIELD: &str = "records";
+
+/// Fields of the document before `records`, the records are written and read one by one.
+#[derive(Serialize, Deserialize, Debug)]
+pub(super) struct Document {
+    pub header: DocHeader,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(super) struct DocHeader {
+    pub schema_version: u32,
+    pub tool: String,
+    pub tool_version: String,
+    /// Absolute path of the export root, paths of records are relative to it.
+    pub root: String,
+}
+
+impl DocHeader {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            tool: env!("CARGO_PKG_NAME").to_owned(),
+            tool_version: env!("CARGO_PKG_VERSION").to_owned(),
+            root: root.to_string_lossy().to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(super) struct DocRecord {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Modified time in RFC 3339.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<String>,
+    pub tags: BTreeMap<String, Option<DocValue>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub props: Option<BTreeMap<String, Vec<String>>>,
+    /// Audio properties, they are ignored by `imp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<BTreeMap<String, u32>>,
+}
+
+/// Typed tag value, e.g. `{"type": "num", "value": 1, "text": "01"}`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(super) enum DocValue {
+    Text { values: Vec<String> },
+    /// `text` is the original form in file, the padding is kept by it.
+    Num { value: u32, text: String },
+    Date { text: String },
+}
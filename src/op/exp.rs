@@ -1,26 +1,50 @@
 use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, stdout, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Error};
+use chrono::{DateTime, Local, SecondsFormat};
 use log::{debug, error};
 
-use crate::model::{ALL_TAGS, FilenameExistPolicy, MyTag};
-use crate::op::{check_where, get_file_iterator, get_new_path, get_properties, get_tags_from_args,
-                get_tags_value, get_where};
+use crate::model::{ALL_AUDIO_PROPERTIES, ALL_TAGS, DataFormat, FilenameExistPolicy, MyTag};
+use crate::op::{check_where, get_ascii_char, get_audio_properties, get_file_iterator, get_new_path,
+                get_properties, get_tags_from_args, get_tags_value, get_where, PROP_COLUMN_PREFIX,
+                to_csv_cell};
 use crate::op::{Action, MyValue, MyValues, ReadAction, ReadTag, WalkAction};
+use crate::op::document::{DocHeader, DocRecord, DocValue, RECORDS_FIELD};
 use crate::op::tag_impl::TagImpl;
+use crate::util::path::is_std_stream;
 use crate::where_clause::WhereClause;
 
 const BUFFER_SIZE: usize = 4 * 1024;
 
+/// Columns and format of the exported data.
+pub struct ExpOptions {
+    pub with_properties: bool,
+    pub with_audio_properties: bool,
+    pub format: DataFormat,
+    pub prop_columns: Vec<String>,
+    pub delimiter: Option<char>,
+    pub quote: char,
+}
+
 pub struct ExpAction {
     it: Box<dyn Iterator<Item=PathBuf>>,
     writer: Box<dyn Write>,
     with_properties: bool,
+    with_audio_properties: bool,
     tags: Vec<MyTag>,
     where_clause: Option<WhereClause>,
+    format: DataFormat,
+    prop_columns: Vec<String>,
+    delimiter: u8,
+    quote: u8,
+    /// Paths of records are relative to it.
+    base_dir: PathBuf,
+    /// Absolute path of `base_dir`.
+    root: PathBuf,
     // state
     is_first: bool,
 }
@@ -29,10 +53,33 @@ impl ExpAction {
     pub fn new<P: AsRef<Path>>(dir: P,
                                tags: &[MyTag],
                                where_string: &Option<String>,
-                               with_properties: bool,
+                               options: ExpOptions,
                                output_file: P,
                                filename_exist_policy: FilenameExistPolicy) -> Result<Self, Error> {
+        let ExpOptions {
+            with_properties,
+            with_audio_properties,
+            format,
+            prop_columns,
+            delimiter,
+            quote,
+        } = options;
+        if format == DataFormat::Json && !prop_columns.is_empty() {
+            return Err(anyhow!("Parameter: prop-columns is only for CSV / TSV format."));
+        }
+        if format.is_csv() && with_properties {
+            return Err(anyhow!("Parameter: with-properties is only for JSON / NDJSON format, \
+            use prop-columns for CSV / TSV format."));
+        }
+        let delimiter = match delimiter {
+            Some(c) => get_ascii_char("delimiter", c)?,
+            None => format.get_delimiter(),
+        };
+        let quote = get_ascii_char("quote", quote)?;
+
         let it = get_file_iterator(dir.as_ref())?;
+        let base_dir = get_base_dir(dir.as_ref());
+        let root = fs::canonicalize(&base_dir)?;
         let tags = get_tags_from_args(tags, &ALL_TAGS)?;
         let where_clause = get_where(where_string)?;
         let writer = get_file_writer(output_file, filename_exist_policy)?;
@@ -40,27 +87,50 @@ impl ExpAction {
             it,
             writer,
             with_properties,
+            with_audio_properties,
             tags,
             where_clause,
+            format,
+            prop_columns: prop_columns.iter().map(|key| key.to_uppercase()).collect(),
+            delimiter,
+            quote,
+            base_dir,
+            root,
             is_first: true,
         })
     }
 
     #[inline]
     fn do_start(&mut self) -> Result<(), Error> {
-        write!(self.writer, "[")?;
+        match self.format {
+            DataFormat::Json => {
+                let header = serde_json::to_string_pretty(&DocHeader::new(&self.root))?;
+                writeln!(self.writer, "{{\n\"header\": {},\n\"{}\": [", header, RECORDS_FIELD)?
+            }
+            DataFormat::Csv | DataFormat::Tsv => {
+                let header = get_csv_header(&self.tags, &self.prop_columns,
+                                            self.with_audio_properties);
+                let content = get_csv_record(&header, self.delimiter, self.quote)?;
+                write!(self.writer, "{}", content)?
+            }
+            DataFormat::Ndjson => {}
+        }
         Ok(())
     }
 
     #[inline]
     fn do_end(&mut self) -> Result<(), Error> {
-        write!(self.writer, "]")?;
+        if self.format == DataFormat::Json {
+            writeln!(self.writer, "\n]\n}}")?;
+        }
         Ok(())
     }
 
     #[inline]
     fn do_sep(&mut self) -> Result<(), Error> {
-        writeln!(self.writer, ",")?;
+        if self.format == DataFormat::Json {
+            writeln!(self.writer, ",")?;
+        }
         Ok(())
     }
 }
@@ -68,23 +138,25 @@ impl ExpAction {
 fn get_values<'a, P>(path: P,
                      tags: &'a Vec<MyTag>,
                      where_clause: &Option<WhereClause>,
-                     with_properties: bool) -> Result<MyValues<'a>, Error>
+                     with_properties: bool,
+                     with_audio_properties: bool) -> Result<MyValues<'a>, Error>
     where P: AsRef<Path>
 {
     let tag_impl = TagImpl::new(&path, true)?;
-    read_tags(&tag_impl, tags, where_clause, with_properties)
+    read_tags(&tag_impl, tags, where_clause, with_properties, with_audio_properties)
 }
 
 fn read_tags<'a>(t: &dyn ReadTag,
                  tags: &'a Vec<MyTag>,
                  where_clause: &Option<WhereClause>,
-                 with_properties: bool) -> Result<MyValues<'a>, Error> {
+                 with_properties: bool,
+                 with_audio_properties: bool) -> Result<MyValues<'a>, Error> {
     if tags.is_empty() {
-        return Ok(MyValues { raw: None, properties: None });
+        return Ok(MyValues { raw: None, properties: None, audio_properties: None });
     }
 
     if !check_where(where_clause, t.as_dyn_read_tag())? {
-        return Ok(MyValues { raw: None, properties: None });
+        return Ok(MyValues { raw: None, properties: None, audio_properties: None });
     }
 
     let mut map: HashMap<&MyTag, MyValue> = HashMap::with_capacity(tags.len());
@@ -99,14 +171,36 @@ fn read_tags<'a>(t: &dyn ReadTag,
         None
     };
 
-    Ok(MyValues { raw: Some(map), properties })
+    let audio_properties = if with_audio_properties {
+        Some(get_audio_properties(t))
+    } else {
+        None
+    };
+
+    Ok(MyValues { raw: Some(map), properties, audio_properties })
+}
+
+/// Directory of the file(s) to export, `.` for a file in current directory.
+fn get_base_dir(dir: &Path) -> PathBuf {
+    let base_dir = if dir.is_file() {
+        dir.parent().unwrap_or(dir)
+    } else {
+        dir
+    };
+    if base_dir.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base_dir.to_path_buf()
+    }
 }
 
 fn get_file_writer<P>(path: P,
                       filename_exist_policy: FilenameExistPolicy) -> Result<Box<dyn Write>, Error>
     where P: AsRef<Path> {
     let path = path.as_ref();
-    if let Some(path) = get_new_path(path, filename_exist_policy) {
+    if is_std_stream(path) {
+        Ok(Box::new(BufWriter::with_capacity(BUFFER_SIZE, stdout())))
+    } else if let Some(path) = get_new_path(path, filename_exist_policy) {
         let f = File::create(path)?;
         let writer = BufWriter::with_capacity(BUFFER_SIZE, f);
         Ok(Box::new(writer))
@@ -168,8 +262,14 @@ impl ReadAction for ExpAction {
         self.with_properties
     }
 
+    fn with_audio_properties(&self) -> bool {
+        self.with_audio_properties
+    }
+
     fn do_one_file_read(&mut self, path: &Path) -> Result<bool, Error> {
-        let v = get_values(path, &self.tags, &self.where_clause, self.with_properties)?;
+        let with_properties = self.with_properties || !self.prop_columns.is_empty();
+        let v = get_values(path, &self.tags, &self.where_clause, with_properties,
+                           self.with_audio_properties)?;
         match self.get_content(path, &v)? {
             Some(content) => {
                 debug!("content: {}", &content);
@@ -183,7 +283,23 @@ impl ReadAction for ExpAction {
     }
 
     fn get_content(&self, path: &Path, v: &MyValues) -> Result<Option<String>, Error> {
-        get_json(&v, path, &self.tags)
+        match self.format {
+            _ if v.is_empty_value() => Ok(None),
+            DataFormat::Json => {
+                let record = get_doc_record(v, path, &self.base_dir, &self.tags);
+                Ok(Some(serde_json::to_string_pretty(&record)?))
+            }
+            DataFormat::Ndjson => {
+                // every line could be used alone, so the path is absolute
+                let mut record = get_doc_record(v, path, &self.base_dir, &self.tags);
+                record.path = self.root.join(&record.path).to_string_lossy().to_string();
+                Ok(Some(serde_json::to_string(&record)? + "\n"))
+            }
+            DataFormat::Csv | DataFormat::Tsv => {
+                let record = get_csv_values(v, path, &self.tags, &self.prop_columns);
+                get_csv_record(&record, self.delimiter, self.quote).map(Some)
+            }
+        }
     }
 
     fn do_output(&mut self, _path: &Path, content: &str) -> Result<bool, Error> {
@@ -193,124 +309,97 @@ impl ReadAction for ExpAction {
     }
 }
 
-fn get_json<P>(v: &MyValues,
-               path: P,
-               tags: &Vec<MyTag>) -> Result<Option<String>, Error>
-    where P: AsRef<Path>,
-{
-    let mut w = Vec::new();
-    let success = output_json(&mut w, v, path, tags)?;
-    if success {
-        let s = String::from_utf8(w)?;
-        Ok(Some(s))
-    } else {
-        Ok(None)
+fn get_doc_record(v: &MyValues,
+                  path: &Path,
+                  base_dir: &Path,
+                  tags: &[MyTag]) -> DocRecord {
+    let relative_path = path.strip_prefix(base_dir).unwrap_or(path);
+    let metadata = fs::metadata(path).ok();
+    let size = metadata.as_ref().map(|m| m.len());
+    let mtime = metadata.and_then(|m| m.modified().ok())
+        .map(|v| DateTime::<Local>::from(v).to_rfc3339_opts(SecondsFormat::Secs, false));
+
+    let tags = tags.iter()
+        .map(|tag| {
+            let value = v.raw.as_ref()
+                .and_then(|r| r.get(tag))
+                .and_then(|value| get_doc_value(tag, value));
+            (tag.to_string(), value)
+        })
+        .collect();
+
+    let audio = v.get_audio_properties().map(|audio_properties|
+        audio_properties.iter()
+            .map(|(p, value)| (p.to_string(), *value))
+            .collect());
+
+    DocRecord {
+        path: relative_path.to_string_lossy().to_string(),
+        size,
+        mtime,
+        tags,
+        props: v.properties.clone(),
+        audio,
     }
 }
 
-fn output_json<W, P>(writer: &mut W,
-                     v: &MyValues,
-                     path: P,
-                     tags: &Vec<MyTag>) -> Result<bool, Error>
-    where W: Write,
-          P: AsRef<Path>,
-{
-    if v.is_empty_value() {
-        return Ok(false);
-    }
-
-    writeln!(writer, "{{")?;
-
-    writeln!(writer, "  \"{}\": {:?},", "path", path.as_ref())?;
-    {
-        output_json_tags(writer, tags, &v)?;
-    }
-
-    if !v.is_empty_properties() {
-        writeln!(writer, ",")?;
-    } else {
-        writeln!(writer)?;
-    }
-
-    if !v.is_empty_properties() {
-        let keys = v.get_prop_keys().unwrap();
-        output_json_props(writer, v, keys)?;
+fn get_doc_value(tag: &MyTag, value: &MyValue) -> Option<DocValue> {
+    match value {
+        MyValue::Num(n, s) => Some(DocValue::Num { value: *n, text: s.to_owned() }),
+        MyValue::Text(s) if tag.is_date() => Some(DocValue::Date { text: s.to_owned() }),
+        MyValue::Text(s) => Some(DocValue::Text { values: vec![s.to_owned()] }),
+        MyValue::Texts(values, _) => Some(DocValue::Text { values: values.to_owned() }),
+        MyValue::None => None,
     }
-
-    write!(writer, "}}")?;
-    Ok(true)
 }
 
-fn output_json_props<W>(writer: &mut W,
-                        v: &MyValues,
-                        keys: Vec<&String>) -> Result<(), Error>
-    where W: Write
-{
-    writeln!(writer, "  \"props\": {{")?;
-    let mut it = keys.iter().peekable();
-    while let Some(key) = it.next() {
-        if let Some(values) = v.get_prop(key) {
-            write!(writer, "    \"{}\": {:?}", key, values)?;
-        }
-
-        if it.peek().is_some() {
-            writeln!(writer, ",")?;
-        } else {
-            writeln!(writer)?;
-        }
+fn get_csv_header(tags: &[MyTag],
+                  prop_columns: &[String],
+                  with_audio_properties: bool) -> Vec<String> {
+    let mut header = vec!["path".to_owned()];
+    header.extend(tags.iter().map(|tag| tag.to_string()));
+    header.extend(prop_columns.iter().map(|key| format!("{}{}", PROP_COLUMN_PREFIX, key)));
+    if with_audio_properties {
+        header.extend(ALL_AUDIO_PROPERTIES.iter().map(|p| p.to_string()));
     }
-    writeln!(writer, "  }}")?;
-    Ok(())
+    header
 }
 
-fn output_json_tags<W>(writer: &mut W,
-                       tags: &Vec<MyTag>,
-                       v: &&MyValues) -> Result<(), Error>
-    where W: Write
-{
-    writeln!(writer, "  \"tags\": {{")?;
-    let mut it = tags.iter().peekable();
-    while let Some(tag) = it.next() {
-        let tag_name = tag.to_string();
-        if tag.is_text() || tag.is_date() {
-            if let Some(s) = v.get_text(tag) {
-                write!(writer, "    \"{}\": \"{}\"", &tag_name, &escape(s))?;
-            } else {
-                write!(writer, "    \"{}\": null", &tag_name)?;
-            }
-        } else if tag.is_numeric() {
-            if let Some(n) = v.get_num(tag) {
-                write!(writer, "    \"{}\": {}", &tag_name, n)?;
-            } else {
-                write!(writer, "    \"{}\": null", &tag_name)?;
-            }
+/// Values are in the same order as `get_csv_header`, numbers are written as they are in the
+/// file to keep the padding.
+fn get_csv_values(v: &MyValues,
+                  path: &Path,
+                  tags: &[MyTag],
+                  prop_columns: &[String]) -> Vec<String> {
+    let mut values = vec![path.to_string_lossy().to_string()];
+    for tag in tags {
+        let value = if tag.is_text() {
+            v.get_texts(tag).map(to_csv_cell)
         } else {
-            // do_nothing
-        }
-
-        if it.peek().is_some() {
-            writeln!(writer, ",")?;
-        } else {
-            writeln!(writer)?;
+            v.get_text(tag).map(|s| s.to_owned())
+        };
+        values.push(value.unwrap_or_default());
+    }
+    for key in prop_columns {
+        values.push(v.get_prop(key).map(|values| to_csv_cell(values)).unwrap_or_default());
+    }
+    if let Some(audio_properties) = v.get_audio_properties() {
+        for p in ALL_AUDIO_PROPERTIES.iter() {
+            let value = audio_properties.iter()
+                .find(|(ap, _)| ap == p)
+                .map(|(_, value)| value.to_string());
+            values.push(value.unwrap_or_default());
         }
     }
-    write!(writer, "  }}")?;
-    Ok(())
+    values
 }
 
-fn escape(src: &str) -> String {
-    let mut escaped = String::with_capacity(src.len());
-    for c in src.chars() {
-        match c {
-            '\x08' => escaped += "\\b",
-            '\x0c' => escaped += "\\f",
-            '\n' => escaped += "\\n",
-            '\r' => escaped += "\\r",
-            '\t' => escaped += "\\t",
-            '"' => escaped += "\\\"",
-            '\\' => escaped += "\\\\",
-            c => escaped.push(c),
-        }
-    }
-    escaped
+fn get_csv_record(record: &[String], delimiter: u8, quote: u8) -> Result<String, Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .quote(quote)
+        .from_writer(Vec::new());
+    writer.write_record(record)?;
+    let w = writer.into_inner().map_err(|e| anyhow!("Could NOT write CSV. (error: {})", e))?;
+    Ok(String::from_utf8(w)?)
 }
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use log::{debug, error, info};
+
+use crate::model::{AudioProperty, GroupBy, MyPicture, MyTag};
+use crate::op::ReadTag;
+use crate::op::tag_impl::TagImpl;
+use crate::where_clause::{Key, WhereClause};
+
+lazy_static! {
+    static ref WHERE_GROUP: Mutex<Option<WhereGroup>> = Mutex::new(None);
+}
+
+struct WhereGroup {
+    where_clause: WhereClause,
+    group_by: GroupBy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum GroupKey {
+    Dir(Option<PathBuf>),
+    /// album-artist and album-title
+    Album(String, String),
+}
+
+struct TagValues {
+    tag: MyTag,
+    text: Option<String>,
+    values: Vec<String>,
+    numeric: Option<String>,
+}
+
+/// Values of the keys in `--where-group` read from a file, so the file is closed before the
+/// whole group is checked.
+struct TagSnapshot {
+    path: PathBuf,
+    tags: Vec<TagValues>,
+    props: Vec<(String, Vec<String>)>,
+    audio_props: Vec<(AudioProperty, Option<u32>)>,
+}
+
+impl TagSnapshot {
+    fn new(t: &dyn ReadTag, keys: &[&Key]) -> Self {
+        let mut snapshot = Self {
+            path: t.get_path().to_path_buf(),
+            tags: vec![],
+            props: vec![],
+            audio_props: vec![],
+        };
+        for key in keys {
+            match key {
+                Key::Tag(tag) if snapshot.get_tag(tag).is_none() =>
+                    snapshot.tags.push(TagValues {
+                        tag: **tag,
+                        text: t.get_text_tag(tag),
+                        values: t.get_text_tag_values(tag),
+                        numeric: t.get_numeric_tag_string(tag),
+                    }),
+                Key::Prop(key) if snapshot.props.iter().all(|(k, _)| k != key) =>
+                    snapshot.props.push((key.to_owned(), t.get_property(key).unwrap_or_else(|e| {
+                        error!("Get property {} failed: {}", key, e);
+                        vec![]
+                    }))),
+                Key::Audio(p) if snapshot.audio_props.iter().all(|(k, _)| k != p) =>
+                    snapshot.audio_props.push((*p, t.get_audio_property(p))),
+                _ => (),
+            }
+        }
+        snapshot
+    }
+
+    fn get_tag(&self, key: &MyTag) -> Option<&TagValues> {
+        self.tags.iter().find(|v| &v.tag == key)
+    }
+}
+
+impl ReadTag for TagSnapshot {
+    fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    fn get_text_tag(&self, key: &MyTag) -> Option<String> {
+        self.get_tag(key).and_then(|v| v.text.clone())
+    }
+
+    fn get_text_tag_values(&self, key: &MyTag) -> Vec<String> {
+        self.get_tag(key).map(|v| v.values.clone()).unwrap_or_default()
+    }
+
+    fn get_numeric_tag(&self, key: &MyTag) -> Option<u32> {
+        self.get_numeric_tag_string(key).and_then(|s| s.parse().ok())
+    }
+
+    fn get_numeric_tag_string(&self, key: &MyTag) -> Option<String> {
+        self.get_tag(key).and_then(|v| v.numeric.clone())
+    }
+
+    fn get_property_keys(&self) -> Result<Vec<String>, Error> {
+        Ok(self.props.iter().map(|(k, _)| k.clone()).collect())
+    }
+
+    fn get_property(&self, key: &str) -> Result<Vec<String>, Error> {
+        Ok(self.props.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, values)| values.clone())
+            .unwrap_or_default())
+    }
+
+    fn get_pictures(&self) -> Result<Vec<MyPicture>, Error> {
+        Ok(vec![])
+    }
+
+    fn get_audio_property(&self, key: &AudioProperty) -> Option<u32> {
+        self.audio_props.iter().find(|(k, _)| k == key).and_then(|(_, v)| *v)
+    }
+}
+
+/// Set `--where-group` of current run, all file iterators will only return files of the
+/// matched groups.
+pub fn set_where_group(where_string: &str, group_by: GroupBy) -> Result<(), Error> {
+    let where_clause = WhereClause::new_group(where_string)
+        .map_err(|s| anyhow!("{}", &s))?;
+    *WHERE_GROUP.lock().unwrap() = Some(WhereGroup { where_clause, group_by });
+    Ok(())
+}
+
+/// Files are kept in the original order, the iterator is returned as is without
+/// `--where-group`.
+pub(super) fn filter_by_where_group(it: Box<dyn Iterator<Item=PathBuf>>)
+                                    -> Box<dyn Iterator<Item=PathBuf>> {
+    let guard = WHERE_GROUP.lock().unwrap();
+    let where_group = match guard.as_ref() {
+        Some(where_group) => where_group,
+        None => return it,
+    };
+
+    let paths: Vec<PathBuf> = it.collect();
+    let keys = where_group.where_clause.get_keys();
+    let mut groups: BTreeMap<GroupKey, Vec<TagSnapshot>> = BTreeMap::new();
+    for path in &paths {
+        // each file is opened once, and closed before the next one
+        match TagImpl::new(path, true) {
+            Ok(t) => groups.entry(get_group_key(&t, &where_group.group_by))
+                .or_default()
+                .push(TagSnapshot::new(&t, &keys)),
+            Err(e) => error!("Could NOT get group of {:?}. (error: {})", path, e),
+        }
+    }
+
+    let group_count = groups.len();
+    let mut matched_count = 0;
+    let mut matched: HashSet<PathBuf> = HashSet::new();
+    for (key, files) in groups {
+        match check_group(&where_group.where_clause, &files) {
+            Ok(true) => {
+                debug!("group {:?} matched, files: {}", key, files.len());
+                matched_count += 1;
+                matched.extend(files.into_iter().map(|f| f.path));
+            }
+            Ok(false) => debug!("group {:?} not matched", key),
+            Err(e) => error!("Check group {:?} failed. (error: {})", key, e),
+        }
+    }
+    info!("{} of {} groups matched --where-group, {} files.",
+        matched_count, group_count, matched.len());
+
+    Box::new(paths.into_iter().filter(move |p| matched.contains(p)))
+}
+
+fn get_group_key(t: &dyn ReadTag, group_by: &GroupBy) -> GroupKey {
+    let dir = GroupKey::Dir(t.get_path().parent().map(|p| p.to_path_buf()));
+    match group_by {
+        GroupBy::Dir => dir,
+        GroupBy::Album => match t.get_text_tag(&MyTag::AlbumTitle) {
+            Some(album) if !album.trim().is_empty() => {
+                let album_artist = t.get_text_tag(&MyTag::AlbumArtist).unwrap_or_default();
+                GroupKey::Album(album_artist, album)
+            }
+            _ => dir,
+        },
+    }
+}
+
+fn check_group(where_clause: &WhereClause, snapshots: &[TagSnapshot]) -> Result<bool, Error> {
+    let files = snapshots.iter()
+        .map(|t| t as &dyn ReadTag)
+        .collect::<Vec<_>>();
+    where_clause.check_group(&files)
+        .ok_or_else(|| anyhow!("Some error in where group clause."))
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::io::{stdout, Write};
+
+use anyhow::Error;
+
+use crate::journal::{self, JournalEntry};
+use crate::op::Action;
+
+pub struct HistoryAction {
+    run_id: Option<String>,
+    limit: usize,
+}
+
+impl HistoryAction {
+    pub fn new(run_id: &Option<String>,
+               limit: usize) -> Result<Self, Error> {
+        Ok(Self {
+            run_id: run_id.clone(),
+            limit,
+        })
+    }
+}
+
+impl Action for HistoryAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        let stdout = stdout();
+        let mut writer = stdout.lock();
+        match &self.run_id {
+            Some(run_id) => {
+                let entries = journal::read_entries(run_id)?;
+                output_run(&mut writer, &entries)
+            }
+            None => {
+                for run_id in journal::get_run_ids()?.iter().rev().take(self.limit) {
+                    let entries = journal::read_entries(run_id)?;
+                    writeln!(writer, "{}", get_summary(run_id, &entries))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// One line for a run, e.g. `20240601-120000-000  2024-06-01 12:00:00  3 file(s)  music-tag-cli ...`
+fn get_summary(run_id: &str, entries: &[JournalEntry]) -> String {
+    let mut time = "";
+    let mut command = "";
+    let mut files = HashSet::new();
+    let mut renames = 0;
+    let mut undone_by = None;
+    for entry in entries {
+        match entry {
+            JournalEntry::Start { time: t, command: c, .. } => {
+                time = t;
+                command = c;
+            }
+            JournalEntry::Saved { path, .. } => {
+                files.insert(path);
+            }
+            JournalEntry::Rename { .. } => renames += 1,
+            JournalEntry::Undone { run_id, .. } => undone_by = Some(run_id),
+            _ => (),
+        }
+    }
+
+    let mut result = format!("{}  {}  {} file(s)", run_id, time, files.len());
+    if renames > 0 {
+        result.push_str(&format!(", {} rename(s)", renames));
+    }
+    result.push_str(&format!("  {}", command));
+    if let Some(by) = undone_by {
+        result.push_str(&format!("  (undone by {})", by));
+    }
+    result
+}
+
+fn output_run<W: Write>(writer: &mut W, entries: &[JournalEntry]) -> Result<(), Error> {
+    for entry in entries {
+        match entry {
+            JournalEntry::Start { run_id, time, command } =>
+                writeln!(writer, "-- RUN {} at {}: {} --", run_id, time, command)?,
+            JournalEntry::Tag { path, tag, old, new } =>
+                writeln!(writer, "{:?} tag {}: {:?} -> {:?}", path, tag, old, new)?,
+            JournalEntry::Prop { path, key, old, new } =>
+                writeln!(writer, "{:?} property {}: {:?} -> {:?}", path, key, old, new)?,
+            JournalEntry::Pictures { path, old, new } =>
+                writeln!(writer, "{:?} pictures: {:?} -> {:?}", path,
+                    old.iter().map(|p| &p.picture_type).collect::<Vec<_>>(), new)?,
+            JournalEntry::Rename { from, to } =>
+                writeln!(writer, "{:?} rename to {:?}", from, to)?,
+            JournalEntry::Undone { run_id, time } =>
+                writeln!(writer, "-- UNDONE by {} at {} --", run_id, time)?,
+            JournalEntry::Saved { .. } => (),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::journal::JournalEntry;
+
+    use super::get_summary;
+
+    #[test]
+    fn test_get_summary() {
+        let mut entries = vec![
+            JournalEntry::Start {
+                run_id: "20240601-120000-000".to_owned(),
+                time: "2024-06-01 12:00:00".to_owned(),
+                command: "music-tag-cli clear -t comment .".to_owned(),
+            },
+            JournalEntry::Saved { path: PathBuf::from("/a.flac"), size: 1, mtime: 1 },
+            JournalEntry::Saved { path: PathBuf::from("/b.flac"), size: 1, mtime: 1 },
+            JournalEntry::Saved { path: PathBuf::from("/a.flac"), size: 1, mtime: 2 },
+        ];
+        assert_eq!(get_summary("20240601-120000-000", &entries),
+                   "20240601-120000-000  2024-06-01 12:00:00  2 file(s)  \
+                   music-tag-cli clear -t comment .");
+
+        entries.push(JournalEntry::Undone {
+            run_id: "20240602-120000-000".to_owned(),
+            time: "2024-06-02 12:00:00".to_owned(),
+        });
+        assert!(get_summary("20240601-120000-000", &entries)
+            .ends_with("  (undone by 20240602-120000-000)"));
+    }
+}
@@ -1,64 +1,343 @@
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufRead, BufReader, Error as IOError, Read, stdin};
+use std::mem;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
 
 use anyhow::{anyhow, Error};
-use log::error;
+use csv::StringRecord;
+use encoding_rs::Encoding as EncodingRs;
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonData;
 
-use crate::model::{DEFAULT_PADDING, MyTag};
-use crate::op::Action;
+use crate::config::get_multi_value_separator;
+use crate::model::{AudioProperty, DataFormat, DEFAULT_PADDING, MyTag, PropsMode};
+use crate::op::{Action, from_csv_cell, get_ascii_char, get_encoding, is_utf8, MAX_PADDING,
+                MIN_PADDING, numeric_is_valid, PROP_COLUMN_PREFIX};
+use crate::op::document::{DocRecord, DocValue, Document, RECORDS_FIELD, SCHEMA_VERSION};
 use crate::op::tag_impl::{ReadWriteTag, TagImpl, WriteTagFile};
-use crate::util::json_de::iter_json_array;
+use crate::util::json_de::{iter_json_array, peek_json, read_json_fields_until};
+use crate::util::path::is_std_stream;
+use crate::where_clause::PartialDate;
 
 const READ_BUFFER_SIZE: usize = 16 * 1024;
 
+/// Format of the source file and how its records are written.
+pub struct ImpOptions {
+    pub format: Option<DataFormat>,
+    pub delimiter: Option<char>,
+    pub quote: char,
+    pub encoding_name: String,
+    pub props_mode: PropsMode,
+    pub validate_all: bool,
+    pub continue_on_error: bool,
+}
+
 pub struct ImpAction {
-    reader: Box<dyn Read>,
+    reader: Box<dyn BufRead>,
+    format: DataFormat,
+    delimiter: u8,
+    quote: u8,
+    encoding: &'static EncodingRs,
     base_dir: Rc<Option<PathBuf>>,
     dry_run: bool,
+    props_mode: PropsMode,
+    validate_all: bool,
+    continue_on_error: bool,
 }
 
 impl ImpAction {
     pub fn new<P>(src_file_path: P,
+                  options: ImpOptions,
                   base_dir: &Option<PathBuf>,
                   dry_run: bool) -> Result<Self, Error>
         where P: AsRef<Path>
     {
+        let ImpOptions {
+            format,
+            delimiter,
+            quote,
+            encoding_name,
+            props_mode,
+            validate_all,
+            continue_on_error,
+        } = options;
+        let format = format.unwrap_or_else(|| DataFormat::from_path(src_file_path.as_ref()));
+        let delimiter = match delimiter {
+            Some(c) => get_ascii_char("delimiter", c)?,
+            None => format.get_delimiter(),
+        };
+        let quote = get_ascii_char("quote", quote)?;
+        let encoding = get_encoding(&encoding_name)?;
+        if !format.is_csv() && !is_utf8(encoding) {
+            return Err(anyhow!("Parameter: encoding-name is only for CSV / TSV format, \
+            JSON must be UTF-8."));
+        }
+
         let reader = get_file_reader(src_file_path)?;
         let base_dir = Rc::new(get_base_dir(base_dir)?);
         Ok(Self {
             reader,
+            format,
+            delimiter,
+            quote,
+            encoding,
             base_dir,
             dry_run,
+            props_mode,
+            validate_all,
+            continue_on_error,
         })
     }
+
+    /// Both the document written by `exp` and the legacy JSON array are accepted, records are
+    /// read one by one.
+    fn do_json(&mut self, importer: &mut Importer) -> Result<(), Error> {
+        let base_dir = Rc::clone(&self.base_dir);
+        let (peek, mut reader) = peek_json(&mut self.reader)?;
+        if peek == b'{' {
+            let document: Document = read_json_fields_until(&mut reader, RECORDS_FIELD)
+                .map_err(|e| anyhow!("Could NOT process JSON header! (error {:?})", e))?;
+            let header = document.header;
+            if header.schema_version > SCHEMA_VERSION {
+                return Err(anyhow!("Unsupported schema version {} by {} {}, please upgrade.",
+                    header.schema_version, header.tool, header.tool_version));
+            }
+
+            // `-b` takes precedence over the export root
+            let base_dir = Some(base_dir.as_ref().clone()
+                .unwrap_or_else(|| PathBuf::from(&header.root)));
+            do_records(iter_json_array(&mut reader), importer,
+                       |record| from_doc_record(record, &base_dir))
+        } else {
+            do_records(iter_json_array(&mut reader), importer,
+                       |record| from_json_record(record, &base_dir))
+        }
+    }
+
+    /// Each line is a record as `exp` writes, empty lines are skipped.
+    fn do_ndjson(&mut self, importer: &mut Importer) -> Result<(), Error> {
+        let base_dir = Rc::clone(&self.base_dir);
+        for (i, line) in (&mut self.reader).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            importer.add(serde_json::from_str::<DocRecord>(&line)
+                .map_err(|e| anyhow!("Could NOT process JSON data at line {}! (error {})", i + 1, e))
+                .and_then(|record| from_doc_record(record, &base_dir)))?;
+        }
+        Ok(())
+    }
+
+    /// The whole source file is decoded at first, `encoding_rs` also removes the BOM which is
+    /// written by Excel.
+    fn do_csv(&mut self, importer: &mut Importer) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        self.reader.read_to_end(&mut bytes)?;
+        let (content, encoding, had_errors) = self.encoding.decode(&bytes);
+        if had_errors {
+            return Err(anyhow!("Could NOT decode source file by encoding {}.", encoding.name()));
+        }
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .from_reader(content.as_bytes());
+        let columns = get_csv_columns(reader.headers()?)?;
+        for row in reader.records() {
+            importer.add(row
+                .map_err(|e| anyhow!("Could NOT process CSV data! (error {})", e))
+                .and_then(|row| get_csv_record(&columns, &row))
+                .and_then(|record| from_json_record(record, &self.base_dir)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Elements are parsed as JSON value at first, so an element with invalid fields is a failed
+/// record, but invalid JSON stops the import.
+fn do_records<T, I, F>(it: I,
+                       importer: &mut Importer,
+                       to_record: F) -> Result<(), Error>
+    where T: DeserializeOwned,
+          I: Iterator<Item=Result<JsonData, IOError>>,
+          F: Fn(T) -> Result<ImpRecord, Error>
+{
+    for item in it {
+        match item {
+            Ok(value) => {
+                importer.add(serde_json::from_value::<T>(value)
+                    .map_err(|e| anyhow!("Could NOT process JSON data! (error {})", e))
+                    .and_then(&to_record))?;
+            }
+            Err(e) => {
+                return Err(anyhow!("Could NOT process JSON data! (error {:?})", e));
+            }
+        }
+    }
+    Ok(())
 }
 
-fn do_record(my_file: &JsonRecord,
-             base_directory: &Option<PathBuf>,
-             dry_run: bool) -> Result<(), Error> {
-    let path =
-        if let Some(base_dir) = base_directory {
-            Ok(base_dir.clone().join(&my_file.path))
+/// Records are written one by one, the first failed record stops the import, unless
+/// `continue_on_error` is set. With `validate_all`, all records are validated at first, and
+/// nothing is written if any record is invalid.
+struct Importer {
+    dry_run: bool,
+    props_mode: PropsMode,
+    continue_on_error: bool,
+    // state
+    count: usize,
+    imported: usize,
+    /// Validated records with their index, only for `validate_all`.
+    pending: Vec<(usize, ImpRecord)>,
+    failures: Vec<String>,
+    /// It is false while validating records for `validate_all`.
+    writing: bool,
+}
+
+impl Importer {
+    fn new(dry_run: bool,
+           props_mode: PropsMode,
+           validate_all: bool,
+           continue_on_error: bool) -> Self {
+        Self {
+            dry_run,
+            props_mode,
+            continue_on_error,
+            count: 0,
+            imported: 0,
+            pending: Vec::new(),
+            failures: Vec::new(),
+            writing: !validate_all,
+        }
+    }
+
+    fn add(&mut self, record: Result<ImpRecord, Error>) -> Result<(), Error> {
+        self.count += 1;
+        let index = self.count;
+        match record.and_then(check_record) {
+            Ok(record) if !self.writing => {
+                self.pending.push((index, record));
+                Ok(())
+            }
+            Ok(record) => self.write(index, &record),
+            Err(e) => self.fail(index, e),
+        }
+    }
+
+    fn write(&mut self, index: usize, record: &ImpRecord) -> Result<(), Error> {
+        match do_record(record, self.dry_run, self.props_mode) {
+            Ok(_) => {
+                self.imported += 1;
+                Ok(())
+            }
+            Err(e) => self.fail(index, e),
+        }
+    }
+
+    /// All failures are kept while validating, otherwise it stops the import unless
+    /// `continue_on_error` is set.
+    fn fail(&mut self, index: usize, e: Error) -> Result<(), Error> {
+        let failure = format!("record {}: {}", index, e);
+        if self.continue_on_error || !self.writing {
+            error!("Error: {}", &failure);
+            self.failures.push(failure);
+            Ok(())
         } else {
-            PathBuf::from_str(&my_file.path)
-        }?;
+            Err(anyhow!("{}, {} records before it are imported.", failure, self.imported))
+        }
+    }
+
+    /// Write validated records for `validate_all`, and show summary of failures.
+    fn finish(&mut self) -> Result<(), Error> {
+        if !self.writing {
+            if !self.failures.is_empty() && !self.continue_on_error {
+                return Err(anyhow!("{} of {} records are invalid, nothing is written.",
+                    self.failures.len(), self.count));
+            }
+            self.writing = true;
+            for (index, record) in mem::take(&mut self.pending) {
+                self.write(index, &record)?;
+            }
+        }
+
+        if self.continue_on_error {
+            info!("{} of {} records are imported, {} failed.",
+                self.imported, self.count, self.failures.len());
+            if !self.failures.is_empty() {
+                for failure in &self.failures {
+                    error!("  {}", failure);
+                }
+                return Err(anyhow!("{} of {} records failed.", self.failures.len(), self.count));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Problems of the record: file exists, tag types, numeric ranges and date formats.
+fn validate_record(record: &ImpRecord) -> Vec<String> {
+    let mut problems = record.problems.clone();
+    if !record.path.is_file() {
+        problems.push("file NOT found".to_owned());
+    }
+    for (tag, value) in &record.tags {
+        match value {
+            ImpValue::Num(u, _) if !numeric_is_valid(tag, *u) =>
+                problems.push(format!("tag {} exceed the boundary: {}", tag, u)),
+            ImpValue::Text(s) if tag.is_date() && !s.trim().is_empty()
+                && PartialDate::parse(s).is_none() =>
+                problems.push(format!("tag {} is NOT a valid date: {:?}", tag, s)),
+            _ => {}
+        }
+    }
+    problems
+}
+
+fn check_record(record: ImpRecord) -> Result<ImpRecord, Error> {
+    let problems = validate_record(&record);
+    if problems.is_empty() {
+        Ok(record)
+    } else {
+        Err(anyhow!("file {:?} is invalid, {}", &record.path, problems.join("; ")))
+    }
+}
 
-    let mut t = TagImpl::new(&path, dry_run)?;
+fn do_record(record: &ImpRecord,
+             dry_run: bool,
+             props_mode: PropsMode) -> Result<(), Error> {
+    let mut t = TagImpl::new(&record.path, dry_run)?;
 
-    if write_tag(my_file, &mut t) {
+    let props_changed = write_props(&record.props, &mut t, props_mode)?;
+    if write_tag(&record.tags, &mut t) || props_changed {
         t.save()
     } else {
         Ok(())
     }
 }
 
-fn get_file_reader<P>(source_file: P) -> Result<Box<dyn Read>, Error>
+fn get_path(path: &str, base_directory: &Option<PathBuf>) -> Result<PathBuf, Error> {
+    if let Some(base_dir) = base_directory {
+        Ok(base_dir.join(path))
+    } else {
+        Ok(PathBuf::from_str(path)?)
+    }
+}
+
+fn get_file_reader<P>(source_file: P) -> Result<Box<dyn BufRead>, Error>
     where P: AsRef<Path>
 {
+    if is_std_stream(source_file.as_ref()) {
+        return Ok(Box::new(
+            BufReader::with_capacity(READ_BUFFER_SIZE, stdin())
+        ));
+    }
+
     let f = File::open(source_file)?;
     Ok(Box::new(
         BufReader::with_capacity(READ_BUFFER_SIZE, f)
@@ -79,110 +358,488 @@ fn get_base_dir(base_directory: &Option<PathBuf>) -> Result<Option<PathBuf>, Err
 
 impl Action for ImpAction {
     fn do_any(&mut self) -> Result<(), Error> {
-        let mut it = iter_json_array(&mut self.reader);
-        while let Some(item) = it.next() {
-            match item {
-                Ok(record) => {
-                    let base_dir = Rc::clone(&self.base_dir);
-                    let dry_run = self.dry_run;
-                    do_record(&record, &base_dir, dry_run)
-                        .err()
-                        .map(|e| error!("Error: {}", e));
-                }
-                Err(e) => {
-                    return Err(anyhow!("Could NOT process JSON data! (error {:?})", e));
-                }
+        let mut importer = Importer::new(self.dry_run, self.props_mode, self.validate_all,
+                                         self.continue_on_error);
+        match self.format {
+            DataFormat::Json => self.do_json(&mut importer),
+            DataFormat::Csv | DataFormat::Tsv => self.do_csv(&mut importer),
+            DataFormat::Ndjson => self.do_ndjson(&mut importer),
+        }?;
+        importer.finish()
+    }
+}
+
+fn write_tag<T: ReadWriteTag>(tags: &[(&MyTag, ImpValue)], t: &mut T) -> bool {
+    for (tag, value) in tags {
+        match value {
+            ImpValue::Num(u, padding) => t.write_numeric_tag(tag, *u, *padding),
+            ImpValue::Text(s) => t.write_text_tag(tag, s),
+            ImpValue::Texts(values) => t.write_text_tag_values(tag, values),
+        }
+    }
+    !tags.is_empty()
+}
+
+/// Tag names are in kebab-case as `exp` writes them, snake_case is accepted as well.
+fn get_tag(key: &str) -> Option<&'static MyTag> {
+    MyTag::from_str(&key.replace('_', "-")).ok()
+}
+
+fn write_props<T: ReadWriteTag>(props: &Option<BTreeMap<String, Vec<String>>>,
+                                t: &mut T,
+                                props_mode: PropsMode) -> Result<bool, Error> {
+    let props = match props {
+        Some(props) => props,
+        None => return Ok(false),
+    };
+
+    let mut current = BTreeMap::new();
+    for key in t.get_property_keys()? {
+        let values = t.get_property(&key)?;
+        current.insert(key, values);
+    }
+
+    let changes = get_props_changes(&current, props, props_mode);
+    for (key, values) in &changes {
+        match values {
+            Some(values) => t.write_property(key, values),
+            None => t.remove_property(key),
+        }
+    }
+    Ok(!changes.is_empty())
+}
+
+/// Compare current properties with imported ones, a `None` value means the key should be removed.
+fn get_props_changes(current: &BTreeMap<String, Vec<String>>,
+                     imported: &BTreeMap<String, Vec<String>>,
+                     props_mode: PropsMode) -> Vec<(String, Option<Vec<String>>)> {
+    let imported = imported.iter()
+        .map(|(k, v)| (k.to_uppercase(), v))
+        .collect::<BTreeMap<_, _>>();
+    let is_empty = |key: &str| current.get(key).map_or(true, |v| v.is_empty());
+
+    let mut changes = Vec::new();
+    if props_mode == PropsMode::Replace {
+        for key in current.keys() {
+            if !imported.contains_key(key) {
+                changes.push((key.to_owned(), None));
             }
         }
-        Ok(())
     }
+
+    for (key, values) in imported {
+        if props_mode == PropsMode::FillMissing && !is_empty(&key) {
+            continue;
+        }
+
+        if values.is_empty() {
+            if !is_empty(&key) {
+                changes.push((key, None));
+            }
+        } else if current.get(&key) != Some(values) {
+            changes.push((key, Some(values.to_owned())));
+        }
+    }
+    changes
 }
 
-fn write_tag<T: ReadWriteTag>(my_file: &JsonRecord, t: &mut T) -> bool {
-    let mut changed = false;
+/// Tag value could be a number, a string, or an array of strings for multiple values.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+enum JsonValue {
+    Num(u32),
+    Single(String),
+    Multiple(Vec<String>),
+}
 
-    if let Some(ref s) = my_file.tags.title {
-        t.write_text_tag(&MyTag::Title, s);
-        if !changed { changed = true }
+impl JsonValue {
+    fn to_numeric(&self) -> Option<u32> {
+        match self {
+            JsonValue::Num(u) => Some(*u),
+            JsonValue::Single(s) => s.trim().parse::<u32>().ok(),
+            JsonValue::Multiple(_) => None,
+        }
     }
-    if let Some(ref s) = my_file.tags.artist {
-        t.write_text_tag(&MyTag::Artist, s);
-        if !changed { changed = true }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct JsonRecord {
+    path: String,
+    tags: BTreeMap<String, Option<JsonValue>>,
+    props: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// A record of any format, tags are resolved and path is joined with the base directory.
+#[derive(Debug)]
+struct ImpRecord {
+    path: PathBuf,
+    tags: Vec<(&'static MyTag, ImpValue)>,
+    props: Option<BTreeMap<String, Vec<String>>>,
+    /// Problems found while resolving tags, e.g. a numeric tag is NOT a number.
+    problems: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ImpValue {
+    /// Number and padding.
+    Num(u32, usize),
+    Text(String),
+    Texts(Vec<String>),
+}
+
+/// Numbers written as text (e.g. "01" in CSV / TSV) keep the padding of the text,
+/// JSON numbers are written with default padding.
+fn from_json_record(record: JsonRecord,
+                    base_directory: &Option<PathBuf>) -> Result<ImpRecord, Error> {
+    let path = get_path(&record.path, base_directory)?;
+    let mut tags = Vec::with_capacity(record.tags.len());
+    let mut problems = Vec::new();
+    for (key, value) in record.tags {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        let tag = match get_tag(&key) {
+            Some(tag) => tag,
+            None => {
+                warn!("file {:?} has unknown tag {}, Ignore it.", &path, key);
+                continue;
+            }
+        };
+
+        let value = if tag.is_numeric() {
+            let numeric = match &value {
+                JsonValue::Single(s) => get_numeric(s),
+                _ => value.to_numeric().map(|u| ImpValue::Num(u, DEFAULT_PADDING)),
+            };
+            match numeric {
+                Some(numeric) => numeric,
+                None => {
+                    problems.push(format!("tag {} is NOT a number: {:?}", tag, value));
+                    continue;
+                }
+            }
+        } else {
+            match value {
+                JsonValue::Num(u) => ImpValue::Text(u.to_string()),
+                JsonValue::Single(s) => ImpValue::Text(s),
+                JsonValue::Multiple(values) if tag.is_text() => ImpValue::Texts(values),
+                JsonValue::Multiple(values) =>
+                    ImpValue::Text(values.join(get_multi_value_separator())),
+            }
+        };
+        tags.push((tag, value));
     }
-    if let Some(ref s) = my_file.tags.album_title {
-        t.write_text_tag(&MyTag::AlbumTitle, s);
-        if !changed { changed = true }
+    Ok(ImpRecord { path, tags, props: record.props, problems })
+}
+
+/// The padding of numbers is kept by their text, if it is NOT changed.
+fn from_doc_record(record: DocRecord,
+                   base_directory: &Option<PathBuf>) -> Result<ImpRecord, Error> {
+    let path = get_path(&record.path, base_directory)?;
+    let mut tags = Vec::with_capacity(record.tags.len());
+    let mut problems = Vec::new();
+    for (key, value) in record.tags {
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        let tag = match get_tag(&key) {
+            Some(tag) => tag,
+            None => {
+                warn!("file {:?} has unknown tag {}, Ignore it.", &path, key);
+                continue;
+            }
+        };
+
+        let imp_value = match (tag.is_numeric(), &value) {
+            (true, DocValue::Num { value, text }) =>
+                Some(ImpValue::Num(*value, get_padding(text, *value))),
+            (true, DocValue::Text { values }) if values.len() == 1 => get_numeric(&values[0]),
+            (true, DocValue::Date { text }) => get_numeric(text),
+            (true, _) => None,
+            (false, DocValue::Num { text, .. }) | (false, DocValue::Date { text }) =>
+                Some(ImpValue::Text(text.to_owned())),
+            (false, DocValue::Text { values }) if values.len() > 1 && tag.is_text() =>
+                Some(ImpValue::Texts(values.to_owned())),
+            (false, DocValue::Text { values }) =>
+                Some(ImpValue::Text(values.join(get_multi_value_separator()))),
+        };
+        match imp_value {
+            Some(imp_value) => tags.push((tag, imp_value)),
+            None => problems.push(format!("tag {} is NOT a number: {:?}", tag, value)),
+        }
     }
-    if let Some(ref s) = my_file.tags.album_artist {
-        t.write_text_tag(&MyTag::AlbumArtist, s);
-        if !changed { changed = true }
+    Ok(ImpRecord { path, tags, props: record.props, problems })
+}
+
+fn get_numeric(text: &str) -> Option<ImpValue> {
+    let s = text.trim();
+    s.parse::<u32>().ok().map(|u| ImpValue::Num(u, s.len().clamp(MIN_PADDING, MAX_PADDING)))
+}
+
+fn get_padding(text: &str, value: u32) -> usize {
+    match get_numeric(text) {
+        Some(ImpValue::Num(u, padding)) if u == value => padding,
+        _ => DEFAULT_PADDING,
     }
-    if let Some(ref s) = my_file.tags.genre {
-        t.write_text_tag(&MyTag::Genre, s);
-        if !changed { changed = true }
+}
+
+#[derive(Debug, PartialEq)]
+enum CsvColumn {
+    Path,
+    Tag(&'static MyTag),
+    Prop(String),
+    /// Audio properties and unknown columns.
+    Ignored,
+}
+
+/// Columns are mapped by header, so they could be in any order, or be removed in spreadsheet.
+fn get_csv_columns(header: &StringRecord) -> Result<Vec<CsvColumn>, Error> {
+    let columns = header.iter()
+        .map(|name| {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("path") {
+                CsvColumn::Path
+            } else if let Some(key) = name.strip_prefix(PROP_COLUMN_PREFIX) {
+                CsvColumn::Prop(key.to_uppercase())
+            } else if let Some(tag) = get_tag(&name.to_lowercase()) {
+                CsvColumn::Tag(tag)
+            } else if AudioProperty::from_str(name).is_some() {
+                CsvColumn::Ignored
+            } else {
+                warn!("Unknown column {:?} in header, Ignore it.", name);
+                CsvColumn::Ignored
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if columns.contains(&CsvColumn::Path) {
+        Ok(columns)
+    } else {
+        Err(anyhow!("Column `path` is NOT found in header."))
     }
-    if let Some(ref s) = my_file.tags.composer {
-        t.write_text_tag(&MyTag::Composer, s);
-        if !changed { changed = true }
+}
+
+/// Empty cells are ignored as `null` in JSON, multiple values of text tags and properties are
+/// written as a JSON array.
+fn get_csv_record(columns: &[CsvColumn], row: &StringRecord) -> Result<JsonRecord, Error> {
+    let mut path = None;
+    let mut tags = BTreeMap::new();
+    let mut props = BTreeMap::new();
+    for (column, cell) in columns.iter().zip(row.iter()) {
+        if cell.is_empty() {
+            continue;
+        }
+        match column {
+            CsvColumn::Path => path = Some(cell.to_owned()),
+            CsvColumn::Tag(tag) => {
+                let value = match from_csv_cell(cell) {
+                    values if tag.is_text() && values.len() != 1 => JsonValue::Multiple(values),
+                    _ => JsonValue::Single(cell.to_owned()),
+                };
+                tags.insert(tag.to_string(), Some(value));
+            }
+            CsvColumn::Prop(key) => {
+                props.insert(key.to_owned(), from_csv_cell(cell));
+            }
+            CsvColumn::Ignored => {}
+        }
     }
 
-    if let Some(u) = my_file.tags.year {
-        t.write_numeric_tag(&MyTag::Year, u, DEFAULT_PADDING);
-        if !changed { changed = true }
+    match path {
+        Some(path) => Ok(JsonRecord {
+            path,
+            tags,
+            props: if props.is_empty() { None } else { Some(props) },
+        }),
+        None => Err(anyhow!("Line {} has no path, Ignore it.",
+            row.position().map_or(0, |p| p.line()))),
     }
-    if let Some(u) = my_file.tags.track_number {
-        t.write_numeric_tag(&MyTag::TrackNumber, u, DEFAULT_PADDING);
-        if !changed { changed = true }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use anyhow::anyhow;
+    use csv::StringRecord;
+
+    use crate::model::{MyTag, PropsMode};
+    use crate::op::document::{DocRecord, Document, RECORDS_FIELD};
+    use crate::util::json_de::{iter_json_array, peek_json, read_json_fields_until};
+
+    use crate::op::{from_csv_cell, to_csv_cell};
+
+    use super::{CsvColumn, from_doc_record, from_json_record, get_csv_columns, get_csv_record, get_props_changes,
+                get_tag, ImpRecord, Importer, ImpValue, JsonValue, validate_record};
+
+    fn to_record(path: &str, tags: Vec<(&'static MyTag, ImpValue)>) -> ImpRecord {
+        ImpRecord { path: PathBuf::from(path), tags, props: None, problems: vec![] }
     }
-    if let Some(u) = my_file.tags.track_total {
-        t.write_numeric_tag(&MyTag::TrackTotal, u, DEFAULT_PADDING);
-        if !changed { changed = true }
+
+    fn to_map(items: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        items.iter()
+            .map(|(k, v)| (k.to_string(), v.iter().map(|s| s.to_string()).collect()))
+            .collect()
     }
-    if let Some(u) = my_file.tags.disc_number {
-        t.write_numeric_tag(&MyTag::DiscNumber, u, DEFAULT_PADDING);
-        if !changed { changed = true }
+
+    #[test]
+    fn test_get_props_changes() {
+        let current = to_map(&[("BPM", &["120"]), ("LABEL", &["RCA"])]);
+        let imported = to_map(&[("bpm", &["128"]), ("ISRC", &["USRC17607839"]),
+            ("LABEL", &["RCA"])]);
+
+        let changes = get_props_changes(&current, &imported, PropsMode::Merge);
+        assert_eq!(changes, vec![
+            ("BPM".to_owned(), Some(vec!["128".to_owned()])),
+            ("ISRC".to_owned(), Some(vec!["USRC17607839".to_owned()])),
+        ]);
+
+        let changes = get_props_changes(&current, &imported, PropsMode::FillMissing);
+        assert_eq!(changes, vec![
+            ("ISRC".to_owned(), Some(vec!["USRC17607839".to_owned()])),
+        ]);
+
+        let imported = to_map(&[("BPM", &["120"]), ("ISRC", &[])]);
+        let changes = get_props_changes(&current, &imported, PropsMode::Replace);
+        assert_eq!(changes, vec![("LABEL".to_owned(), None)]);
     }
-    if let Some(u) = my_file.tags.disc_total {
-        t.write_numeric_tag(&MyTag::DiscTotal, u, DEFAULT_PADDING);
-        if !changed { changed = true }
+
+    #[test]
+    fn test_get_tag() {
+        assert_eq!(get_tag("album-artist"), Some(&MyTag::AlbumArtist));
+        assert_eq!(get_tag("track_number"), Some(&MyTag::TrackNumber));
+        assert_eq!(get_tag("musicbrainz-album-id"), Some(&MyTag::MusicbrainzAlbumId));
+        assert_eq!(get_tag("unknown"), None);
     }
 
-    if let Some(ref s) = my_file.tags.comment {
-        t.write_text_tag(&MyTag::Comment, s);
-        if !changed { changed = true }
+    #[test]
+    fn test_get_csv_record() {
+        let header = StringRecord::from(vec!["title", "Path", "track-number", "prop:bpm",
+                                             "bitrate", "artist"]);
+        let columns = get_csv_columns(&header).unwrap();
+        assert_eq!(columns, vec![CsvColumn::Tag(&MyTag::Title), CsvColumn::Path,
+                                 CsvColumn::Tag(&MyTag::TrackNumber),
+                                 CsvColumn::Prop("BPM".to_owned()), CsvColumn::Ignored,
+                                 CsvColumn::Tag(&MyTag::Artist)]);
+
+        let row = StringRecord::from(vec!["Hello; World", "a/01.flac", "01", "120", "320",
+                                          r#"["A","B"]"#]);
+        let record = get_csv_record(&columns, &row).unwrap();
+        assert_eq!(record.path, "a/01.flac");
+        assert_eq!(record.tags.len(), 3);
+        assert!(matches!(record.tags.get("title"),
+            Some(Some(JsonValue::Single(s))) if s == "Hello; World"));
+        assert!(matches!(record.tags.get("artist"),
+            Some(Some(JsonValue::Multiple(v))) if v == &["A", "B"]));
+        assert_eq!(record.props.as_ref().unwrap().get("BPM"), Some(&vec!["120".to_owned()]));
+        // padding is kept by the text of the cell
+        let record = from_json_record(record, &None).unwrap();
+        assert!(record.tags.contains(&(&MyTag::TrackNumber, ImpValue::Num(1, 2))));
+
+        for values in [vec!["A; B"], vec!["A", "B"], vec![r#"["A"]"#], vec!["[A]"]] {
+            let values = values.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            assert_eq!(from_csv_cell(&to_csv_cell(&values)), values);
+        }
+
+        let row = StringRecord::from(vec!["Hello", "", "", "", "", ""]);
+        assert!(get_csv_record(&columns, &row).is_err());
+
+        let header = StringRecord::from(vec!["title", "artist"]);
+        assert!(get_csv_columns(&header).is_err());
     }
-    if let Some(ref s) = my_file.tags.copyright {
-        t.write_text_tag(&MyTag::Copyright, s);
-        if !changed { changed = true }
+
+    #[test]
+    fn test_from_doc_record() {
+        let json = r#"
+        {
+          "header": {"schema_version": 2, "tool": "music-tag-cli", "tool_version": "1.0.4",
+            "root": "/music"},
+          "records": [
+            {"path": "a/01.flac", "size": 1024, "tags": {
+              "title": {"type": "text", "values": ["Hello"]},
+              "artist": {"type": "text", "values": ["A", "B"]},
+              "track-number": {"type": "num", "value": 1, "text": "001"},
+              "disc-number": {"type": "num", "value": 2, "text": "1"},
+              "date": {"type": "date", "text": "2001-5-1"},
+              "year": {"type": "text", "values": ["2001"]},
+              "genre": null
+            }, "props": {"BPM": ["120"]}, "audio": {"bitrate": 320}},
+            {"path": "a/02.flac", "tags": {}}
+          ]
+        }"#;
+        let (peek, mut reader) = peek_json(Cursor::new(json)).unwrap();
+        assert_eq!(peek, b'{');
+        let document: Document = read_json_fields_until(&mut reader, RECORDS_FIELD).unwrap();
+        assert_eq!(document.header.root, "/music");
+
+        let records = iter_json_array::<DocRecord, _>(&mut reader)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+
+        let base_dir = Some(PathBuf::from(&document.header.root));
+        let record = from_doc_record(records.into_iter().next().unwrap(), &base_dir).unwrap();
+        assert_eq!(record.path, PathBuf::from("/music/a/01.flac"));
+        assert_eq!(record.props.unwrap().get("BPM"), Some(&vec!["120".to_owned()]));
+        assert_eq!(record.tags, vec![
+            (&MyTag::Artist, ImpValue::Texts(vec!["A".to_owned(), "B".to_owned()])),
+            (&MyTag::Date, ImpValue::Text("2001-5-1".to_owned())),
+            (&MyTag::DiscNumber, ImpValue::Num(2, 2)),
+            (&MyTag::Title, ImpValue::Text("Hello".to_owned())),
+            (&MyTag::TrackNumber, ImpValue::Num(1, 3)),
+            (&MyTag::Year, ImpValue::Num(2001, 4)),
+        ]);
+
+        let (peek, _) = peek_json(Cursor::new(" [{}]")).unwrap();
+        assert_eq!(peek, b'[');
+        let json = r#"{"header": {"schema_version": 2}, "other": 1, "records": []}"#;
+        assert!(read_json_fields_until::<Document, _>(Cursor::new(json), RECORDS_FIELD).is_err());
     }
-    if let Some(ref s) = my_file.tags.lyrics {
-        t.write_text_tag(&MyTag::Lyrics, s);
-        if !changed { changed = true }
+
+    #[test]
+    fn test_validate_record() {
+        let record = to_record("Cargo.toml", vec![
+            (&MyTag::Year, ImpValue::Num(2001, 4)),
+            (&MyTag::TrackNumber, ImpValue::Num(0, 2)),
+            (&MyTag::Date, ImpValue::Text("2001-5-1".to_owned())),
+            (&MyTag::OriginalDate, ImpValue::Text("2001-13".to_owned())),
+        ]);
+        assert_eq!(validate_record(&record), vec![
+            "tag track-number exceed the boundary: 0".to_owned(),
+            "tag original-date is NOT a valid date: \"2001-13\"".to_owned(),
+        ]);
+
+        let mut record = to_record("not-exists.flac", vec![]);
+        record.problems.push("tag year is NOT a number: Single(\"x\")".to_owned());
+        assert_eq!(validate_record(&record).len(), 2);
     }
-    changed
-}
 
-#[derive(Serialize, Deserialize, Debug)]
-pub(crate) struct JsonTag {
-    title: Option<String>,
-    artist: Option<String>,
-    album_title: Option<String>,
-    album_artist: Option<String>,
-    genre: Option<String>,
-    composer: Option<String>,
-    year: Option<u32>,
-    track_number: Option<u32>,
-    track_total: Option<u32>,
-    disc_number: Option<u32>,
-    disc_total: Option<u32>,
-    date: Option<String>,
-    comment: Option<String>,
-    copyright: Option<String>,
-    lyrics: Option<String>,
-}
+    #[test]
+    fn test_importer() {
+        let mut importer = Importer::new(true, PropsMode::Merge, false, false);
+        assert!(importer.add(Err(anyhow!("bad"))).is_err());
 
-#[derive(Serialize, Deserialize, Debug)]
-struct JsonRecord {
-    path: String,
-    tags: JsonTag,
+        let mut importer = Importer::new(true, PropsMode::Merge, true, false);
+        assert!(importer.add(Err(anyhow!("bad"))).is_ok());
+        assert!(importer.add(Ok(to_record("Cargo.toml", vec![]))).is_ok());
+        assert!(importer.add(Ok(to_record("not-exists.flac", vec![]))).is_ok());
+        assert_eq!(importer.failures.len(), 2);
+        assert_eq!(importer.pending.len(), 1);
+        assert!(importer.finish().is_err());
+        assert_eq!(importer.imported, 0);
+    }
+
+    #[test]
+    fn test_json_value() {
+        let v: JsonValue = serde_json::from_str("128").unwrap();
+        assert_eq!(v.to_numeric(), Some(128));
+        let v: JsonValue = serde_json::from_str("\"07\"").unwrap();
+        assert_eq!(v.to_numeric(), Some(7));
+        let v: JsonValue = serde_json::from_str("[\"A\", \"B\"]").unwrap();
+        assert_eq!(v.to_numeric(), None);
+    }
 }
@@ -253,6 +253,10 @@ impl ReadAction for LrcExpAction {
         false
     }
 
+    fn with_audio_properties(&self) -> bool {
+        false
+    }
+
     fn get_content(&self, _path: &Path, v: &MyValues) -> Result<Option<String>, Error> {
         Ok(v.get_text(&MyTag::Lyrics).map(|t| t.to_owned()))
     }
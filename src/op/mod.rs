@@ -1,6 +1,6 @@
 extern crate lazy_static;
 
-use std::{fs, iter};
+use std::{fs, iter, slice};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
@@ -10,46 +10,67 @@ use anyhow::{anyhow, Error};
 use chrono::NaiveDate;
 use encoding_rs::Encoding as EncodingRs;
 use itertools::Itertools;
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
 use walkdir::WalkDir;
 
-use crate::model::{ConstValue, FilenameExistPolicy, ModifyMode, MyTag};
+use crate::config::get_multi_value_separator;
+use crate::model::{ALL_AUDIO_PROPERTIES, AudioProperty, ConstValue, FilenameExistPolicy, ModifyMode,
+                   MyTag};
 use crate::op::tag_impl::ReadWriteTag;
 use crate::util::path::get_dup_path;
+use crate::util::sanitize::{SanitizeReport, Sanitizer};
 use crate::where_clause::WhereClause;
 
+pub use self::art::ArtAction;
 pub use self::clear::ClearAction;
 pub use self::conv_en::ConvEnAction;
 pub use self::conv_utf8::ConvUtf8Action;
 pub use self::conv_zh::ConvZhAction;
-pub use self::exp::ExpAction;
-pub use self::imp::ImpAction;
+pub use self::exp::{ExpAction, ExpOptions};
+pub use self::group::set_where_group;
+pub use self::history::HistoryAction;
+pub use self::imp::{ImpAction, ImpOptions};
 pub use self::lrc::{LrcExpAction, LrcImpAction};
 pub use self::mod_num::ModNumAction;
 pub use self::mod_text_const::ModTextConstAction;
 pub use self::mod_text_regex::ModTextRegexAction;
+pub use self::organize::{OrganizeAction, OrganizeOptions};
+pub use self::multi_values::{JoinValuesAction, SplitValuesAction};
+pub use self::prop::PropAction;
 pub use self::ren::RenAction;
 pub use self::set_const::SetConstAction;
-pub use self::set_name::{get_tags_from_template, SetNameAction};
+pub use self::set_expr::SetExprAction;
+pub use self::set_name::SetNameAction;
 pub use self::set_seq::SetSeqAction;
+use self::group::filter_by_where_group;
 use self::tag_impl::{is_available_suffix, TagImpl};
 pub use self::tag_impl::ReadTag;
+pub use self::undo::UndoAction;
 pub use self::view::ViewAction;
 
+mod art;
 mod clear;
 mod conv_en;
 mod conv_utf8;
 mod conv_zh;
+mod document;
 mod exp;
+mod group;
+mod history;
 mod imp;
 mod lrc;
 mod mod_num;
 mod mod_text_const;
 mod mod_text_regex;
+mod organize;
+mod multi_values;
+mod prop;
 mod set_const;
+mod set_expr;
 mod set_name;
 mod set_seq;
 mod ren;
+mod undo;
 mod view;
 
 mod tag_impl;
@@ -57,6 +78,7 @@ mod tag_impl;
 #[derive(Debug)]
 enum MyValue {
     Text(String),
+    Texts(Vec<String>, String),
     Num(u32, String),
     None,
 }
@@ -65,6 +87,8 @@ enum MyValue {
 struct MyValues<'a> {
     raw: Option<HashMap<&'a MyTag, MyValue>>,
     properties: Option<BTreeMap<String, Vec<String>>>,
+    // only available properties
+    audio_properties: Option<Vec<(AudioProperty, u32)>>,
 }
 
 impl MyValues<'_> {
@@ -88,6 +112,7 @@ impl MyValues<'_> {
             if let Some(v) = r.get(tag) {
                 match v {
                     MyValue::Text(s) => Some(s),
+                    MyValue::Texts(_, s) => Some(s),
                     MyValue::Num(_, s) => Some(s),
                     MyValue::None => None,
                 }
@@ -99,12 +124,14 @@ impl MyValues<'_> {
         }
     }
 
-    fn get_num(&self, tag: &MyTag) -> Option<u32> {
+    fn get_texts(&self, tag: &MyTag) -> Option<&[String]> {
         if let Some(r) = &self.raw {
             if let Some(v) = r.get(tag) {
                 match v {
-                    MyValue::Num(u, _) => Some(*u),
-                    _ => None
+                    MyValue::Text(s) => Some(slice::from_ref(s)),
+                    MyValue::Texts(v, _) => Some(v),
+                    MyValue::Num(_, s) => Some(slice::from_ref(s)),
+                    MyValue::None => None,
                 }
             } else {
                 None
@@ -129,6 +156,10 @@ impl MyValues<'_> {
             None
         }
     }
+
+    fn get_audio_properties(&self) -> Option<&[(AudioProperty, u32)]> {
+        self.audio_properties.as_deref()
+    }
 }
 
 pub trait Action {
@@ -214,6 +245,8 @@ fn sorted_filtered_files<P>(path: P) -> Result<Vec<PathBuf>, Error>
 trait ReadAction: WalkAction {
     fn with_properties(&self) -> bool;
 
+    fn with_audio_properties(&self) -> bool;
+
     fn do_one_file_read(&mut self, path: &Path) -> Result<bool, Error> {
         let v = self.read_tags_value(path)?;
         if let Some(content) = self.get_content(path, &v)? {
@@ -236,11 +269,11 @@ trait ReadAction: WalkAction {
     fn read_tags(&self, t: &dyn ReadTag) -> Result<MyValues, Error> {
         let tags = self.tags();
         if tags.is_empty() {
-            return Ok(MyValues { raw: None, properties: None });
+            return Ok(MyValues { raw: None, properties: None, audio_properties: None });
         }
 
         if !self.check_where(t)? {
-            return Ok(MyValues { raw: None, properties: None });
+            return Ok(MyValues { raw: None, properties: None, audio_properties: None });
         }
 
         let mut map: HashMap<&MyTag, MyValue> = HashMap::with_capacity(tags.len());
@@ -255,7 +288,13 @@ trait ReadAction: WalkAction {
             None
         };
 
-        Ok(MyValues { raw: Some(map), properties })
+        let audio_properties = if self.with_audio_properties() {
+            Some(get_audio_properties(t))
+        } else {
+            None
+        };
+
+        Ok(MyValues { raw: Some(map), properties, audio_properties })
     }
 }
 
@@ -283,15 +322,7 @@ trait WriteTextAction: WriteAction {
         let mut any_changed = false;
         for tag in self.tags() {
             let changed = match tag {
-                MyTag::Title
-                | MyTag::Artist
-                | MyTag::AlbumTitle
-                | MyTag::Genre
-                | MyTag::Comment
-                | MyTag::AlbumArtist
-                | MyTag::Composer
-                | MyTag::Copyright
-                | MyTag::Lyrics => self.set_text_tag(t, tag),
+                tag if tag.is_text() => self.set_text_tag(t, tag),
 
                 _ => false,
             };
@@ -313,6 +344,11 @@ trait WriteTextAction: WriteAction {
 
 trait WriteTextForCurrentAction: WriteTextAction {
     fn set_text_tag_impl(&self, t: &mut dyn ReadWriteTag, tag: &MyTag) -> bool {
+        let values = t.get_text_tag_values(tag);
+        if values.len() > 1 {
+            return self.set_text_tag_values(t, tag, &values);
+        }
+
         let current = &t.get_text_tag(tag);
         let new_value = self.get_new_text(current);
 
@@ -324,6 +360,28 @@ trait WriteTextForCurrentAction: WriteTextAction {
         }
     }
 
+    /// Apply `get_new_text` to each value of a multi-value tag.
+    fn set_text_tag_values(&self,
+                           t: &mut dyn ReadWriteTag,
+                           tag: &MyTag,
+                           values: &[String]) -> bool {
+        let mut changed = false;
+        let new_values = values.iter()
+            .map(|v| match self.get_new_text(&Some(v.to_owned())) {
+                Some(new_v) => {
+                    changed = true;
+                    new_v
+                }
+                None => v.to_owned(),
+            })
+            .collect::<Vec<_>>();
+
+        if changed {
+            t.write_text_tag_values(tag, &new_values);
+        }
+        changed
+    }
+
     fn get_new_text(&self, current: &Option<String>) -> Option<String>;
 }
 
@@ -340,11 +398,7 @@ trait WriteNumAction: WriteAction {
         let mut any_changed = false;
         for tag in self.tags() {
             let changed = match tag {
-                MyTag::Year
-                | MyTag::TrackNumber
-                | MyTag::TrackTotal
-                | MyTag::DiscNumber
-                | MyTag::DiscTotal => self.set_numeric_tag(t, tag),
+                tag if tag.is_numeric() => self.set_numeric_tag(t, tag),
 
                 _ => false,
             };
@@ -396,23 +450,13 @@ trait WriteAllAction: WriteAction {
         let mut any_changed = false;
         for tag in self.tags() {
             let changed = match tag {
-                MyTag::Title
-                | MyTag::Artist
-                | MyTag::AlbumTitle
-                | MyTag::Genre
-                | MyTag::Comment
-                | MyTag::AlbumArtist
-                | MyTag::Composer
-                | MyTag::Copyright
-                | MyTag::Lyrics => self.set_text_tag(t, tag),
-
-                MyTag::Year
-                | MyTag::TrackNumber
-                | MyTag::TrackTotal
-                | MyTag::DiscNumber
-                | MyTag::DiscTotal => self.set_numeric_tag(t, &tag),
-
-                MyTag::Date => self.set_date_tag(t, &tag),
+                tag if tag.is_text() => self.set_text_tag(t, tag),
+
+                tag if tag.is_numeric() => self.set_numeric_tag(t, tag),
+
+                tag if tag.is_date() => self.set_date_tag(t, tag),
+
+                _ => false,
             };
             if !any_changed {
                 any_changed = changed;
@@ -479,7 +523,7 @@ fn get_file_iterator<P>(dir: P) -> Result<Box<dyn Iterator<Item=PathBuf>>, Error
     let path = dir.as_ref();
     if path.is_dir() {
         debug!("dir: {:?}", path);
-        Ok(Box::new(
+        Ok(filter_by_where_group(Box::new(
             WalkDir::new(path)
                 .follow_links(false)
                 .into_iter()
@@ -495,10 +539,10 @@ fn get_file_iterator<P>(dir: P) -> Result<Box<dyn Iterator<Item=PathBuf>>, Error
                     |(e, m)|
                         m.is_file() && is_available_suffix(&e.path().to_string_lossy()))
                 .map(|(e, _)| e.into_path())
-        ))
+        )))
     } else if path.is_file() {
         debug!("file: {:?}", path);
-        Ok(Box::new(iter::once(PathBuf::from(path))))
+        Ok(filter_by_where_group(Box::new(iter::once(PathBuf::from(path)))))
     } else {
         Err(anyhow!("Could NOT perform action on path: {:?}. Please check the path.", path))
     }
@@ -550,7 +594,17 @@ fn string_to_option(new_value: String, value: &str) -> Option<String> {
 }
 
 fn get_tags_value(t: &dyn ReadTag, tag: &MyTag) -> MyValue {
-    if tag.is_text() || tag.is_date() {
+    if tag.is_text() {
+        let values = t.get_text_tag_values(tag);
+        if values.len() > 1 {
+            let joined = values.join(get_multi_value_separator());
+            return MyValue::Texts(values, joined);
+        }
+        if let Some(s) = t.get_text_tag(tag) {
+            return MyValue::Text(s);
+        }
+        MyValue::None
+    } else if tag.is_date() {
         if let Some(s) = t.get_text_tag(tag) {
             return MyValue::Text(s);
         }
@@ -568,6 +622,12 @@ fn get_tags_value(t: &dyn ReadTag, tag: &MyTag) -> MyValue {
     }
 }
 
+fn get_audio_properties<T: ReadTag + ?Sized>(t: &T) -> Vec<(AudioProperty, u32)> {
+    ALL_AUDIO_PROPERTIES.iter()
+        .filter_map(|p| t.get_audio_property(p).map(|v| (*p, v)))
+        .collect()
+}
+
 fn get_properties<T: ReadTag + ?Sized>(t: &T) -> Option<BTreeMap<String, Vec<String>>> {
     t.get_property_keys()
         .map_or_else(
@@ -595,6 +655,28 @@ const MIN_NUMBER: u32 = 0;
 const MIN_NATURAL_NUMBER: u32 = 1;
 const MAX_NUMBER: u32 = u16::MAX as u32;
 
+/// Header prefix of property columns in CSV / TSV, e.g. `prop:BPM`.
+const PROP_COLUMN_PREFIX: &str = "prop:";
+
+/// Multiple values in a CSV / TSV cell are written as a JSON array, e.g. `["A","B"]`, so a
+/// single value is NOT split even if it contains `multi_value_separator`.
+fn to_csv_cell(values: &[String]) -> String {
+    match values {
+        [value] if from_csv_cell(value) == values => value.to_owned(),
+        _ if values.is_empty() => String::new(),
+        _ => serde_json::to_string(values).unwrap_or_default(),
+    }
+}
+
+fn from_csv_cell(cell: &str) -> Vec<String> {
+    if cell.starts_with('[') {
+        if let Ok(values) = serde_json::from_str::<Vec<String>>(cell) {
+            return values;
+        }
+    }
+    vec![cell.to_owned()]
+}
+
 fn check_numeric_date_tags_must_be_overwrite(tags: &[MyTag],
                                              mode: &ModifyMode) -> Result<(), Error> {
     if exist_numeric_or_date(tags) && mode != &ModifyMode::Overwrite {
@@ -648,7 +730,8 @@ fn check_value_is_ok(tags: &[MyTag], value: &ConstValue) -> Result<(), Error> {
                 && (tags.contains(&MyTag::TrackNumber)
                 || tags.contains(&MyTag::TrackTotal)
                 || tags.contains(&MyTag::DiscNumber)
-                || tags.contains(&MyTag::DiscTotal)) {
+                || tags.contains(&MyTag::DiscTotal)
+                || tags.contains(&MyTag::Bpm)) {
                 Err(anyhow!("Parameter: value must great or equal {}, when use tags: {:?}",
                     MIN_NATURAL_NUMBER, tags))
             } else {
@@ -682,6 +765,7 @@ fn numeric_is_valid(tag: &MyTag, numeric_value: u32) -> bool {
         MyTag::TrackTotal => MIN_NATURAL_NUMBER <= numeric_value && numeric_value <= MAX_NUMBER,
         MyTag::DiscNumber => MIN_NATURAL_NUMBER <= numeric_value && numeric_value <= MAX_NUMBER,
         MyTag::DiscTotal => MIN_NATURAL_NUMBER <= numeric_value && numeric_value <= MAX_NUMBER,
+        MyTag::Bpm => (MIN_NATURAL_NUMBER..=MAX_NUMBER).contains(&numeric_value),
         _ => false
     }
 }
@@ -691,6 +775,15 @@ fn get_encoding(enc_name: &str) -> Result<&'static EncodingRs, Error> {
     encoding.ok_or(anyhow!("Unsupported encoding: {}", enc_name))
 }
 
+/// Delimiter and quote of CSV / TSV must be a single byte.
+fn get_ascii_char(param_name: &str, c: char) -> Result<u8, Error> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(anyhow!("Parameter: {} must be an ASCII character, but it is {:?}.", param_name, c))
+    }
+}
+
 fn check_encoding_not_utf8(e: &'static EncodingRs) -> Result<(), Error> {
     if is_utf8(e) {
         Err(anyhow!("Encoding could NOT be UTF-8."))
@@ -704,6 +797,20 @@ fn is_utf8(e: &'static EncodingRs) -> bool {
     s.eq("utf8") || s.eq("utf-8")
 }
 
+/// Sanitize a path component generated by template, substitutions are logged and reported.
+fn sanitize_component(sanitizer: &Sanitizer,
+                      report: &mut SanitizeReport,
+                      path: &Path,
+                      component: &str,
+                      reserved_bytes: usize) -> String {
+    let (result, subs) = sanitizer.sanitize(component, reserved_bytes);
+    if !subs.is_empty() {
+        info!("file {:?} sanitized {:?} as {:?}, substitutions: {:?}", path, component, result, subs);
+        report.add(&subs);
+    }
+    result
+}
+
 fn get_new_path(path: &Path,
                 filename_exist_policy: FilenameExistPolicy) -> Option<Cow<Path>> {
     match filename_exist_policy {
@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use fancy_regex::{Regex, RegexBuilder};
+use itertools::Itertools;
+use log::{debug, error};
+
+use crate::config::{get_multi_value_separator, get_split_separators};
+use crate::model::{MULTI_VALUE_TAGS, MyTag};
+use crate::op::{get_file_iterator, get_tags_from_args, get_where};
+use crate::op::{Action, WalkAction, WriteAction, WriteTextAction};
+use crate::op::tag_impl::ReadWriteTag;
+use crate::where_clause::WhereClause;
+
+pub struct SplitValuesAction {
+    it: Box<dyn Iterator<Item=PathBuf>>,
+    dry_run: bool,
+    tags: Vec<MyTag>,
+    where_clause: Option<WhereClause>,
+    re: Regex,
+}
+
+impl SplitValuesAction {
+    pub fn new<P>(dir: P,
+                  dry_run: bool,
+                  tags: &[MyTag],
+                  where_string: &Option<String>,
+                  separators: &[String],
+                  ignore_case: bool) -> Result<Self, Error>
+        where P: AsRef<Path>
+    {
+        let re = if separators.is_empty() {
+            get_regex(&get_split_separators(), ignore_case)?
+        } else {
+            get_regex(separators, ignore_case)?
+        };
+        let it = get_file_iterator(dir.as_ref())?;
+        let tags = get_tags_from_args(tags, &MULTI_VALUE_TAGS)?;
+        let where_clause = get_where(where_string)?;
+        Ok(Self {
+            it,
+            dry_run,
+            tags,
+            where_clause,
+            re,
+        })
+    }
+}
+
+impl Action for SplitValuesAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        self.do_all()
+    }
+}
+
+impl WalkAction for SplitValuesAction {
+    fn get_iterator(&mut self) -> &mut dyn Iterator<Item=PathBuf> {
+        &mut self.it
+    }
+
+    fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
+        self.do_one_file_write(path)
+    }
+
+    fn get_where(&self) -> &Option<WhereClause> {
+        &self.where_clause
+    }
+
+    fn tags(&self) -> &Vec<MyTag> {
+        &self.tags
+    }
+}
+
+impl WriteAction for SplitValuesAction {
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn write_tags(&self, t: &mut dyn ReadWriteTag) -> Result<bool, Error> {
+        self.write_tags_impl(t)
+    }
+}
+
+impl WriteTextAction for SplitValuesAction {
+    fn set_text_tag(&self, t: &mut dyn ReadWriteTag, tag: &MyTag) -> bool {
+        let values = t.get_text_tag_values(tag);
+        let mut new_values = Vec::with_capacity(values.len());
+        for value in &values {
+            match split_value(&self.re, value) {
+                Ok(v) => new_values.extend(v),
+                Err(e) => {
+                    error!("file {:?} could NOT split tag {}. (error: {})", t.get_path(), tag, e);
+                    return false;
+                }
+            }
+        }
+        let new_values = new_values.into_iter().unique().collect::<Vec<_>>();
+
+        if !new_values.is_empty() && new_values != values {
+            t.write_text_tag_values(tag, &new_values);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub struct JoinValuesAction {
+    it: Box<dyn Iterator<Item=PathBuf>>,
+    dry_run: bool,
+    tags: Vec<MyTag>,
+    where_clause: Option<WhereClause>,
+    separator: String,
+}
+
+impl JoinValuesAction {
+    pub fn new<P>(dir: P,
+                  dry_run: bool,
+                  tags: &[MyTag],
+                  where_string: &Option<String>,
+                  separator: &Option<String>) -> Result<Self, Error>
+        where P: AsRef<Path>
+    {
+        let it = get_file_iterator(dir.as_ref())?;
+        let tags = get_tags_from_args(tags, &MULTI_VALUE_TAGS)?;
+        let where_clause = get_where(where_string)?;
+        let separator = separator.as_deref()
+            .unwrap_or_else(|| get_multi_value_separator())
+            .to_owned();
+        Ok(Self {
+            it,
+            dry_run,
+            tags,
+            where_clause,
+            separator,
+        })
+    }
+}
+
+impl Action for JoinValuesAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        self.do_all()
+    }
+}
+
+impl WalkAction for JoinValuesAction {
+    fn get_iterator(&mut self) -> &mut dyn Iterator<Item=PathBuf> {
+        &mut self.it
+    }
+
+    fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
+        self.do_one_file_write(path)
+    }
+
+    fn get_where(&self) -> &Option<WhereClause> {
+        &self.where_clause
+    }
+
+    fn tags(&self) -> &Vec<MyTag> {
+        &self.tags
+    }
+}
+
+impl WriteAction for JoinValuesAction {
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn write_tags(&self, t: &mut dyn ReadWriteTag) -> Result<bool, Error> {
+        self.write_tags_impl(t)
+    }
+}
+
+impl WriteTextAction for JoinValuesAction {
+    fn set_text_tag(&self, t: &mut dyn ReadWriteTag, tag: &MyTag) -> bool {
+        let values = t.get_text_tag_values(tag);
+        if values.len() > 1 {
+            t.write_text_tag(tag, &values.join(&self.separator));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn get_regex(separators: &[String], ignore_case: bool) -> Result<Regex, Error> {
+    let pattern = separators.iter()
+        .map(|s| format!("(?:{})", s))
+        .join("|");
+    let pattern = if ignore_case {
+        format!("(?i){}", pattern)
+    } else {
+        pattern
+    };
+    debug!("pattern: {}", pattern);
+
+    RegexBuilder::new(&pattern)
+        .build()
+        .map_err(|e| anyhow!("Invalid separator pattern {:?}. (error: {})", separators, e))
+}
+
+fn split_value(re: &Regex, value: &str) -> Result<Vec<String>, Error> {
+    let mut result = Vec::new();
+    let mut last = 0;
+    for m in re.find_iter(value) {
+        let m = m?;
+        if m.start() == m.end() {
+            continue;
+        }
+        result.push(&value[last..m.start()]);
+        last = m.end();
+    }
+    result.push(&value[last..]);
+
+    Ok(result.into_iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_regex, split_value};
+
+    #[test]
+    fn test_split_value() {
+        let separators = vec![r"\s*;\s*".to_owned(),
+                              r"\s+/\s+".to_owned(),
+                              r"\s+&\s+".to_owned(),
+                              r"\s+(?i:feat\.?|ft\.)\s+".to_owned()];
+        let re = get_regex(&separators, false).unwrap();
+        assert_eq!(split_value(&re, "A feat. B & C").unwrap(), vec!["A", "B", "C"]);
+        assert_eq!(split_value(&re, "A; B / C").unwrap(), vec!["A", "B", "C"]);
+        assert_eq!(split_value(&re, "A FT. B;").unwrap(), vec!["A", "B"]);
+        assert_eq!(split_value(&re, "AC/DC").unwrap(), vec!["AC/DC"]);
+        assert_eq!(split_value(&re, "").unwrap(), Vec::<String>::new());
+
+        let re = get_regex(&[" x ".to_owned()], true).unwrap();
+        assert_eq!(split_value(&re, "A X B").unwrap(), vec!["A", "B"]);
+
+        assert!(get_regex(&["(".to_owned()], false).is_err());
+    }
+}
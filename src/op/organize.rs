@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use log::{error, info, warn};
+
+use crate::expr::Template;
+use crate::journal;
+use crate::model::{FilenameExistPolicy, MyTag};
+use crate::op::{get_file_iterator, get_new_path, get_where, is_available_suffix,
+                sanitize_component};
+use crate::op::{Action, WalkAction};
+use crate::op::tag_impl::TagImpl;
+use crate::util::path::move_file;
+use crate::util::sanitize::{SanitizeReport, Sanitizer};
+use crate::where_clause::WhereClause;
+
+/// Files with the same stem as the music file, they are moved / copied with it.
+const SIBLING_EXTS: [&str; 2] = ["lrc", "cue"];
+
+/// Cover images in the directory of music files, they are copied to each target directory, or
+/// moved to the first one with `--remove-empty-dirs`.
+const COVER_STEMS: [&str; 5] = ["cover", "folder", "front", "album", "albumart"];
+const COVER_EXTS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// How the files are transferred to `dest` and how their paths are built.
+pub struct OrganizeOptions {
+    pub copy: bool,
+    pub remove_empty_dirs: bool,
+    pub filename_exist_policy: FilenameExistPolicy,
+    pub sanitizer: Sanitizer,
+}
+
+pub struct OrganizeAction {
+    it: Box<dyn Iterator<Item=PathBuf>>,
+    tags: Vec<MyTag>,
+    dry_run: bool,
+    where_clause: Option<WhereClause>,
+    template: Template,
+    template_string: String,
+    dest: PathBuf,
+    root: PathBuf,
+    copy: bool,
+    remove_empty_dirs: bool,
+    filename_exist_policy: FilenameExistPolicy,
+    sanitizer: Sanitizer,
+    // source directories of processed files
+    source_dirs: BTreeSet<PathBuf>,
+    // covers to be moved when cleaning the source directories, and their targets
+    covers: BTreeMap<PathBuf, PathBuf>,
+    report: SanitizeReport,
+}
+
+impl OrganizeAction {
+    pub fn new<P1, P2>(dir: P1,
+                       dry_run: bool,
+                       where_string: &Option<String>,
+                       template: &str,
+                       dest: P2,
+                       options: OrganizeOptions) -> Result<Self, Error>
+        where P1: AsRef<Path>,
+              P2: AsRef<Path>
+    {
+        let it = get_file_iterator(dir.as_ref())?;
+        let where_clause = get_where(where_string)?;
+        let template_string = template.to_owned();
+        let template = Template::new(template)?;
+        if !template.has_field() {
+            return Err(anyhow!("You must specified one tag at least in \"--template\" parameter!"));
+        }
+        let tags = template.get_tags().into_iter().copied().collect();
+        let OrganizeOptions { copy, remove_empty_dirs, filename_exist_policy, sanitizer } = options;
+        if filename_exist_policy == FilenameExistPolicy::Overwrite {
+            return Err(anyhow!("\"--filename-exist-policy overwrite\" is NOT supported, \
+            the overwritten files could NOT be restored by undo."));
+        }
+        Ok(Self {
+            it,
+            tags,
+            dry_run,
+            where_clause,
+            template,
+            template_string,
+            dest: dest.as_ref().to_path_buf(),
+            root: dir.as_ref().to_path_buf(),
+            copy,
+            remove_empty_dirs,
+            filename_exist_policy,
+            sanitizer,
+            source_dirs: BTreeSet::new(),
+            covers: BTreeMap::new(),
+            report: SanitizeReport::default(),
+        })
+    }
+
+    /// Target path of the file under `dest`, with the extension of the file.
+    fn get_target(&mut self, path: &Path, t: &TagImpl) -> Result<PathBuf, Error> {
+        let (components, any) = self.template.eval_path(t)?;
+        if !any {
+            return Err(anyhow!("File {:?} NOT contains any value for tags in --template \"{}\".",
+                path, &self.template_string));
+        }
+
+        let ext = path.extension().and_then(|e| e.to_str());
+        let last = components.len() - 1;
+        let components = components.iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let reserved_bytes = if i == last { ext.map_or(0, |e| e.len() + 1) } else { 0 };
+                sanitize_component(&self.sanitizer, &mut self.report, path, c, reserved_bytes)
+            })
+            .collect::<Vec<_>>();
+        get_target_path(&self.dest, &components, ext)
+            .ok_or(anyhow!("File {:?} got an empty path component by --template \"{}\".",
+                path, &self.template_string))
+    }
+
+    fn transfer(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let op = if self.copy { "Copy" } else { "Move" };
+        if !self.dry_run {
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| anyhow!("Create directory {:?} failed. (error: {:?})", parent, e))?;
+            }
+            if self.copy {
+                fs::copy(from, to).map(|_| ())
+            } else {
+                move_file(from, to)
+            }.map_err(|e| anyhow!("{} file {:?} to {:?} failed. (error: {:?})", op, from, to, e))?;
+            if !self.copy {
+                journal::record_rename(from, to);
+            }
+        }
+        info!("{} file {:?} to {:?}", op, from, to);
+        Ok(())
+    }
+
+    fn transfer_siblings(&self, path: &Path, target: &Path) {
+        for ext in SIBLING_EXTS {
+            let from = path.with_extension(ext);
+            if from.is_file() {
+                let to = target.with_extension(ext);
+                if let Some(to) = get_new_path(&to, self.filename_exist_policy) {
+                    if let Err(e) = self.transfer(&from, &to) {
+                        error!("Error: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// With `--remove-empty-dirs` in move mode, the first target of a cover is kept to move it
+    /// when cleaning the source directories.
+    fn copy_covers(&mut self, path: &Path, target: &Path) {
+        let (Some(dir), Some(target_dir)) = (path.parent(), target.parent()) else {
+            return;
+        };
+        for cover in get_covers(dir) {
+            if let Some(name) = cover.file_name() {
+                let to = target_dir.join(name);
+                if to.exists() || self.covers.get(&cover) == Some(&to) {
+                    continue;
+                }
+                if !self.copy && self.remove_empty_dirs && !self.covers.contains_key(&cover) {
+                    self.covers.insert(cover, to);
+                } else {
+                    self.copy_cover(&cover, &to);
+                }
+            }
+        }
+    }
+
+    fn copy_cover(&self, from: &Path, to: &Path) {
+        if !self.dry_run {
+            if let Err(e) = to.parent().map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::copy(from, to)) {
+                error!("Error: Copy file {:?} to {:?} failed. (error: {:?})", from, to, e);
+                return;
+            }
+        }
+        info!("Copy file {:?} to {:?}", from, to);
+    }
+
+    /// Move covers out of the source directories which have no music files, or copy them if
+    /// there are still music files, then remove the empty directories up to the source root.
+    fn clean_source_dirs(&self) {
+        for (cover, to) in &self.covers {
+            if to.exists() {
+                continue;
+            }
+            if cover.parent().map_or(true, has_music_file) {
+                self.copy_cover(cover, to);
+            } else if let Err(e) = self.transfer(cover, to) {
+                error!("Error: {}", e);
+            }
+        }
+
+        for dir in self.source_dirs.iter().rev() {
+            if self.remove_empty_dirs {
+                let mut current = Some(dir.as_path());
+                while let Some(d) = current {
+                    if !d.starts_with(&self.root) || d == self.root || !is_empty_dir(d) {
+                        break;
+                    }
+                    if !self.dry_run {
+                        if let Err(e) = fs::remove_dir(d) {
+                            warn!("Remove directory {:?} failed. (error: {:?})", d, e);
+                            break;
+                        }
+                    }
+                    info!("Remove directory {:?}", d);
+                    current = d.parent();
+                }
+            }
+        }
+    }
+}
+
+impl Action for OrganizeAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        self.do_all()?;
+        self.clean_source_dirs();
+        if !self.report.is_empty() {
+            info!("Substituted in paths: {}", self.report);
+        }
+        Ok(())
+    }
+}
+
+impl WalkAction for OrganizeAction {
+    fn get_iterator(&mut self) -> &mut dyn Iterator<Item=PathBuf> {
+        &mut self.it
+    }
+
+    fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
+        let t = TagImpl::new(&path, true)?;
+        if !self.check_where(&t)? {
+            return Ok(false);
+        }
+
+        let target = self.get_target(path, &t)?;
+        drop(t);
+        if target == path {
+            return Ok(false);
+        }
+
+        let target = match get_new_path(&target, self.filename_exist_policy) {
+            Some(p) => p.into_owned(),
+            None => return Ok(false),
+        };
+        self.transfer(path, &target)?;
+        self.transfer_siblings(path, &target);
+        self.copy_covers(path, &target);
+        if let Some(dir) = path.parent() {
+            self.source_dirs.insert(dir.to_path_buf());
+        }
+        Ok(true)
+    }
+
+    fn get_where(&self) -> &Option<WhereClause> {
+        &self.where_clause
+    }
+
+    fn tags(&self) -> &Vec<MyTag> {
+        &self.tags
+    }
+}
+
+/// `None` if any component is empty.
+fn get_target_path(dest: &Path, components: &[String], ext: Option<&str>) -> Option<PathBuf> {
+    if components.iter().any(|c| c.trim().is_empty()) {
+        return None;
+    }
+
+    let mut result = dest.to_path_buf();
+    for c in components {
+        result.push(c);
+    }
+    if let Some(ext) = ext {
+        let mut filename = result.file_name()?.to_os_string();
+        filename.push(".");
+        filename.push(ext);
+        result.set_file_name(filename);
+    }
+    Some(result)
+}
+
+fn is_cover(path: &Path) -> bool {
+    let matches = |s: Option<&std::ffi::OsStr>, names: &[&str]| s
+        .and_then(|s| s.to_str())
+        .is_some_and(|s| names.iter().any(|n| n.eq_ignore_ascii_case(s)));
+    matches(path.file_stem(), &COVER_STEMS) && matches(path.extension(), &COVER_EXTS)
+}
+
+fn get_covers(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|rd| rd.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && is_cover(p))
+            .collect())
+        .unwrap_or_default()
+}
+
+fn has_music_file(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|mut rd| rd.any(|e| e.is_ok_and(|e|
+            e.path().is_file() && is_available_suffix(&e.path().to_string_lossy()))))
+        .unwrap_or(true)
+}
+
+fn is_empty_dir(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut rd| rd.next().is_none())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{get_target_path, is_cover};
+
+    #[test]
+    fn test_get_target_path() {
+        let components = vec!["John Denver".to_owned(), "1971 - Poems".to_owned(),
+                              "03 - Take Me Home".to_owned()];
+        assert_eq!(get_target_path(Path::new("/lib"), &components, Some("flac")),
+                   Some(PathBuf::from("/lib/John Denver/1971 - Poems/03 - Take Me Home.flac")));
+        assert_eq!(get_target_path(Path::new("/lib"), &components[2..], None),
+                   Some(PathBuf::from("/lib/03 - Take Me Home")));
+
+        let components = vec!["John Denver".to_owned(), " ".to_owned(), "03".to_owned()];
+        assert_eq!(get_target_path(Path::new("/lib"), &components, Some("flac")), None);
+    }
+
+    #[test]
+    fn test_is_cover() {
+        assert!(is_cover(Path::new("/a/cover.jpg")));
+        assert!(is_cover(Path::new("/a/Folder.JPG")));
+        assert!(!is_cover(Path::new("/a/back.jpg")));
+        assert!(!is_cover(Path::new("/a/cover.txt")));
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use log::debug;
+
+use crate::model::{MyTag, PropOperation};
+use crate::op::{check_where, get_file_iterator, get_where};
+use crate::op::{Action, WalkAction};
+use crate::op::tag_impl::{ReadWriteTag, TagImpl};
+use crate::where_clause::WhereClause;
+
+pub struct PropAction {
+    it: Box<dyn Iterator<Item=PathBuf>>,
+    dry_run: bool,
+    tags: Vec<MyTag>,
+    where_clause: Option<WhereClause>,
+    operation: PropOperation,
+}
+
+impl PropAction {
+    pub fn new<P>(dir: P,
+                  dry_run: bool,
+                  where_string: &Option<String>,
+                  operation: PropOperation) -> Result<Self, Error>
+        where P: AsRef<Path>
+    {
+        let it = get_file_iterator(dir.as_ref())?;
+        let where_clause = get_where(where_string)?;
+        let operation = Self::check(operation)?;
+        Ok(Self {
+            it,
+            dry_run,
+            tags: vec![],
+            where_clause,
+            operation,
+        })
+    }
+
+    fn check(operation: PropOperation) -> Result<PropOperation, Error> {
+        match operation {
+            PropOperation::Set { key, values } => {
+                let key = get_key(&key)?;
+                if values.is_empty() {
+                    return Err(anyhow!("Parameter: values should NOT be empty."));
+                }
+                Ok(PropOperation::Set { key, values })
+            }
+            PropOperation::Add { key, values } => {
+                let key = get_key(&key)?;
+                if values.is_empty() {
+                    return Err(anyhow!("Parameter: values should NOT be empty."));
+                }
+                Ok(PropOperation::Add { key, values })
+            }
+            PropOperation::Remove { key, values } =>
+                Ok(PropOperation::Remove { key: get_key(&key)?, values }),
+            PropOperation::RenameKey { from, to } => {
+                let from = get_key(&from)?;
+                let to = get_key(&to)?;
+                if from == to {
+                    return Err(anyhow!("Parameter: from and to should NOT be same."));
+                }
+                Ok(PropOperation::RenameKey { from, to })
+            }
+        }
+    }
+
+    fn set(t: &mut dyn ReadWriteTag, key: &str, values: &[String]) -> Result<bool, Error> {
+        let current = t.get_property(key)?;
+        if current == values {
+            debug!("file {:?} property {} has already been {:?}", t.get_path(), key, values);
+            return Ok(false);
+        }
+
+        t.write_property(key, values);
+        t.save()?;
+        Ok(true)
+    }
+
+    fn add(t: &mut dyn ReadWriteTag, key: &str, values: &[String]) -> Result<bool, Error> {
+        let current = t.get_property(key)?;
+        let new_values = values.iter()
+            .filter(|v| !current.contains(v))
+            .cloned()
+            .collect::<Vec<_>>();
+        if new_values.is_empty() {
+            debug!("file {:?} property {} has already contained {:?}", t.get_path(), key, values);
+            return Ok(false);
+        }
+
+        t.append_property(key, &new_values);
+        t.save()?;
+        Ok(true)
+    }
+
+    fn remove(t: &mut dyn ReadWriteTag, key: &str, values: &[String]) -> Result<bool, Error> {
+        let current = t.get_property(key)?;
+        if current.is_empty() {
+            return Ok(false);
+        }
+
+        if values.is_empty() {
+            t.remove_property(key);
+        } else {
+            let remained = current.iter()
+                .filter(|v| !values.contains(v))
+                .cloned()
+                .collect::<Vec<_>>();
+            if remained.len() == current.len() {
+                return Ok(false);
+            } else if remained.is_empty() {
+                t.remove_property(key);
+            } else {
+                t.write_property(key, &remained);
+            }
+        }
+        t.save()?;
+        Ok(true)
+    }
+
+    fn rename_key(t: &mut dyn ReadWriteTag, from: &str, to: &str) -> Result<bool, Error> {
+        let current = t.get_property(from)?;
+        if current.is_empty() {
+            return Ok(false);
+        }
+
+        if !t.get_property(to)?.is_empty() {
+            return Err(anyhow!("file {:?} has already had property {}, could NOT rename {} to it.",
+                t.get_path(), to, from));
+        }
+
+        t.write_property(to, &current);
+        t.remove_property(from);
+        t.save()?;
+        Ok(true)
+    }
+}
+
+impl Action for PropAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        self.do_all()
+    }
+}
+
+impl WalkAction for PropAction {
+    fn get_iterator(&mut self) -> &mut dyn Iterator<Item=PathBuf> {
+        &mut self.it
+    }
+
+    fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
+        let mut tag_impl = TagImpl::new(&path, self.dry_run)?;
+        if !check_where(&self.where_clause, &tag_impl)? {
+            return Ok(false);
+        }
+
+        match &self.operation {
+            PropOperation::Set { key, values } =>
+                Self::set(&mut tag_impl, key, values),
+            PropOperation::Add { key, values } =>
+                Self::add(&mut tag_impl, key, values),
+            PropOperation::Remove { key, values } =>
+                Self::remove(&mut tag_impl, key, values),
+            PropOperation::RenameKey { from, to } =>
+                Self::rename_key(&mut tag_impl, from, to),
+        }
+    }
+
+    fn get_where(&self) -> &Option<WhereClause> {
+        &self.where_clause
+    }
+
+    fn tags(&self) -> &Vec<MyTag> {
+        &self.tags
+    }
+}
+
+/// Property keys of TagLib `PropertyMap` are always in upper case.
+fn get_key(key: &str) -> Result<String, Error> {
+    let key = key.trim();
+    if key.is_empty() || key.contains('=') || key.chars().any(|c| c.is_control()) {
+        Err(anyhow!("Parameter: key \"{}\" is NOT a valid property key.", key))
+    } else {
+        Ok(key.to_uppercase())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::get_key;
+
+    #[test]
+    fn test_get_key() {
+        assert_eq!(get_key("bpm").unwrap(), "BPM");
+        assert_eq!(get_key(" MusicBrainz_AlbumId ").unwrap(), "MUSICBRAINZ_ALBUMID");
+        assert!(get_key("").is_err());
+        assert!(get_key("A=B").is_err());
+    }
+}
@@ -1,55 +1,122 @@
 use std::fs;
+use std::io::{stdout, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Error};
 use log::{error, info};
 
+use crate::expr::Template;
+use crate::journal;
 use crate::model::{FilenameExistPolicy, MyTag};
-use crate::op::{get_file_iterator, get_new_path, get_tags_from_template, get_where};
-use crate::op::{Action, MyValues, ReadAction, WalkAction};
+use crate::op::{get_file_iterator, get_new_path, get_where, sanitize_component};
+use crate::op::{Action, WalkAction};
+use crate::op::tag_impl::TagImpl;
 use crate::util::path::combine_path;
+use crate::util::sanitize::{SanitizeReport, Sanitizer};
 use crate::where_clause::WhereClause;
 
 pub struct RenAction {
     it: Box<dyn Iterator<Item=PathBuf>>,
     tags: Vec<MyTag>,
     dry_run: bool,
+    preview: bool,
     where_clause: Option<WhereClause>,
-    with_properties: bool,
-    template: String,
+    template: Template,
+    template_string: String,
     filename_exist_policy: FilenameExistPolicy,
-    // for cache
-    empty_value: String,
+    sanitizer: Sanitizer,
+    // old and new filename for preview
+    rows: Vec<(String, String)>,
+    report: SanitizeReport,
 }
 
 impl RenAction {
     pub fn new<P>(dir: P,
                   dry_run: bool,
+                  preview: bool,
                   where_string: &Option<String>,
                   template: &str,
-                  filename_exist_policy: FilenameExistPolicy) -> Result<Self, Error>
+                  filename_exist_policy: FilenameExistPolicy,
+                  sanitizer: Sanitizer) -> Result<Self, Error>
         where P: AsRef<Path>
     {
         let it = get_file_iterator(dir.as_ref())?;
-        let tags = get_tags_from_template(template)?;
         let where_clause = get_where(where_string)?;
-        let empty_value = get_empty_value(&tags, template);
+        let template_string = template.to_owned();
+        let template = Template::new(template)?;
+        if !template.has_field() {
+            return Err(anyhow!("You must specified one tag at least in \"--template\" parameter!"));
+        }
+        let tags = template.get_tags().into_iter().copied().collect();
         Ok(Self {
             it,
             tags,
             dry_run,
+            preview,
             where_clause,
-            with_properties: false,
-            template: template.to_owned(),
+            template,
+            template_string,
             filename_exist_policy,
-            empty_value,
+            sanitizer,
+            rows: vec![],
+            report: SanitizeReport::default(),
         })
     }
+
+    fn get_content(&self, path: &Path, t: &TagImpl) -> Result<String, Error> {
+        match self.template.eval_any(t)? {
+            Some(result) if !result.is_empty() => Ok(result),
+            _ => Err(anyhow!("File {:?} NOT contains any value for tags in --template \"{}\".",
+                path, &self.template_string)),
+        }
+    }
+
+    fn do_output(&mut self, path: &Path, content: &str) -> Result<bool, Error> {
+        if let Some(p) = combine_path(path, content) {
+            if path.eq(&p) {
+                return Ok(true);
+            }
+
+            if let Some(ref new_path) = get_new_path(&p, self.filename_exist_policy) {
+                return if self.preview {
+                    self.rows.push((get_filename(path), get_filename(new_path)));
+                    Ok(true)
+                } else if !self.dry_run {
+                    ren_real(self.filename_exist_policy, path, new_path)
+                } else {
+                    ren_dry_run(self.filename_exist_policy, path, new_path)
+                };
+            }
+        }
+
+        error!(
+            "Could NOT get the new filename! path: {:?}, content: {}, filename_exist_policy: {:?}",
+            path, & content, self.filename_exist_policy);
+        Ok(false)
+    }
+
+    fn output_preview(&self) -> Result<(), Error> {
+        let stdout = stdout();
+        let mut writer = stdout.lock();
+        if self.rows.is_empty() {
+            writeln!(writer, "No file will be renamed.")?;
+        } else {
+            write_table(&mut writer, ("Old name", "New name"), &self.rows)?;
+        }
+        Ok(())
+    }
 }
 
 impl Action for RenAction {
     fn do_any(&mut self) -> Result<(), Error> {
-        self.do_all()
+        self.do_all()?;
+        if !self.report.is_empty() {
+            info!("Substituted in filenames: {}", self.report);
+        }
+        if self.preview {
+            self.output_preview()?;
+        }
+        Ok(())
     }
 }
 
@@ -59,7 +126,17 @@ impl WalkAction for RenAction {
     }
 
     fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
-        self.do_one_file_read(path)
+        let t = TagImpl::new(&path, true)?;
+        if !self.check_where(&t)? {
+            return Ok(false);
+        }
+
+        let content = self.get_content(path, &t)?;
+        drop(t);
+        let ext_bytes = path.extension().map_or(0, |e| e.len() + 1);
+        let content = sanitize_component(&self.sanitizer, &mut self.report,
+                                         path, &content, ext_bytes);
+        self.do_output(path, &content)
     }
 
     fn get_where(&self) -> &Option<WhereClause> {
@@ -71,61 +148,28 @@ impl WalkAction for RenAction {
     }
 }
 
-impl ReadAction for RenAction {
-    fn with_properties(&self) -> bool {
-        self.with_properties
-    }
-
-    fn get_content(&self, path: &Path, v: &MyValues) -> Result<Option<String>, Error> {
-        if !v.is_empty_value() {
-            let mut result = self.template.clone();
-            for tag in self.tags() {
-                let tag_name = &tag.to_string();
-                result = result.replace(&format!("${{{}}}", tag_name),
-                                        v.get_text(tag).unwrap_or_default());
-            }
-            if !result.eq(&self.empty_value)
-                && !result.eq(&self.template)
-                && !result.is_empty() {
-                Ok(Some(result))
-            } else {
-                Err(anyhow!("File {:?} NOT contains any value for tags in --template \"{}\".",
-                    path, &self.template))
-            }
-        } else {
-            Err(anyhow!("Not found any tag in file {:?}", path))
-        }
-    }
-
-    fn do_output(&mut self, path: &Path, content: &str) -> Result<bool, Error> {
-        if let Some(p) = combine_path(path, content) {
-            if path.eq(&p) {
-                return Ok(true);
-            }
-
-            if let Some(ref new_path) = get_new_path(&p, self.filename_exist_policy) {
-                return if !self.dry_run {
-                    ren_real(self.filename_exist_policy, path, new_path)
-                } else {
-                    ren_dry_run(self.filename_exist_policy, path, new_path)
-                };
-            }
-        }
-
-        error!(
-            "Could NOT get the new filename! path: {:?}, content: {}, filename_exist_policy: {:?}",
-            path, & content, self.filename_exist_policy);
-        Ok(false)
-    }
+fn get_filename(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
 }
 
-fn get_empty_value(tags: &[MyTag], template: &str) -> String {
-    let mut result = template.to_owned();
-    for tag in tags {
-        let tag_name = &tag.to_string();
-        result = result.replace(&format!("${{{}}}", tag_name), "");
+fn write_table<W: Write>(writer: &mut W,
+                         header: (&str, &str),
+                         rows: &[(String, String)]) -> Result<(), Error> {
+    let width = rows.iter()
+        .map(|(old, _)| old.chars().count())
+        .chain(std::iter::once(header.0.chars().count()))
+        .max()
+        .unwrap_or_default();
+    let pad = |s: &str| format!("{}{}", s, " ".repeat(width - s.chars().count()));
+
+    writeln!(writer, "{} | {}", pad(header.0), header.1)?;
+    writeln!(writer, "{}-+-{}", "-".repeat(width), "-".repeat(header.1.chars().count()))?;
+    for (old, new) in rows {
+        writeln!(writer, "{} | {}", pad(old), new)?;
     }
-    result
+    Ok(())
 }
 
 fn ren_real<P1, P2>(filename_exist_policy: FilenameExistPolicy,
@@ -150,6 +194,7 @@ fn ren_real<P1, P2>(filename_exist_policy: FilenameExistPolicy,
         },
         |_| {
             info!("Rename file {:?} to {:?}", path, new_path);
+            journal::record_rename(path, new_path);
             Ok(true)
         })
 }
@@ -175,3 +220,24 @@ fn ren_dry_run<P1, P2>(filename_exist_policy: FilenameExistPolicy,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::write_table;
+
+    #[test]
+    fn test_write_table() {
+        let rows = vec![
+            ("01 a.flac".to_owned(), "01. A - John.flac".to_owned()),
+            ("02.flac".to_owned(), "02. B.flac".to_owned()),
+        ];
+        let mut buf = Vec::new();
+        write_table(&mut buf, ("Old name", "New name"), &rows).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "\
+Old name  | New name
+----------+---------
+01 a.flac | 01. A - John.flac
+02.flac   | 02. B.flac
+");
+    }
+}
@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 
 use crate::model::{ConstValue, EMPTY_TAGS, ModifyMode, MyTag, SetWhen};
 use crate::op::{check_numeric_date_tags_must_be_overwrite, check_tags_type_value_type,
@@ -55,6 +55,7 @@ impl SetConstAction {
         check_numeric_date_tags_must_be_overwrite(tags, modify_mode)?;
         check_tags_type_value_type(tags, value)?;
         check_value_is_ok(tags, value)?;
+        check_multiple_values_must_be_overwrite(modify_mode, value)?;
         Ok(())
     }
 
@@ -98,6 +99,13 @@ impl SetConstAction {
         &self.text_value
     }
 
+    fn get_text_values(&self) -> Option<&[String]> {
+        match &self.value {
+            ConstValue::Text { values } if values.len() > 1 => Some(values),
+            _ => None,
+        }
+    }
+
     fn get_numeric_value(&self) -> Option<(u32, usize)> {
         match &self.value {
             ConstValue::Num { value, padding } => Some((*value, *padding)),
@@ -149,6 +157,16 @@ impl WriteAction for SetConstAction {
 
 impl WriteAllAction for SetConstAction {
     fn set_text_tag(&self, t: &mut dyn ReadWriteTag, tag: &MyTag) -> bool {
+        if let Some(values) = self.get_text_values() {
+            return if self.set_when == SetWhen::Always
+                || should_write_text(&t.get_text_tag(tag), &self.set_when) {
+                t.write_text_tag_values(tag, values);
+                true
+            } else {
+                false
+            };
+        }
+
         if &self.set_when == &SetWhen::Always && &self.modify_mode == &ModifyMode::Overwrite {
             let new_value = self.get_text_value();
             t.write_text_tag(tag, new_value);
@@ -198,7 +216,17 @@ impl WriteAllAction for SetConstAction {
     }
 }
 
-fn should_write_text(current: &Option<String>, set_when: &SetWhen) -> bool {
+fn check_multiple_values_must_be_overwrite(modify_mode: &ModifyMode,
+                                           value: &ConstValue) -> Result<(), Error> {
+    match value {
+        ConstValue::Text { values }
+        if values.len() > 1 && modify_mode != &ModifyMode::Overwrite =>
+            Err(anyhow!("multiple text values must work with \"--modify-mode overwrite\".")),
+        _ => Ok(()),
+    }
+}
+
+pub(super) fn should_write_text(current: &Option<String>, set_when: &SetWhen) -> bool {
     if let Some(curr) = current {
         if !curr.is_empty() {
             set_when != &SetWhen::OnlyEmpty
@@ -210,7 +238,7 @@ fn should_write_text(current: &Option<String>, set_when: &SetWhen) -> bool {
     }
 }
 
-fn should_write_numeric(current: &Option<u32>, set_when: &SetWhen) -> bool {
+pub(super) fn should_write_numeric(current: &Option<u32>, set_when: &SetWhen) -> bool {
     if current.is_some() {
         set_when != &SetWhen::OnlyEmpty
     } else {
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use log::warn;
+
+use crate::expr::Template;
+use crate::model::{EMPTY_TAGS, ModifyMode, MyTag, SetWhen};
+use crate::op::{check_numeric_date_tags_must_be_overwrite, get_file_iterator, get_tags_from_args,
+                get_where, numeric_is_valid, MAX_PADDING};
+use crate::op::{Action, WalkAction, WriteAction};
+use crate::op::set_const::{should_write_numeric, should_write_text};
+use crate::op::tag_impl::ReadWriteTag;
+use crate::where_clause::WhereClause;
+
+pub struct SetExprAction {
+    it: Box<dyn Iterator<Item=PathBuf>>,
+    dry_run: bool,
+    tags: Vec<MyTag>,
+    where_clause: Option<WhereClause>,
+    template: Template,
+    set_when: SetWhen,
+    modify_mode: ModifyMode,
+}
+
+impl SetExprAction {
+    pub fn new<P>(dir: P,
+                  dry_run: bool,
+                  tags: &[MyTag],
+                  where_string: &Option<String>,
+                  expression: &str,
+                  set_when: &SetWhen,
+                  modify_mode: &ModifyMode) -> Result<Self, Error>
+        where P: AsRef<Path>
+    {
+        let it = get_file_iterator(dir.as_ref())?;
+        let tags = get_tags_from_args(tags, &EMPTY_TAGS)?;
+        let where_clause = get_where(where_string)?;
+        let template = Template::new(expression)?;
+        check_numeric_date_tags_must_be_overwrite(&tags, modify_mode)?;
+        Ok(Self {
+            it,
+            dry_run,
+            tags,
+            where_clause,
+            template,
+            set_when: set_when.clone(),
+            modify_mode: modify_mode.clone(),
+        })
+    }
+
+    fn set_text_tag(&self, t: &mut dyn ReadWriteTag, tag: &MyTag, value: &str) -> bool {
+        let current = t.get_text_tag(tag);
+        if !should_write_text(&current, &self.set_when) {
+            return false;
+        }
+
+        let new_value = match (&self.modify_mode, current) {
+            (ModifyMode::Append, Some(curr)) => curr + value,
+            (ModifyMode::Insert, Some(curr)) => value.to_owned() + &curr,
+            (_, curr) if curr.as_deref() == Some(value) => return false,
+            _ => value.to_owned(),
+        };
+        t.write_text_tag(tag, &new_value);
+        true
+    }
+
+    fn set_numeric_tag(&self, t: &mut dyn ReadWriteTag, tag: &MyTag, value: &str) -> bool {
+        if !should_write_numeric(&t.get_numeric_tag(tag), &self.set_when) {
+            return false;
+        }
+
+        match value.trim().parse::<u32>() {
+            Ok(u) if numeric_is_valid(tag, u) => {
+                t.write_numeric_tag(tag, u, value.trim().len().min(MAX_PADDING));
+                true
+            }
+            _ => {
+                warn!("file {:?} could NOT set numeric tag {}: {}", t.get_path(), tag, value);
+                false
+            }
+        }
+    }
+
+    fn set_date_tag(&self, t: &mut dyn ReadWriteTag, tag: &MyTag, value: &str) -> bool {
+        let current = t.get_text_tag(tag);
+        if current.as_deref() != Some(value) && should_write_text(&current, &self.set_when) {
+            t.write_text_tag(tag, value);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Action for SetExprAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        self.do_all()
+    }
+}
+
+impl WalkAction for SetExprAction {
+    fn get_iterator(&mut self) -> &mut dyn Iterator<Item=PathBuf> {
+        &mut self.it
+    }
+
+    fn do_one_file(&mut self, path: &Path) -> Result<bool, Error> {
+        self.do_one_file_write(path)
+    }
+
+    fn get_where(&self) -> &Option<WhereClause> {
+        &self.where_clause
+    }
+
+    fn tags(&self) -> &Vec<MyTag> {
+        &self.tags
+    }
+}
+
+impl WriteAction for SetExprAction {
+    fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn write_tags(&self, t: &mut dyn ReadWriteTag) -> Result<bool, Error> {
+        if self.tags.is_empty() {
+            return Ok(false);
+        }
+
+        if !self.check_where(t.as_dyn_read_tag_mut())? {
+            return Ok(false);
+        }
+
+        // evaluate once before writing, so all tags get the value of the original file
+        let value = self.template.eval(t.as_dyn_read_tag())?;
+        if value.is_empty() {
+            warn!("file {:?} got empty value by the expression, skip it.", t.get_path());
+            return Ok(false);
+        }
+
+        let mut any_changed = false;
+        for tag in &self.tags {
+            let changed = match tag {
+                tag if tag.is_text() => self.set_text_tag(t, tag, &value),
+                tag if tag.is_numeric() => self.set_numeric_tag(t, tag, &value),
+                tag if tag.is_date() => self.set_date_tag(t, tag, &value),
+                _ => false,
+            };
+            any_changed = any_changed || changed;
+        }
+
+        if any_changed {
+            t.save()?;
+        }
+        Ok(any_changed)
+    }
+}
@@ -215,7 +215,7 @@ impl Token {
     }
 }
 
-pub fn get_tags_from_template(input: &str) -> Result<Vec<MyTag>, Error> {
+fn get_tags_from_template(input: &str) -> Result<Vec<MyTag>, Error> {
     let res = get_tokens(input).map_or_else(
         |_| vec![],
         |t| t.iter()
@@ -321,6 +321,9 @@ mod test {
         assert_eq!(get_regex_string("${track-number} - ${title}").unwrap(),
                    "(?<tracknumber>.*)(?= - ) - (?<title>.*)".to_owned());
 
+        assert_eq!(get_regex_string("${catalog-number} ${title-sort}").unwrap(),
+                   "(?<catalognumber>.*)(?= ) (?<titlesort>.*)".to_owned());
+
         assert!(get_regex_string("abc").unwrap().is_empty());
         assert!(get_regex_string("${unknown-tag}").is_err());
     }
@@ -170,23 +170,11 @@ impl SeqWriteAction for SetSeqAction {
         let out = out.unwrap();
         for tag in &self.tags {
             let changed = match tag {
-                MyTag::Title
-                | MyTag::Artist
-                | MyTag::AlbumTitle
-                | MyTag::Genre
-                | MyTag::Comment
-                | MyTag::AlbumArtist
-                | MyTag::Composer
-                | MyTag::Copyright
-                | MyTag::Lyrics => self.set_text_tag(t, tag, out),
-
-                MyTag::Year
-                | MyTag::TrackNumber
-                | MyTag::TrackTotal
-                | MyTag::DiscNumber
-                | MyTag::DiscTotal => self.set_numeric_tag(t, tag, out),
-
-                MyTag::Date => {
+                tag if tag.is_text() => self.set_text_tag(t, tag, out),
+
+                tag if tag.is_numeric() => self.set_numeric_tag(t, tag, out),
+
+                _ => {
                     warn!("Unable set tag {} as Sequence, Ignore it.", tag);
                     false
                 }
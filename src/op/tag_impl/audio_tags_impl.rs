@@ -1,13 +1,14 @@
 extern crate lazy_static;
 
 use anyhow::{anyhow, Error};
-use audiotags::{AudioTag, Tag};
+use audiotags::{AudioTag, MimeType, Picture, Tag};
 use lazy_static::lazy_static;
 use log::{info, warn};
 use std::collections::HashSet;
 use std::path::Path;
 
-use crate::model::MyTag;
+use crate::config::get_multi_value_separator;
+use crate::model::{AudioProperty, MyPicture, MyTag, PictureType};
 use crate::op::{MAX_NUMBER, MIN_NATURAL_NUMBER};
 use super::{ReadTag, ReadWriteTag, WriteTag, WriteTagFile};
 
@@ -67,6 +68,19 @@ impl ReadTag for AudioTagWrapper<'_> {
         }
     }
 
+    fn get_text_tag_values(&self, key: &MyTag) -> Vec<String> {
+        let t = &self.tag;
+        let values = match key {
+            MyTag::Artist => t.artists(),
+            MyTag::AlbumArtist => t.album_artists(),
+            _ => None,
+        };
+        match values {
+            Some(values) => values.into_iter().map(|v| v.to_owned()).collect(),
+            None => self.get_text_tag(key).into_iter().collect(),
+        }
+    }
+
     fn get_numeric_tag(&self, key: &MyTag) -> Option<u32> {
         if key.is_numeric() {
             let t = &self.tag;
@@ -117,6 +131,23 @@ impl ReadTag for AudioTagWrapper<'_> {
     fn get_property(&self, _key: &str) -> Result<Vec<String>, Error> {
         Err(anyhow!("Unsupported any properties."))
     }
+
+    fn get_pictures(&self) -> Result<Vec<MyPicture>, Error> {
+        Ok(self.tag.album_cover()
+            .map_or_else(Vec::new, |p| vec![MyPicture {
+                picture_type: PictureType::FrontCover,
+                mime_type: String::from(p.mime_type),
+                description: String::new(),
+                data: p.data.to_vec(),
+            }]))
+    }
+
+    fn get_audio_property(&self, key: &AudioProperty) -> Option<u32> {
+        match key {
+            AudioProperty::Length => self.tag.duration().map(|d| d.round() as u32),
+            _ => None,
+        }
+    }
 }
 
 impl WriteTagFile for AudioTagWrapper<'_> {
@@ -172,15 +203,22 @@ impl WriteTag for AudioTagWrapper<'_> {
                     warn!("Not supported tag {} in file {:?}, could NOT set {}",
                         key, &self.file_name, value);
                 }
-                MyTag::Date => {
+                _ => {
                     warn!("Not supported tag {} in file {:?}, could NOT set {}",
                         key, &self.file_name, value);
                 }
-                _ => (),
             }
         }
     }
 
+    fn write_text_tag_values(&mut self, key: &MyTag, values: &[String]) {
+        if values.is_empty() {
+            self.clear_tag(key);
+        } else {
+            self.write_text_tag(key, &values.join(get_multi_value_separator()));
+        }
+    }
+
     fn write_numeric_tag(&mut self, key: &MyTag, value: u32, _padding: usize) {
         if key.is_numeric() {
             let t = &mut self.tag;
@@ -215,7 +253,10 @@ impl WriteTag for AudioTagWrapper<'_> {
                         info!("file {:?} set tag {}: {}", &self.file_name, key, value);
                     }
                 }
-                _ => (),
+                _ => {
+                    warn!("Not supported tag {} in file {:?}, could NOT set {}",
+                        key, &self.file_name, value);
+                }
             }
         }
     }
@@ -281,6 +322,52 @@ impl WriteTag for AudioTagWrapper<'_> {
                 t.remove_total_discs();
                 info!("file {:?} remove tag {}", &self.file_name, key);
             }
+
+            _ => {
+                warn!("Not supported tag {} in file {:?}, could NOT reset",
+                        key, &self.file_name);
+            }
+        }
+    }
+
+    fn write_property(&mut self, key: &str, _values: &[String]) {
+        warn!("Unsupported any properties in file {:?}, could NOT set {}", &self.file_name, key);
+    }
+
+    fn append_property(&mut self, key: &str, _values: &[String]) {
+        warn!("Unsupported any properties in file {:?}, could NOT add {}", &self.file_name, key);
+    }
+
+    fn remove_property(&mut self, key: &str) {
+        warn!("Unsupported any properties in file {:?}, could NOT remove {}", &self.file_name, key);
+    }
+
+    fn write_pictures(&mut self, pictures: &[MyPicture]) {
+        if pictures.iter().any(|p| p.picture_type != PictureType::FrontCover) {
+            warn!("Only front cover is supported in file {:?}, other pictures are ignored.",
+                &self.file_name);
+        }
+
+        let t = &mut self.tag;
+        match pictures.iter().find(|p| p.picture_type == PictureType::FrontCover) {
+            Some(p) => {
+                match MimeType::try_from(p.mime_type.as_str()) {
+                    Ok(mime_type) => {
+                        t.remove_album_cover();
+                        t.set_album_cover(Picture::new(&p.data, mime_type));
+                        info!("file {:?} set pictures: {:?}", &self.file_name,
+                            vec![PictureType::FrontCover.to_string()]);
+                    }
+                    Err(e) => {
+                        warn!("Not supported picture in file {:?}, could NOT set it. (error: {})",
+                            &self.file_name, e);
+                    }
+                }
+            }
+            None => {
+                t.remove_album_cover();
+                info!("file {:?} set pictures: {:?}", &self.file_name, Vec::<String>::new());
+            }
         }
     }
 }
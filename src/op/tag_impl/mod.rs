@@ -3,15 +3,17 @@ use std::path::Path;
 
 use anyhow::{anyhow, Error};
 use as_dyn_trait::as_dyn_trait;
-use log::info;
+use log::{info, warn};
 
 use crate::config::get_tag_lab;
-use crate::model::MyTag;
+use crate::journal;
+use crate::model::{AudioProperty, MyPicture, MyTag};
 
 pub use self::audio_tags_impl::{AudioTagWrapper, available_suffix as audio_tags_available_suffix};
 pub use self::taglib_impl::{available_suffix as taglib_available_suffix, TaglibWrapper};
 
 mod audio_tags_impl;
+mod native_impl;
 mod taglib_impl;
 
 pub struct TagImpl<'a> {
@@ -60,6 +62,57 @@ impl<'a> TagImpl<'a> {
     }
 }
 
+impl TagImpl<'_> {
+    /// Values of the tag in journal, numeric tag is kept as string with padding.
+    fn get_journal_values(&self, key: &MyTag) -> Option<Vec<String>> {
+        if !journal::is_recording() {
+            None
+        } else if key.is_text() {
+            Some(self.get_text_tag_values(key))
+        } else if key.is_numeric() {
+            Some(self.get_numeric_tag_string(key).into_iter().collect())
+        } else {
+            Some(self.get_text_tag(key).into_iter().collect())
+        }
+    }
+
+    fn get_journal_property(&self, key: &str) -> Option<Vec<String>> {
+        if journal::is_recording() {
+            Some(self.get_property(key).unwrap_or_default())
+        } else {
+            None
+        }
+    }
+
+    /// `None` if the old pictures could NOT be read, then the change is NOT recorded.
+    fn get_journal_pictures(&self) -> Option<Vec<MyPicture>> {
+        if !journal::is_recording() {
+            return None;
+        }
+
+        match self.get_pictures() {
+            Ok(pictures) => Some(pictures),
+            Err(e) => {
+                warn!("Could NOT read pictures of file {:?} for journal. (error: {})",
+                    self.get_path(), e);
+                None
+            }
+        }
+    }
+
+    fn record_tag(&self, key: &MyTag, old: Option<Vec<String>>) {
+        if let (Some(old), Some(new)) = (old, self.get_journal_values(key)) {
+            journal::record_tag(self.get_path(), &key.to_string(), old, new);
+        }
+    }
+
+    fn record_prop(&self, key: &str, old: Option<Vec<String>>) {
+        if let (Some(old), Some(new)) = (old, self.get_journal_property(key)) {
+            journal::record_prop(self.get_path(), key, old, new);
+        }
+    }
+}
+
 pub fn is_available_suffix(file_name: &str) -> bool {
     match get_tag_lab() {
         Some(ref s) => {
@@ -88,6 +141,13 @@ impl<'a> ReadTag for TagImpl<'a> {
         }
     }
 
+    fn get_text_tag_values(&self, key: &MyTag) -> Vec<String> {
+        match &self.raw {
+            TagImplRaw::Taglib(inner) => inner.get_text_tag_values(key),
+            TagImplRaw::AudioTag(inner) => inner.get_text_tag_values(key),
+        }
+    }
+
     fn get_numeric_tag(&self, key: &MyTag) -> Option<u32> {
         match &self.raw {
             TagImplRaw::Taglib(inner) => inner.get_numeric_tag(key),
@@ -115,6 +175,20 @@ impl<'a> ReadTag for TagImpl<'a> {
             TagImplRaw::AudioTag(inner) => inner.get_property(key),
         }
     }
+
+    fn get_pictures(&self) -> Result<Vec<MyPicture>, Error> {
+        match &self.raw {
+            TagImplRaw::Taglib(inner) => inner.get_pictures(),
+            TagImplRaw::AudioTag(inner) => inner.get_pictures(),
+        }
+    }
+
+    fn get_audio_property(&self, key: &AudioProperty) -> Option<u32> {
+        match &self.raw {
+            TagImplRaw::Taglib(inner) => inner.get_audio_property(key),
+            TagImplRaw::AudioTag(inner) => inner.get_audio_property(key),
+        }
+    }
 }
 
 impl WriteTagFile for TagImpl<'_> {
@@ -123,7 +197,7 @@ impl WriteTagFile for TagImpl<'_> {
             match &mut self.raw {
                 TagImplRaw::Taglib(inner) => inner.save(),
                 TagImplRaw::AudioTag(inner) => inner.save(),
-            }
+            }.map(|_| journal::record_saved(self.get_path()))
         } else {
             let path = self.get_path();
             if path.exists()
@@ -144,21 +218,38 @@ impl WriteTagFile for TagImpl<'_> {
 impl WriteTag for TagImpl<'_> {
     fn write_text_tag(&mut self, key: &MyTag, value: &str) {
         if !self.dry_run {
+            let old = self.get_journal_values(key);
             match &mut self.raw {
                 TagImplRaw::Taglib(inner) => inner.write_text_tag(key, value),
                 TagImplRaw::AudioTag(inner) => inner.write_text_tag(key, value),
             }
+            self.record_tag(key, old);
         } else {
             info!("file {:?} set tag {}: {}", self.get_path(), key, value);
         }
     }
 
+    fn write_text_tag_values(&mut self, key: &MyTag, values: &[String]) {
+        if !self.dry_run {
+            let old = self.get_journal_values(key);
+            match &mut self.raw {
+                TagImplRaw::Taglib(inner) => inner.write_text_tag_values(key, values),
+                TagImplRaw::AudioTag(inner) => inner.write_text_tag_values(key, values),
+            }
+            self.record_tag(key, old);
+        } else {
+            info!("file {:?} set tag {}: {:?}", self.get_path(), key, values);
+        }
+    }
+
     fn write_numeric_tag(&mut self, key: &MyTag, value: u32, padding: usize) {
         if !self.dry_run {
+            let old = self.get_journal_values(key);
             match &mut self.raw {
                 TagImplRaw::Taglib(t) => t.write_numeric_tag(key, value, padding),
                 TagImplRaw::AudioTag(t) => t.write_numeric_tag(key, value, padding),
             }
+            self.record_tag(key, old);
         } else {
             let path = self.get_path();
             match &self.raw {
@@ -172,14 +263,71 @@ impl WriteTag for TagImpl<'_> {
 
     fn clear_tag(&mut self, key: &MyTag) {
         if !self.dry_run {
+            let old = self.get_journal_values(key);
             match &mut self.raw {
                 TagImplRaw::Taglib(t) => t.clear_tag(key),
                 TagImplRaw::AudioTag(t) => t.clear_tag(key),
             }
+            self.record_tag(key, old);
         } else {
             info!("file {:?} remove tag {}", self.get_path(), key);
         }
     }
+
+    fn write_property(&mut self, key: &str, values: &[String]) {
+        if !self.dry_run {
+            let old = self.get_journal_property(key);
+            match &mut self.raw {
+                TagImplRaw::Taglib(t) => t.write_property(key, values),
+                TagImplRaw::AudioTag(t) => t.write_property(key, values),
+            }
+            self.record_prop(key, old);
+        } else {
+            info!("file {:?} set property {}: {:?}", self.get_path(), key, values);
+        }
+    }
+
+    fn append_property(&mut self, key: &str, values: &[String]) {
+        if !self.dry_run {
+            let old = self.get_journal_property(key);
+            match &mut self.raw {
+                TagImplRaw::Taglib(t) => t.append_property(key, values),
+                TagImplRaw::AudioTag(t) => t.append_property(key, values),
+            }
+            self.record_prop(key, old);
+        } else {
+            info!("file {:?} add property {}: {:?}", self.get_path(), key, values);
+        }
+    }
+
+    fn remove_property(&mut self, key: &str) {
+        if !self.dry_run {
+            let old = self.get_journal_property(key);
+            match &mut self.raw {
+                TagImplRaw::Taglib(t) => t.remove_property(key),
+                TagImplRaw::AudioTag(t) => t.remove_property(key),
+            }
+            self.record_prop(key, old);
+        } else {
+            info!("file {:?} remove property {}", self.get_path(), key);
+        }
+    }
+
+    fn write_pictures(&mut self, pictures: &[MyPicture]) {
+        if !self.dry_run {
+            let old = self.get_journal_pictures();
+            match &mut self.raw {
+                TagImplRaw::Taglib(t) => t.write_pictures(pictures),
+                TagImplRaw::AudioTag(t) => t.write_pictures(pictures),
+            }
+            if let Some(old) = old {
+                journal::record_pictures(self.get_path(), &old, pictures);
+            }
+        } else {
+            info!("file {:?} set pictures: {:?}", self.get_path(),
+                pictures.iter().map(|p| p.picture_type.to_string()).collect::<Vec<_>>());
+        }
+    }
 }
 
 impl ReadWriteTag for TagImpl<'_> {}
@@ -189,6 +337,10 @@ pub trait ReadTag {
     fn get_path(&self) -> &Path;
 
     fn get_text_tag(&self, key: &MyTag) -> Option<String>;
+
+    /// All values of a text tag, it is empty if the tag does NOT exist.
+    fn get_text_tag_values(&self, key: &MyTag) -> Vec<String>;
+
     fn get_numeric_tag(&self, key: &MyTag) -> Option<u32>;
 
     fn get_numeric_tag_string(&self, key: &MyTag) -> Option<String>;
@@ -196,6 +348,11 @@ pub trait ReadTag {
     fn get_property_keys(&self) -> Result<Vec<String>, Error>;
 
     fn get_property(&self, key: &str) -> Result<Vec<String>, Error>;
+
+    fn get_pictures(&self) -> Result<Vec<MyPicture>, Error>;
+
+    /// It is `None` if the property is NOT available for the file.
+    fn get_audio_property(&self, key: &AudioProperty) -> Option<u32>;
 }
 
 pub trait WriteTagFile {
@@ -205,9 +362,24 @@ pub trait WriteTagFile {
 pub trait WriteTag: WriteTagFile {
     fn write_text_tag(&mut self, key: &MyTag, value: &str);
 
+    /// Write multiple values of a text tag, they will be joined by the multi-value separator
+    /// if the format only holds a single string.
+    fn write_text_tag_values(&mut self, key: &MyTag, values: &[String]);
+
     fn write_numeric_tag(&mut self, key: &MyTag, value: u32, padding: usize);
 
     fn clear_tag(&mut self, key: &MyTag);
+
+    /// Replace all values of property `key` with `values`.
+    fn write_property(&mut self, key: &str, values: &[String]);
+
+    /// Append `values` after the existing values of property `key`.
+    fn append_property(&mut self, key: &str, values: &[String]);
+
+    fn remove_property(&mut self, key: &str);
+
+    /// Replace all embedded pictures with `pictures`, an empty slice removes all of them.
+    fn write_pictures(&mut self, pictures: &[MyPicture]);
 }
 
 pub trait ReadWriteTag: ReadTag + WriteTag {}
\ No newline at end of file
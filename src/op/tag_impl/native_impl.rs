@@ -0,0 +1,649 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use id3::{ErrorKind as Id3ErrorKind, Tag as Id3Tag, TagLike};
+use id3::frame::{Comment, ExtendedText, Lyrics, Picture as Id3Picture,
+                 PictureType as Id3PictureType, UniqueFileIdentifier};
+use log::warn;
+use metaflac::block::PictureType as FlacPictureType;
+use metaflac::Tag as FlacTag;
+use mp4ameta::{Data, Fourcc, FreeformIdent, Img, ImgFmt, Tag as Mp4Tag};
+
+use crate::config::get_multi_value_separator;
+use crate::model::{MyPicture, PictureType};
+
+/// Properties and pictures of FLAC, MP3 and MP4 files, the TagLib binding could NOT write them.
+///
+/// They are written after TagLib saved the file, so TagLib does NOT overwrite them.
+pub struct NativeTag {
+    path: PathBuf,
+    raw: NativeTagRaw,
+}
+
+enum NativeTagRaw {
+    Flac(FlacTag),
+    Id3(Id3Tag),
+    Mp4(Mp4Tag),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum NativeFormat {
+    Flac,
+    Id3,
+    Mp4,
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    get_format(path).is_some()
+}
+
+/// Some keys of MP4 are NOT text, e.g. `TRACKNUMBER` and `COMPILATION`.
+pub fn is_writable_property(path: &Path, key: &str) -> bool {
+    match get_format(path) {
+        Some(NativeFormat::Mp4) => !MP4_NOT_TEXT_KEYS.contains(&key.to_uppercase().as_str()),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+/// Bits per sample in STREAMINFO of FLAC, `None` for other formats.
+pub fn get_bits_per_sample(path: &Path) -> Option<u32> {
+    match get_format(path) {
+        Some(NativeFormat::Flac) => FlacTag::read_from_path(path).ok()?
+            .get_streaminfo()
+            .map(|s| s.bits_per_sample as u32),
+        _ => None,
+    }
+}
+
+fn get_format(path: &Path) -> Option<NativeFormat> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    match ext.as_str() {
+        "flac" => Some(NativeFormat::Flac),
+        "mp3" => Some(NativeFormat::Id3),
+        "m4a" | "m4b" | "m4p" | "m4v" | "mp4" => Some(NativeFormat::Mp4),
+        _ => None,
+    }
+}
+
+impl NativeTag {
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let raw = match get_format(path) {
+            Some(NativeFormat::Flac) => NativeTagRaw::Flac(FlacTag::read_from_path(path)?),
+            Some(NativeFormat::Id3) => match Id3Tag::read_from_path(path) {
+                Ok(tag) => NativeTagRaw::Id3(tag),
+                Err(e) if matches!(e.kind, Id3ErrorKind::NoTag) => NativeTagRaw::Id3(Id3Tag::new()),
+                Err(e) => return Err(anyhow!(e)),
+            },
+            Some(NativeFormat::Mp4) => NativeTagRaw::Mp4(Mp4Tag::read_from_path(path)?),
+            None => return Err(anyhow!("Only FLAC, MP3 and MP4 files are supported, NOT {:?}",
+                path)),
+        };
+        Ok(Self { path: path.to_path_buf(), raw })
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        match &mut self.raw {
+            NativeTagRaw::Flac(tag) => tag.save()?,
+            NativeTagRaw::Id3(tag) => tag.write_to_path(&self.path, tag.version())?,
+            NativeTagRaw::Mp4(tag) => tag.write_to_path(&self.path)?,
+        }
+        Ok(())
+    }
+
+    /// Set values of the property, or remove it if `values` is empty.
+    pub fn set_property(&mut self, key: &str, values: &[String]) {
+        let key = key.to_uppercase();
+        match &mut self.raw {
+            NativeTagRaw::Flac(tag) => {
+                if values.is_empty() {
+                    tag.remove_vorbis(&key);
+                } else {
+                    tag.set_vorbis(key, values.to_vec());
+                }
+            }
+            NativeTagRaw::Id3(tag) => set_id3_property(tag, &key, values),
+            NativeTagRaw::Mp4(tag) => set_mp4_property(tag, &key, values, &self.path),
+        }
+    }
+
+    pub fn get_pictures(&self) -> Vec<MyPicture> {
+        match &self.raw {
+            NativeTagRaw::Flac(tag) => tag.pictures()
+                .map(|p| MyPicture {
+                    picture_type: from_flac_picture_type(p.picture_type),
+                    mime_type: p.mime_type.clone(),
+                    description: p.description.clone(),
+                    data: p.data.clone(),
+                })
+                .collect(),
+            NativeTagRaw::Id3(tag) => tag.pictures()
+                .map(|p| MyPicture {
+                    picture_type: from_id3_picture_type(p.picture_type),
+                    mime_type: p.mime_type.clone(),
+                    description: p.description.clone(),
+                    data: p.data.clone(),
+                })
+                .collect(),
+            // MP4 has no picture type
+            NativeTagRaw::Mp4(tag) => tag.artworks()
+                .map(|img| MyPicture {
+                    picture_type: PictureType::FrontCover,
+                    mime_type: get_mp4_mime_type(&img.fmt).to_owned(),
+                    description: String::new(),
+                    data: img.data.to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Replace all pictures.
+    pub fn set_pictures(&mut self, pictures: &[MyPicture]) {
+        match &mut self.raw {
+            NativeTagRaw::Flac(tag) => {
+                for picture_type in FLAC_PICTURE_TYPES {
+                    tag.remove_picture_type(picture_type);
+                }
+                for p in pictures {
+                    tag.push_block(metaflac::Block::Picture(metaflac::block::Picture {
+                        picture_type: to_flac_picture_type(&p.picture_type),
+                        mime_type: p.mime_type.clone(),
+                        description: p.description.clone(),
+                        data: p.data.clone(),
+                        ..metaflac::block::Picture::new()
+                    }));
+                }
+            }
+            NativeTagRaw::Id3(tag) => {
+                tag.remove_all_pictures();
+                for p in pictures {
+                    tag.add_frame(Id3Picture {
+                        mime_type: p.mime_type.clone(),
+                        picture_type: to_id3_picture_type(&p.picture_type),
+                        description: p.description.clone(),
+                        data: p.data.clone(),
+                    });
+                }
+            }
+            NativeTagRaw::Mp4(tag) => {
+                let images = pictures.iter()
+                    .filter(|p| {
+                        let supported = p.picture_type == PictureType::FrontCover
+                            && get_mp4_image_format(&p.mime_type).is_some();
+                        if !supported {
+                            warn!("Only front cover in JPEG / PNG / BMP is supported in file {:?}, \
+                            {} ({}) is ignored.", &self.path, p.picture_type, p.mime_type);
+                        }
+                        supported
+                    })
+                    .filter_map(|p| get_mp4_image_format(&p.mime_type)
+                        .map(|fmt| Img::new(fmt, p.data.clone())))
+                    .collect::<Vec<_>>();
+                tag.set_artworks(images);
+            }
+        }
+    }
+}
+
+/// ID3v2 frames of TagLib property keys, as TagLib maps them.
+const ID3_TEXT_FRAMES: [(&str, &str); 35] = [
+    ("ALBUM", "TALB"),
+    ("ALBUMARTIST", "TPE2"),
+    ("ALBUMARTISTSORT", "TSO2"),
+    ("ALBUMSORT", "TSOA"),
+    ("ARTIST", "TPE1"),
+    ("ARTISTSORT", "TSOP"),
+    ("BPM", "TBPM"),
+    ("COMPILATION", "TCMP"),
+    ("COMPOSER", "TCOM"),
+    ("COMPOSERSORT", "TSOC"),
+    ("CONDUCTOR", "TPE3"),
+    ("CONTENTGROUP", "TIT1"),
+    ("COPYRIGHT", "TCOP"),
+    ("DATE", "TDRC"),
+    ("DISCNUMBER", "TPOS"),
+    ("DISCSUBTITLE", "TSST"),
+    ("ENCODEDBY", "TENC"),
+    ("ENCODING", "TSSE"),
+    ("GENRE", "TCON"),
+    ("GROUPING", "GRP1"),
+    ("INITIALKEY", "TKEY"),
+    ("ISRC", "TSRC"),
+    ("LABEL", "TPUB"),
+    ("LANGUAGE", "TLAN"),
+    ("LYRICIST", "TEXT"),
+    ("MEDIA", "TMED"),
+    ("MOOD", "TMOO"),
+    ("ORIGINALALBUM", "TOAL"),
+    ("ORIGINALARTIST", "TOPE"),
+    ("ORIGINALDATE", "TDOR"),
+    ("RELEASEDATE", "TDRL"),
+    ("REMIXER", "TPE4"),
+    ("SUBTITLE", "TIT3"),
+    ("TITLE", "TIT2"),
+    ("TITLESORT", "TSOT"),
+];
+
+/// Descriptions of TXXX frames written by MusicBrainz Picard, the other keys are used as is.
+const ID3_TXXX_DESCRIPTIONS: [(&str, &str); 6] = [
+    ("MUSICBRAINZ_ALBUMARTISTID", "MusicBrainz Album Artist Id"),
+    ("MUSICBRAINZ_ALBUMID", "MusicBrainz Album Id"),
+    ("MUSICBRAINZ_ARTISTID", "MusicBrainz Artist Id"),
+    ("MUSICBRAINZ_RELEASEGROUPID", "MusicBrainz Release Group Id"),
+    ("MUSICBRAINZ_RELEASETRACKID", "MusicBrainz Release Track Id"),
+    ("MUSICBRAINZ_WORKID", "MusicBrainz Work Id"),
+];
+
+/// `MUSICBRAINZ_TRACKID` is stored in UFID frame of this owner.
+const MUSICBRAINZ_UFID_OWNER: &str = "http://musicbrainz.org";
+
+const ID3_LANGUAGE: &str = "eng";
+
+fn set_id3_property(tag: &mut Id3Tag, key: &str, values: &[String]) {
+    match key {
+        // TagLib reads the one without description
+        "COMMENT" => {
+            tag.remove_comment(Some(""), None);
+            if !values.is_empty() {
+                tag.add_frame(Comment {
+                    lang: ID3_LANGUAGE.to_owned(),
+                    description: String::new(),
+                    text: values.join(get_multi_value_separator()),
+                });
+            }
+        }
+        "LYRICS" => {
+            tag.remove_all_lyrics();
+            if !values.is_empty() {
+                tag.add_frame(Lyrics {
+                    lang: ID3_LANGUAGE.to_owned(),
+                    description: String::new(),
+                    text: values.join(get_multi_value_separator()),
+                });
+            }
+        }
+        "MUSICBRAINZ_TRACKID" => {
+            tag.remove_unique_file_identifier_by_owner_identifier(MUSICBRAINZ_UFID_OWNER);
+            if let Some(value) = values.first() {
+                tag.add_frame(UniqueFileIdentifier {
+                    owner_identifier: MUSICBRAINZ_UFID_OWNER.to_owned(),
+                    identifier: value.as_bytes().to_vec(),
+                });
+            }
+        }
+        _ => match ID3_TEXT_FRAMES.iter().find(|(k, _)| *k == key) {
+            Some((_, id)) => {
+                tag.remove(id);
+                if !values.is_empty() {
+                    tag.set_text_values(id, values);
+                }
+            }
+            None => {
+                let description = ID3_TXXX_DESCRIPTIONS.iter()
+                    .find(|(k, _)| *k == key)
+                    .map_or(key, |(_, d)| d);
+                // TagLib compares the description case insensitive
+                let existing = tag.extended_texts()
+                    .filter(|t| t.description.eq_ignore_ascii_case(description))
+                    .map(|t| t.description.clone())
+                    .collect::<Vec<_>>();
+                for d in existing {
+                    tag.remove_extended_text(Some(&d), None);
+                }
+                if !values.is_empty() {
+                    tag.add_frame(ExtendedText {
+                        description: description.to_owned(),
+                        value: values.join("\u{0}"),
+                    });
+                }
+            }
+        },
+    }
+}
+
+/// MP4 atoms of TagLib property keys, as TagLib maps them.
+const MP4_TEXT_ATOMS: [(&str, Fourcc); 19] = [
+    ("ALBUM", Fourcc(*b"\xa9alb")),
+    ("ALBUMARTIST", Fourcc(*b"aART")),
+    ("ALBUMARTISTSORT", Fourcc(*b"soaa")),
+    ("ALBUMSORT", Fourcc(*b"soal")),
+    ("ARTIST", Fourcc(*b"\xa9ART")),
+    ("ARTISTSORT", Fourcc(*b"soar")),
+    ("COMMENT", Fourcc(*b"\xa9cmt")),
+    ("COMPOSER", Fourcc(*b"\xa9wrt")),
+    ("COMPOSERSORT", Fourcc(*b"soco")),
+    ("COPYRIGHT", Fourcc(*b"cprt")),
+    ("DATE", Fourcc(*b"\xa9day")),
+    ("ENCODEDBY", Fourcc(*b"\xa9too")),
+    ("GENRE", Fourcc(*b"\xa9gen")),
+    ("GROUPING", Fourcc(*b"\xa9grp")),
+    ("LYRICS", Fourcc(*b"\xa9lyr")),
+    ("MOVEMENTNAME", Fourcc(*b"\xa9mvn")),
+    ("TITLE", Fourcc(*b"\xa9nam")),
+    ("TITLESORT", Fourcc(*b"sonm")),
+    ("WORK", Fourcc(*b"\xa9wrk")),
+];
+
+const MP4_BPM: Fourcc = Fourcc(*b"tmpo");
+
+const MP4_STANDARD_GENRE: Fourcc = Fourcc(*b"gnre");
+
+const MP4_NOT_TEXT_KEYS: [&str; 9] = [
+    "COMPILATION",
+    "DISCNUMBER",
+    "GAPLESSPLAYBACK",
+    "MOVEMENTCOUNT",
+    "MOVEMENTNUMBER",
+    "PODCAST",
+    "SHOWWORKMOVEMENT",
+    "TRACKNUMBER",
+    "TVSEASON",
+];
+
+/// The other keys are written as freeform atoms of iTunes with the key as name.
+const MP4_FREEFORM_NAMES: [(&str, &str); 7] = [
+    ("MUSICBRAINZ_ALBUMARTISTID", "MusicBrainz Album Artist Id"),
+    ("MUSICBRAINZ_ALBUMID", "MusicBrainz Album Id"),
+    ("MUSICBRAINZ_ARTISTID", "MusicBrainz Artist Id"),
+    ("MUSICBRAINZ_RELEASEGROUPID", "MusicBrainz Release Group Id"),
+    ("MUSICBRAINZ_RELEASETRACKID", "MusicBrainz Release Track Id"),
+    ("MUSICBRAINZ_TRACKID", "MusicBrainz Track Id"),
+    ("MUSICBRAINZ_WORKID", "MusicBrainz Work Id"),
+];
+
+const MP4_FREEFORM_MEAN: &str = "com.apple.iTunes";
+
+fn set_mp4_property(tag: &mut Mp4Tag, key: &str, values: &[String], path: &Path) {
+    let data = values.iter().map(|v| Data::Utf8(v.to_owned())).collect::<Vec<_>>();
+    if key == "BPM" {
+        tag.remove_data_of(&MP4_BPM);
+        match values.first().map(|v| v.trim().parse::<u16>()) {
+            Some(Ok(bpm)) => tag.set_data(MP4_BPM, Data::BeSigned(bpm.to_be_bytes().to_vec())),
+            Some(Err(_)) => warn!("BPM of file {:?} must be an integer, could NOT set {:?}",
+                path, values),
+            None => (),
+        }
+    } else if let Some((_, fourcc)) = MP4_TEXT_ATOMS.iter().find(|(k, _)| *k == key) {
+        if key == "GENRE" {
+            tag.remove_data_of(&MP4_STANDARD_GENRE);
+        }
+        tag.remove_data_of(fourcc);
+        if !data.is_empty() {
+            tag.set_all_data(*fourcc, data);
+        }
+    } else {
+        let name = MP4_FREEFORM_NAMES.iter()
+            .find(|(k, _)| *k == key)
+            .map_or(key, |(_, n)| n);
+        let ident = FreeformIdent::new(MP4_FREEFORM_MEAN, name);
+        tag.remove_data_of(&ident);
+        if !data.is_empty() {
+            tag.set_all_data(ident, data);
+        }
+    }
+}
+
+fn get_mp4_mime_type(fmt: &ImgFmt) -> &'static str {
+    match fmt {
+        ImgFmt::Bmp => "image/bmp",
+        ImgFmt::Jpeg => "image/jpeg",
+        ImgFmt::Png => "image/png",
+    }
+}
+
+fn get_mp4_image_format(mime_type: &str) -> Option<ImgFmt> {
+    match mime_type.to_lowercase().as_str() {
+        "image/bmp" => Some(ImgFmt::Bmp),
+        "image/jpeg" | "image/jpg" => Some(ImgFmt::Jpeg),
+        "image/png" => Some(ImgFmt::Png),
+        _ => None,
+    }
+}
+
+/// Picture types in the order of their code in ID3v2 APIC and FLAC PICTURE.
+const ALL_PICTURE_TYPES: [PictureType; 21] = [
+    PictureType::Other,
+    PictureType::FileIcon,
+    PictureType::OtherFileIcon,
+    PictureType::FrontCover,
+    PictureType::BackCover,
+    PictureType::LeafletPage,
+    PictureType::Media,
+    PictureType::LeadArtist,
+    PictureType::Artist,
+    PictureType::Conductor,
+    PictureType::Band,
+    PictureType::Composer,
+    PictureType::Lyricist,
+    PictureType::RecordingLocation,
+    PictureType::DuringRecording,
+    PictureType::DuringPerformance,
+    PictureType::MovieScreenCapture,
+    PictureType::ColouredFish,
+    PictureType::Illustration,
+    PictureType::BandLogo,
+    PictureType::PublisherLogo,
+];
+
+const FLAC_PICTURE_TYPES: [FlacPictureType; 21] = [
+    FlacPictureType::Other,
+    FlacPictureType::Icon,
+    FlacPictureType::OtherIcon,
+    FlacPictureType::CoverFront,
+    FlacPictureType::CoverBack,
+    FlacPictureType::Leaflet,
+    FlacPictureType::Media,
+    FlacPictureType::LeadArtist,
+    FlacPictureType::Artist,
+    FlacPictureType::Conductor,
+    FlacPictureType::Band,
+    FlacPictureType::Composer,
+    FlacPictureType::Lyricist,
+    FlacPictureType::RecordingLocation,
+    FlacPictureType::DuringRecording,
+    FlacPictureType::DuringPerformance,
+    FlacPictureType::ScreenCapture,
+    FlacPictureType::BrightFish,
+    FlacPictureType::Illustration,
+    FlacPictureType::BandLogo,
+    FlacPictureType::PublisherLogo,
+];
+
+const ID3_PICTURE_TYPES: [Id3PictureType; 21] = [
+    Id3PictureType::Other,
+    Id3PictureType::Icon,
+    Id3PictureType::OtherIcon,
+    Id3PictureType::CoverFront,
+    Id3PictureType::CoverBack,
+    Id3PictureType::Leaflet,
+    Id3PictureType::Media,
+    Id3PictureType::LeadArtist,
+    Id3PictureType::Artist,
+    Id3PictureType::Conductor,
+    Id3PictureType::Band,
+    Id3PictureType::Composer,
+    Id3PictureType::Lyricist,
+    Id3PictureType::RecordingLocation,
+    Id3PictureType::DuringRecording,
+    Id3PictureType::DuringPerformance,
+    Id3PictureType::ScreenCapture,
+    Id3PictureType::BrightFish,
+    Id3PictureType::Illustration,
+    Id3PictureType::BandLogo,
+    Id3PictureType::PublisherLogo,
+];
+
+fn get_picture_code(picture_type: &PictureType) -> usize {
+    ALL_PICTURE_TYPES.iter().position(|t| t == picture_type).unwrap_or_default()
+}
+
+fn to_flac_picture_type(picture_type: &PictureType) -> FlacPictureType {
+    FLAC_PICTURE_TYPES[get_picture_code(picture_type)]
+}
+
+fn from_flac_picture_type(picture_type: FlacPictureType) -> PictureType {
+    FLAC_PICTURE_TYPES.iter()
+        .position(|t| *t == picture_type)
+        .map_or(PictureType::Other, |i| ALL_PICTURE_TYPES[i])
+}
+
+fn to_id3_picture_type(picture_type: &PictureType) -> Id3PictureType {
+    ID3_PICTURE_TYPES[get_picture_code(picture_type)]
+}
+
+fn from_id3_picture_type(picture_type: Id3PictureType) -> PictureType {
+    ID3_PICTURE_TYPES.iter()
+        .position(|t| *t == picture_type)
+        .map_or(PictureType::Other, |i| ALL_PICTURE_TYPES[i])
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    use id3::frame::PictureType as Id3PictureType;
+    use id3::TagLike;
+    use metaflac::block::PictureType as FlacPictureType;
+
+    use crate::model::{MyPicture, PictureType};
+
+    use super::{from_flac_picture_type, from_id3_picture_type, get_bits_per_sample,
+                is_supported, is_writable_property, NativeTag, to_flac_picture_type, to_id3_picture_type};
+
+    /// `fLaC` and a STREAMINFO block of 44.1kHz, 2 channels, 24 bits.
+    fn write_flac(path: &Path) {
+        let mut data = b"fLaC".to_vec();
+        // last block, STREAMINFO, 34 bytes
+        data.extend_from_slice(&[0x80, 0x00, 0x00, 0x22]);
+        data.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        // 44100Hz (20 bits), 2 channels - 1 (3 bits), 24 bits - 1 (5 bits), total samples
+        data.extend_from_slice(&[0x0a, 0xc4, 0x43, 0x70, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0u8; 16]);
+        fs::write(path, data).unwrap();
+    }
+
+    fn picture(picture_type: PictureType) -> MyPicture {
+        MyPicture {
+            picture_type,
+            mime_type: "image/png".to_owned(),
+            description: "desc".to_owned(),
+            data: vec![0x89, b'P', b'N', b'G'],
+        }
+    }
+
+    #[test]
+    fn test_is_supported() {
+        assert!(is_supported(Path::new("a.FLAC")));
+        assert!(is_supported(Path::new("a.mp3")));
+        assert!(is_supported(Path::new("a.m4a")));
+        assert!(!is_supported(Path::new("a.ogg")));
+        assert!(!is_supported(Path::new("a")));
+
+        assert!(is_writable_property(Path::new("a.flac"), "TRACKNUMBER"));
+        assert!(is_writable_property(Path::new("a.m4a"), "TITLESORT"));
+        assert!(!is_writable_property(Path::new("a.m4a"), "tracknumber"));
+        assert!(!is_writable_property(Path::new("a.ogg"), "TITLESORT"));
+    }
+
+    #[test]
+    fn test_properties() {
+        let dir = env::temp_dir().join("music-tag-cli-native-properties");
+        fs::create_dir_all(&dir).unwrap();
+        let values = vec!["A".to_owned(), "B".to_owned()];
+
+        let flac = dir.join("properties.flac");
+        write_flac(&flac);
+        let mut tag = NativeTag::read(&flac).unwrap();
+        tag.set_property("titlesort", &values);
+        tag.set_property("LABEL", &values[..1]);
+        tag.save().unwrap();
+        let tag = metaflac::Tag::read_from_path(&flac).unwrap();
+        assert_eq!(tag.get_vorbis("TITLESORT").unwrap().collect::<Vec<_>>(), vec!["A", "B"]);
+        assert_eq!(tag.get_vorbis("LABEL").unwrap().collect::<Vec<_>>(), vec!["A"]);
+
+        let mut tag = NativeTag::read(&flac).unwrap();
+        tag.set_property("LABEL", &[]);
+        tag.save().unwrap();
+        assert!(metaflac::Tag::read_from_path(&flac).unwrap().get_vorbis("LABEL").is_none());
+
+        let mp3 = dir.join("properties.mp3");
+        fs::write(&mp3, [0u8; 128]).unwrap();
+        let mut tag = NativeTag::read(&mp3).unwrap();
+        tag.set_property("TITLESORT", &values);
+        tag.set_property("MUSICBRAINZ_ALBUMID", &values[..1]);
+        tag.set_property("MUSICBRAINZ_TRACKID", &values[..1]);
+        tag.set_property("CATALOGNUMBER", &values[..1]);
+        tag.save().unwrap();
+        let tag = id3::Tag::read_from_path(&mp3).unwrap();
+        assert_eq!(tag.get("TSOT").and_then(|f| f.content().text_values())
+                       .map(|v| v.collect::<Vec<_>>()), Some(vec!["A", "B"]));
+        let texts = tag.extended_texts()
+            .map(|t| (t.description.as_str(), t.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec![("MusicBrainz Album Id", "A"), ("CATALOGNUMBER", "A")]);
+        assert_eq!(tag.unique_file_identifiers().next().map(|u| u.identifier.clone()),
+                   Some(b"A".to_vec()));
+
+        let mut tag = NativeTag::read(&mp3).unwrap();
+        tag.set_property("TITLESORT", &[]);
+        tag.set_property("musicbrainz_albumid", &[]);
+        tag.save().unwrap();
+        let tag = id3::Tag::read_from_path(&mp3).unwrap();
+        assert!(tag.get("TSOT").is_none());
+        assert_eq!(tag.extended_texts().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_picture_type() {
+        assert_eq!(to_flac_picture_type(&PictureType::BackCover), FlacPictureType::CoverBack);
+        assert_eq!(from_flac_picture_type(FlacPictureType::BrightFish), PictureType::ColouredFish);
+        assert_eq!(to_id3_picture_type(&PictureType::FrontCover), Id3PictureType::CoverFront);
+        assert_eq!(from_id3_picture_type(Id3PictureType::Undefined(99)), PictureType::Other);
+    }
+
+    #[test]
+    fn test_get_bits_per_sample() {
+        let dir = env::temp_dir().join("music-tag-cli-native-bits");
+        fs::create_dir_all(&dir).unwrap();
+        let flac = dir.join("bits.flac");
+        write_flac(&flac);
+        let mp3 = dir.join("bits.mp3");
+        fs::write(&mp3, [0u8; 128]).unwrap();
+
+        assert_eq!(get_bits_per_sample(&flac), Some(24));
+        assert_eq!(get_bits_per_sample(&mp3), None);
+        assert_eq!(get_bits_per_sample(&dir.join("missing.flac")), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pictures() {
+        let dir = env::temp_dir().join("music-tag-cli-native-pictures");
+        fs::create_dir_all(&dir).unwrap();
+        let pictures = vec![picture(PictureType::FrontCover), picture(PictureType::BackCover)];
+
+        let flac = dir.join("pictures.flac");
+        write_flac(&flac);
+        let mp3 = dir.join("pictures.mp3");
+        fs::write(&mp3, [0u8; 128]).unwrap();
+        for path in [&flac, &mp3] {
+            let mut tag = NativeTag::read(path).unwrap();
+            assert!(tag.get_pictures().is_empty());
+            tag.set_pictures(&pictures);
+            tag.save().unwrap();
+            assert_eq!(NativeTag::read(path).unwrap().get_pictures(), pictures);
+
+            let mut tag = NativeTag::read(path).unwrap();
+            tag.set_pictures(&pictures[1..]);
+            tag.save().unwrap();
+            assert_eq!(NativeTag::read(path).unwrap().get_pictures(), &pictures[1..]);
+        }
+
+        assert!(NativeTag::read(&dir.join("pictures.ogg")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
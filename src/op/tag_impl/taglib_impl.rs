@@ -1,15 +1,21 @@
 use anyhow::{anyhow, Error};
-use log::info;
+use log::{info, warn};
 use taglib::{File as TagLibFile, FileType as TagLibFileType};
+use std::collections::BTreeMap;
 use std::path::Path;
 
-use crate::model::MyTag;
+use crate::config::get_multi_value_separator;
+use crate::model::{AudioProperty, MyPicture, MyTag};
 use crate::op::{MAX_NUMBER, MIN_NATURAL_NUMBER};
 use super::{ReadTag, ReadWriteTag, WriteTag, WriteTagFile};
+use super::native_impl::{get_bits_per_sample, is_supported, is_writable_property, NativeTag};
 
 pub struct TaglibWrapper<'a> {
     file_name: &'a Path,
     file: TagLibFile,
+    /// Properties (empty to remove) and pictures to be written after TagLib saved the file.
+    props: BTreeMap<String, Vec<String>>,
+    pictures: Option<Vec<MyPicture>>,
 }
 
 impl<'a> TaglibWrapper<'a> {
@@ -24,6 +30,8 @@ impl<'a> TaglibWrapper<'a> {
                 Ok(TaglibWrapper {
                     file_name,
                     file,
+                    props: BTreeMap::new(),
+                    pictures: None,
                 })
             } else {
                 Err(anyhow!("No available tags for {:?} (error: {:?})",
@@ -36,6 +44,46 @@ impl<'a> TaglibWrapper<'a> {
     }
 }
 
+impl TaglibWrapper<'_> {
+    fn get_extended_tag(&self, key: &MyTag) -> Option<String> {
+        get_property_keys_of_tag(key).iter()
+            .filter_map(|k| self.get_property(k).ok())
+            .find_map(|v| v.into_iter().next())
+    }
+
+    fn write_extended_tag(&mut self, key: &MyTag, values: &[String]) {
+        if let Some(property_key) = get_property_keys_of_tag(key).first() {
+            if self.set_property(property_key, values) {
+                info!("file {:?} set tag {}: {:?}", &self.file_name, key, values);
+            } else {
+                warn!("Not supported tag {} in file {:?}, could NOT set {:?}",
+                    key, &self.file_name, values);
+            }
+        }
+    }
+
+    /// The property is written when saving, returns false if the file could NOT hold it.
+    fn set_property(&mut self, key: &str, values: &[String]) -> bool {
+        if is_writable_property(self.file_name, key) {
+            self.props.insert(key.to_uppercase(), values.to_vec());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Values of the tag which are NOT saved yet, the tag is written by TagLib again.
+    fn get_unsaved_values(&self, key: &MyTag) -> Option<&Vec<String>> {
+        get_property_keys_of_tag(key).first().and_then(|k| self.props.get(*k))
+    }
+
+    fn forget_unsaved_values(&mut self, key: &MyTag) {
+        for property_key in get_property_keys_of_tag(key) {
+            self.props.remove(*property_key);
+        }
+    }
+}
+
 impl ReadTag for TaglibWrapper<'_> {
     fn get_path(&self) -> &Path {
         self.file_name
@@ -43,6 +91,9 @@ impl ReadTag for TaglibWrapper<'_> {
 
     fn get_text_tag(&self, key: &MyTag) -> Option<String> {
         if key.is_text() || key.is_date() {
+            if let Some(values) = self.get_unsaved_values(key) {
+                return values.first().cloned();
+            }
             let t = &self.file.tag().unwrap();
             let result = match key {
                 MyTag::Title => t.title(),
@@ -58,7 +109,7 @@ impl ReadTag for TaglibWrapper<'_> {
 
                 MyTag::Date => t.date(),
 
-                _ => None,
+                _ => self.get_extended_tag(key),
             };
             result
         } else {
@@ -66,6 +117,18 @@ impl ReadTag for TaglibWrapper<'_> {
         }
     }
 
+    fn get_text_tag_values(&self, key: &MyTag) -> Vec<String> {
+        let values = get_property_keys_of_tag(key).iter()
+            .filter_map(|k| self.get_property(k).ok())
+            .find(|v| !v.is_empty())
+            .unwrap_or_default();
+        if !values.is_empty() {
+            values
+        } else {
+            self.get_text_tag(key).into_iter().collect()
+        }
+    }
+
     fn get_numeric_tag(&self, key: &MyTag) -> Option<u32> {
         if key.is_numeric() {
             let t = &self.file.tag().unwrap();
@@ -77,6 +140,8 @@ impl ReadTag for TaglibWrapper<'_> {
                 MyTag::DiscNumber => t.disc_number(),
                 MyTag::DiscTotal => t.disc_total(),
 
+                MyTag::Bpm => self.get_extended_tag(key).and_then(|s| parse_bpm(&s)),
+
                 _ => None,
             };
             result
@@ -96,6 +161,10 @@ impl ReadTag for TaglibWrapper<'_> {
                 MyTag::DiscNumber => t.disc_number_string(),
                 MyTag::DiscTotal => t.disc_total_string(),
 
+                MyTag::Bpm => self.get_extended_tag(key)
+                    .and_then(|s| parse_bpm(&s))
+                    .map(|u| u.to_string()),
+
                 _ => None,
             };
             result
@@ -105,29 +174,73 @@ impl ReadTag for TaglibWrapper<'_> {
     }
 
     fn get_property_keys(&self) -> Result<Vec<String>, Error> {
-        self.file.keys().map_err(|e| anyhow!(e))
+        let mut keys = self.file.keys().map_err(|e| anyhow!(e))?;
+        keys.retain(|k| self.props.get(&k.to_uppercase()).map_or(true, |v| !v.is_empty()));
+        for (key, values) in &self.props {
+            if !values.is_empty() && !keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                keys.push(key.to_owned());
+            }
+        }
+        Ok(keys)
     }
 
     fn get_property(&self, key: &str) -> Result<Vec<String>, Error> {
-        self.file.get_property(key).map_err(|e| anyhow!(e))
+        match self.props.get(&key.to_uppercase()) {
+            Some(values) => Ok(values.to_owned()),
+            None => self.file.get_property(key).map_err(|e| anyhow!(e)),
+        }
+    }
+
+    fn get_pictures(&self) -> Result<Vec<MyPicture>, Error> {
+        match &self.pictures {
+            Some(pictures) => Ok(pictures.clone()),
+            None => NativeTag::read(self.file_name).map(|t| t.get_pictures()),
+        }
+    }
+
+    fn get_audio_property(&self, key: &AudioProperty) -> Option<u32> {
+        let p = self.file.audioproperties().ok()?;
+        match key {
+            AudioProperty::Bitrate => Some(p.bitrate()),
+            AudioProperty::Length => Some(p.length()),
+            AudioProperty::SampleRate => Some(p.samplerate()),
+            AudioProperty::Channels => Some(p.channels()),
+            // the TagLib binding does NOT read it, so only FLAC is supported
+            AudioProperty::BitsPerSample => get_bits_per_sample(self.file_name),
+        }
     }
 }
 
 impl WriteTagFile for TaglibWrapper<'_> {
     fn save(&mut self) -> Result<(), Error> {
-        if self.file.save() {
-            info!("Save file {:?} ok.", &self.file_name);
-            Ok(())
-        } else {
-            Err(anyhow!("Save file {:?} FAILED! \
+        if !self.file.save() {
+            return Err(anyhow!("Save file {:?} FAILED! \
             Please check if file exists and it's attribute is NOT \"Read-only\".",
-                     &self.file_name))
+                     &self.file_name));
+        }
+
+        // TagLib would overwrite them if they were written before
+        if !self.props.is_empty() || self.pictures.is_some() {
+            let mut t = NativeTag::read(self.file_name)?;
+            for (key, values) in &self.props {
+                t.set_property(key, values);
+            }
+            if let Some(pictures) = &self.pictures {
+                t.set_pictures(pictures);
+            }
+            t.save().map_err(|e| anyhow!("Save properties and pictures of file {:?} FAILED! \
+            (error: {})", &self.file_name, e))?;
+            self.props.clear();
+            self.pictures = None;
         }
+        info!("Save file {:?} ok.", &self.file_name);
+        Ok(())
     }
 }
 
 impl WriteTag for TaglibWrapper<'_> {
     fn write_text_tag(&mut self, key: &MyTag, value: &str) {
+        self.forget_unsaved_values(key);
         if key.is_text() || key.is_date() {
             {
                 let t = &mut self.file.tag().unwrap();
@@ -187,12 +300,30 @@ impl WriteTag for TaglibWrapper<'_> {
                     self.file.set_date(value);
                     info!("file {:?} set tag {}: {}", &self.file_name, key, value);
                 }
-                _ => (),
+                _ => self.write_extended_tag(key, &[value.to_owned()]),
+            }
+        }
+    }
+
+    fn write_text_tag_values(&mut self, key: &MyTag, values: &[String]) {
+        match values {
+            [] => self.clear_tag(key),
+            [value] => self.write_text_tag(key, value),
+            _ => {
+                // some formats (e.g. OGG, APE) are NOT written natively, join the values
+                if let Some(property_key) = get_property_keys_of_tag(key).first() {
+                    if self.set_property(property_key, values) {
+                        info!("file {:?} set tag {}: {:?}", &self.file_name, key, values);
+                        return;
+                    }
+                }
+                self.write_text_tag(key, &values.join(get_multi_value_separator()));
             }
         }
     }
 
     fn write_numeric_tag(&mut self, key: &MyTag, value: u32, padding: usize) {
+        self.forget_unsaved_values(key);
         if key.is_numeric() {
             {
                 let t = &mut self.file.tag().unwrap();
@@ -235,6 +366,7 @@ impl WriteTag for TaglibWrapper<'_> {
                         info!("file {:?} set tag {}: {} with padding {}", &self.file_name,
                             key, value, padding);
                     }
+                    MyTag::Bpm => self.write_extended_tag(key, &[value.to_string()]),
                     _ => (),
                 }
             }
@@ -242,6 +374,7 @@ impl WriteTag for TaglibWrapper<'_> {
     }
 
     fn clear_tag(&mut self, key: &MyTag) {
+        self.forget_unsaved_values(key);
         {
             let t = &mut self.file.tag().unwrap();
             let completed = match key {
@@ -321,17 +454,111 @@ impl WriteTag for TaglibWrapper<'_> {
                     self.file.remove_date();
                     info!("file {:?} remove tag {}", &self.file_name, key);
                 }
-                _ => (),
+                _ => {
+                    let removed = get_property_keys_of_tag(key).iter()
+                        .all(|property_key| self.set_property(property_key, &[]));
+                    if removed {
+                        info!("file {:?} remove tag {}", &self.file_name, key);
+                    } else {
+                        warn!("Not supported tag {} in file {:?}, could NOT remove it",
+                            key, &self.file_name);
+                    }
+                }
             }
+        }
+    }
 
+    fn write_property(&mut self, key: &str, values: &[String]) {
+        if self.set_property(key, values) {
+            info!("file {:?} set property {}: {:?}", &self.file_name, key, values);
+        } else {
+            warn!("Not supported property {} in file {:?}, could NOT set {:?}",
+                key, &self.file_name, values);
         }
+    }
 
+    fn append_property(&mut self, key: &str, values: &[String]) {
+        let mut all = self.get_property(key).unwrap_or_default();
+        all.extend_from_slice(values);
+        if self.set_property(key, &all) {
+            info!("file {:?} add property {}: {:?}", &self.file_name, key, values);
+        } else {
+            warn!("Not supported property {} in file {:?}, could NOT add {:?}",
+                key, &self.file_name, values);
+        }
+    }
+
+    fn remove_property(&mut self, key: &str) {
+        if self.set_property(key, &[]) {
+            info!("file {:?} remove property {}", &self.file_name, key);
+        } else {
+            warn!("Not supported property {} in file {:?}, could NOT remove it",
+                key, &self.file_name);
+        }
+    }
 
+    fn write_pictures(&mut self, pictures: &[MyPicture]) {
+        if !is_supported(self.file_name) {
+            warn!("Pictures are only supported in FLAC, MP3 and MP4 files, could NOT write {:?}",
+                &self.file_name);
+            return;
+        }
+        self.pictures = Some(pictures.to_vec());
+        info!("file {:?} set pictures: {:?}", &self.file_name,
+            pictures.iter().map(|p| p.picture_type.to_string()).collect::<Vec<_>>());
     }
 }
 
 impl ReadWriteTag for TaglibWrapper<'_> {}
 
+/// Keys of TagLib `PropertyMap`, they are mapped to the native field of each container,
+/// e.g. `TITLESORT` is `TSOT` in ID3v2 and `sonm` in MP4.
+///
+/// The first key is used for writing, the others are only read, as some taggers use them
+/// (e.g. `ORGANIZATION` in Xiph comments, `CONTENTGROUP` of TagLib 1.x for ID3v2 `TIT1`).
+fn get_property_keys_of_tag(key: &MyTag) -> &'static [&'static str] {
+    match key {
+        MyTag::Title => &["TITLE"],
+        MyTag::Artist => &["ARTIST"],
+        MyTag::AlbumTitle => &["ALBUM"],
+        MyTag::AlbumArtist => &["ALBUMARTIST"],
+        MyTag::Genre => &["GENRE"],
+        MyTag::Composer => &["COMPOSER"],
+        MyTag::Comment => &["COMMENT"],
+        MyTag::Copyright => &["COPYRIGHT"],
+        MyTag::Lyrics => &["LYRICS"],
+        MyTag::Date => &["DATE"],
+        MyTag::TitleSort => &["TITLESORT"],
+        MyTag::ArtistSort => &["ARTISTSORT"],
+        MyTag::AlbumSort => &["ALBUMSORT"],
+        MyTag::AlbumArtistSort => &["ALBUMARTISTSORT"],
+        MyTag::Bpm => &["BPM"],
+        MyTag::Isrc => &["ISRC"],
+        MyTag::Label => &["LABEL", "ORGANIZATION", "PUBLISHER"],
+        MyTag::CatalogNumber => &["CATALOGNUMBER"],
+        MyTag::Grouping => &["GROUPING", "CONTENTGROUP"],
+        MyTag::Conductor => &["CONDUCTOR"],
+        MyTag::OriginalDate => &["ORIGINALDATE", "ORIGINALYEAR"],
+        MyTag::MusicbrainzTrackId => &["MUSICBRAINZ_TRACKID"],
+        MyTag::MusicbrainzAlbumId => &["MUSICBRAINZ_ALBUMID"],
+        MyTag::MusicbrainzArtistId => &["MUSICBRAINZ_ARTISTID"],
+        MyTag::MusicbrainzAlbumArtistId => &["MUSICBRAINZ_ALBUMARTISTID"],
+        MyTag::MusicbrainzReleaseGroupId => &["MUSICBRAINZ_RELEASEGROUPID"],
+        MyTag::MusicbrainzReleaseTrackId => &["MUSICBRAINZ_RELEASETRACKID"],
+        MyTag::MusicbrainzWorkId => &["MUSICBRAINZ_WORKID"],
+        _ => &[],
+    }
+}
+
+/// Some taggers write BPM as a decimal, e.g. "128.00".
+fn parse_bpm(s: &str) -> Option<u32> {
+    let s = s.trim();
+    s.parse::<u32>().ok()
+        .or_else(|| s.parse::<f64>().ok()
+            .filter(|f| f.is_finite() && *f >= 0.0)
+            .map(|f| f.round() as u32))
+}
+
 pub fn available_suffix(file_name: &str) -> bool {
     for suffix in TagLibFileType::all_suffix().iter() {
         if file_name.to_lowercase().ends_with(*suffix) {
@@ -343,7 +570,15 @@ pub fn available_suffix(file_name: &str) -> bool {
 
 #[cfg(test)]
 mod test {
-    use super::available_suffix;
+    use super::{available_suffix, parse_bpm};
+
+    #[test]
+    fn test_parse_bpm() {
+        assert_eq!(parse_bpm("128"), Some(128));
+        assert_eq!(parse_bpm(" 127.6 "), Some(128));
+        assert_eq!(parse_bpm("fast"), None);
+        assert_eq!(parse_bpm("-1"), None);
+    }
 
     #[test]
     fn test_available_suffix() {
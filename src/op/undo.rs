@@ -0,0 +1,281 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use log::{error, info, warn};
+
+use crate::journal::{self, JournalEntry};
+use crate::model::MyTag;
+use crate::op::Action;
+use crate::op::tag_impl::{ReadWriteTag, TagImpl, WriteTag, WriteTagFile};
+use crate::util::path::move_file;
+
+pub struct UndoAction {
+    run_id: String,
+    dry_run: bool,
+    skip_changed: bool,
+    entries: Vec<JournalEntry>,
+}
+
+impl UndoAction {
+    pub fn new(run_id: &str,
+               dry_run: bool,
+               skip_changed: bool) -> Result<Self, Error> {
+        let entries = journal::read_entries(run_id)?;
+        Self::check(run_id, &entries)?;
+        Ok(Self {
+            run_id: run_id.to_owned(),
+            dry_run,
+            skip_changed,
+            entries,
+        })
+    }
+
+    fn check(run_id: &str, entries: &[JournalEntry]) -> Result<(), Error> {
+        for entry in entries {
+            if let JournalEntry::Undone { run_id: by, .. } = entry {
+                return Err(anyhow!("Run {} has already been undone by run {}.", run_id, by));
+            }
+        }
+        Ok(())
+    }
+
+    fn undo_file(&self, path: &Path, changes: &[&JournalEntry]) -> Result<bool, Error> {
+        let mut t = TagImpl::new(&path, self.dry_run)?;
+        for change in changes.iter().rev() {
+            match change {
+                JournalEntry::Tag { tag, old, .. } => restore_tag(&mut t, MyTag::from_str(tag)?, old),
+                JournalEntry::Prop { key, old, .. } => {
+                    if old.is_empty() {
+                        t.remove_property(key);
+                    } else {
+                        t.write_property(key, old);
+                    }
+                }
+                JournalEntry::Pictures { old, .. } =>
+                    t.write_pictures(&journal::read_pictures(&self.run_id, old)?),
+                _ => (),
+            }
+        }
+        t.save()?;
+        Ok(true)
+    }
+
+    fn undo_rename(&self, from: &Path, to: &Path) -> Result<bool, Error> {
+        if !self.dry_run {
+            // the source directory may be removed by `--remove-empty-dirs`
+            if let Some(dir) = from.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| anyhow!("Create directory {:?} failed. (error: {:?})", dir, e))?;
+            }
+            move_file(to, from)
+                .map_err(|e| anyhow!("Rename file {:?} to {:?} failed. (error: {:?})", to, from, e))?;
+            journal::record_rename(to, from);
+        }
+        info!("Rename file {:?} to {:?}", to, from);
+        Ok(true)
+    }
+}
+
+impl Action for UndoAction {
+    fn do_any(&mut self) -> Result<(), Error> {
+        let changed = get_changed_files(&self.entries, |p| journal::get_file_state(p).ok());
+        if !changed.is_empty() {
+            if self.skip_changed {
+                for path in &changed {
+                    warn!("file {:?} was changed since run {}, skip it.", path, self.run_id);
+                }
+            } else {
+                return Err(anyhow!("{} file(s) changed since run {}, could NOT undo: {:?}",
+                    changed.len(), self.run_id, changed));
+            }
+        }
+
+        let mut failed = 0;
+        for (path, changes) in get_file_changes(&self.entries).iter().rev() {
+            if !changed.contains(path) {
+                if let Err(e) = self.undo_file(path, changes) {
+                    error!("Error: {}", e);
+                    failed += 1;
+                }
+            }
+        }
+
+        for entry in self.entries.iter().rev() {
+            if let JournalEntry::Rename { from, to } = entry {
+                if !changed.contains(to) {
+                    if let Err(e) = self.undo_rename(from, to) {
+                        error!("Error: {}", e);
+                        failed += 1;
+                    }
+                }
+            }
+        }
+
+        if failed > 0 {
+            return Err(anyhow!("{} file(s) could NOT be restored, run {} is NOT marked as undone.",
+                failed, self.run_id));
+        }
+        // run could be undone again later for the skipped files
+        if !self.dry_run && changed.is_empty() {
+            let by = journal::get_run_id().unwrap_or_default();
+            journal::mark_undone(&self.run_id, &by)?;
+        }
+        Ok(())
+    }
+}
+
+fn restore_tag(t: &mut dyn ReadWriteTag, tag: &MyTag, old: &[String]) {
+    match old.first() {
+        None => t.clear_tag(tag),
+        Some(value) if tag.is_numeric() => match value.parse::<u32>() {
+            Ok(u) => t.write_numeric_tag(tag, u, value.len()),
+            Err(_) => error!("file {:?} could NOT restore tag {}: {}", t.get_path(), tag, value),
+        },
+        Some(value) if tag.is_date() => t.write_text_tag(tag, value),
+        Some(_) => t.write_text_tag_values(tag, old),
+    }
+}
+
+/// Tag, property and picture changes of the saved files, in the order of their first change.
+fn get_file_changes(entries: &[JournalEntry]) -> Vec<(PathBuf, Vec<&JournalEntry>)> {
+    let saved = entries.iter()
+        .filter_map(|e| match e {
+            JournalEntry::Saved { path, .. } => Some(path),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    let mut result: Vec<(PathBuf, Vec<&JournalEntry>)> = Vec::new();
+    for entry in entries {
+        let path = match entry {
+            JournalEntry::Tag { path, .. }
+            | JournalEntry::Prop { path, .. }
+            | JournalEntry::Pictures { path, .. } => path,
+            _ => continue,
+        };
+        if !saved.contains(path) {
+            continue;
+        }
+
+        match result.iter_mut().find(|(p, _)| p == path) {
+            Some((_, changes)) => changes.push(entry),
+            None => result.push((path.to_owned(), vec![entry])),
+        }
+    }
+    result
+}
+
+/// Files which are NOT same as the run left them, `get_state` gets (size, mtime) of a file.
+fn get_changed_files<F>(entries: &[JournalEntry], get_state: F) -> Vec<PathBuf>
+    where F: Fn(&Path) -> Option<(u64, u64)>
+{
+    let mut result: Vec<PathBuf> = Vec::new();
+    let mut checked = HashSet::new();
+    for entry in entries.iter().rev() {
+        match entry {
+            JournalEntry::Saved { path, size, mtime }
+                if checked.insert(path) && get_state(path) != Some((*size, *mtime)) => {
+                result.push(path.to_owned());
+            }
+            JournalEntry::Rename { from, to } if !to.exists() || from.exists() => {
+                result.push(to.to_owned());
+            }
+            _ => (),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::journal::JournalEntry;
+
+    use super::{get_changed_files, get_file_changes, UndoAction};
+
+    fn tag(path: &str, tag: &str, old: &str, new: &str) -> JournalEntry {
+        JournalEntry::Tag {
+            path: PathBuf::from(path),
+            tag: tag.to_owned(),
+            old: vec![old.to_owned()],
+            new: vec![new.to_owned()],
+        }
+    }
+
+    fn saved(path: &str, mtime: u64) -> JournalEntry {
+        JournalEntry::Saved { path: PathBuf::from(path), size: 100, mtime }
+    }
+
+    #[test]
+    fn test_get_file_changes() {
+        let entries = vec![
+            tag("/a.flac", "title", "A", "a"),
+            tag("/b.flac", "title", "B", "b"),
+            tag("/a.flac", "artist", "X", "x"),
+            saved("/a.flac", 1),
+            // save failed, nothing to undo
+            tag("/c.flac", "title", "C", "c"),
+            JournalEntry::Pictures {
+                path: PathBuf::from("/b.flac"),
+                old: vec![],
+                new: vec!["front-cover".to_owned()],
+            },
+            saved("/b.flac", 2),
+        ];
+
+        let changes = get_file_changes(&entries);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].0, PathBuf::from("/a.flac"));
+        assert_eq!(changes[0].1, vec![&entries[0], &entries[2]]);
+        assert_eq!(changes[1].0, PathBuf::from("/b.flac"));
+        assert_eq!(changes[1].1, vec![&entries[1], &entries[5]]);
+    }
+
+    #[test]
+    fn test_get_changed_files() {
+        let entries = vec![
+            tag("/a.flac", "title", "A", "a"),
+            saved("/a.flac", 1),
+            tag("/a.flac", "title", "a", "aa"),
+            saved("/a.flac", 2),
+            tag("/b.flac", "title", "B", "b"),
+            saved("/b.flac", 3),
+        ];
+
+        let get_state = |p: &Path| if p == Path::new("/a.flac") {
+            Some((100, 2))
+        } else {
+            Some((100, 4))
+        };
+        assert_eq!(get_changed_files(&entries, get_state), vec![PathBuf::from("/b.flac")]);
+        assert_eq!(get_changed_files(&entries, |_| None).len(), 2);
+    }
+
+    #[test]
+    fn test_undo_rename_removed_dir() {
+        let dir = env::temp_dir().join("music-tag-cli-undo-test");
+        let _ = fs::remove_dir_all(&dir);
+        let from = dir.join("Inbox").join("CD1").join("01.flac");
+        let to = dir.join("Library").join("01 - Title.flac");
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::write(&to, b"fLaC").unwrap();
+
+        // source directories were removed by `--remove-empty-dirs`
+        let undo = UndoAction {
+            run_id: "20240601-120000-000".to_owned(),
+            dry_run: false,
+            skip_changed: false,
+            entries: vec![],
+        };
+        assert!(undo.undo_rename(&from, &to).unwrap());
+        assert!(from.is_file());
+        assert!(!to.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
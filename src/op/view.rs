@@ -13,13 +13,15 @@ pub struct ViewAction {
     tags: Vec<MyTag>,
     where_clause: Option<WhereClause>,
     with_properties: bool,
+    with_audio_properties: bool,
 }
 
 impl ViewAction {
     pub fn new<P>(dir: P,
                   tags: &[MyTag],
                   where_string: &Option<String>,
-                  with_properties: bool) -> Result<Self, Error>
+                  with_properties: bool,
+                  with_audio_properties: bool) -> Result<Self, Error>
         where P: AsRef<Path>
     {
         let it = get_file_iterator(dir.as_ref())?;
@@ -30,6 +32,7 @@ impl ViewAction {
             tags,
             where_clause,
             with_properties,
+            with_audio_properties,
         })
     }
 }
@@ -63,6 +66,10 @@ impl ReadAction for ViewAction {
         self.with_properties
     }
 
+    fn with_audio_properties(&self) -> bool {
+        self.with_audio_properties
+    }
+
     fn get_content(&self, path: &Path, v: &MyValues) -> Result<Option<String>, Error> {
         let mut w = Vec::new();
         let success = output_text(&mut w, self.tags(), &v, path)?;
@@ -101,16 +108,33 @@ fn output_text<W, P>(writer: &mut W,
                         writeln!(writer, "{}", v)?;
                     }
                 }
+                other_tag if other_tag.is_extended() && v.get_text(tag).is_none() => (),
                 other_tag => {
                     let tag_name = other_tag.to_string();
-                    writeln!(writer, "{} {} - {}",
-                             tag_name,
-                             " ".repeat(get_space_count(&tag_name)),
-                             &v.get_text(tag).unwrap_or_default())?;
+                    match v.get_texts(tag) {
+                        Some(values) if values.len() > 1 =>
+                            writeln!(writer, "{} {} - {:?}",
+                                     tag_name,
+                                     " ".repeat(get_space_count(&tag_name)),
+                                     values)?,
+                        _ =>
+                            writeln!(writer, "{} {} - {}",
+                                     tag_name,
+                                     " ".repeat(get_space_count(&tag_name)),
+                                     &v.get_text(tag).unwrap_or_default())?,
+                    }
                 }
             }
         }
 
+        if let Some(audio_properties) = v.get_audio_properties() {
+            writeln!(writer, "-- AUDIO PROPERTIES for {:?} --", path.as_ref())?;
+            for (p, value) in audio_properties {
+                let name = p.to_string();
+                writeln!(writer, "{} {} - {}", name, " ".repeat(get_space_count(&name)), value)?;
+            }
+        }
+
         if !v.is_empty_properties() {
             let keys = v.get_prop_keys().unwrap();
             let len = keys.len();
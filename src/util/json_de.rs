@@ -4,7 +4,7 @@ use std::io::ErrorKind::InvalidData;
 use std::io::Result as IOResult;
 
 use serde::de::DeserializeOwned;
-use serde_json::Deserializer;
+use serde_json::{Deserializer, Map, Value};
 
 fn read_skipping_ws(mut reader: impl Read) -> IOResult<u8> {
     loop {
@@ -57,6 +57,54 @@ fn yield_next_obj<T, R>(mut reader: R, at_start: &mut bool) -> IOResult<Option<T
     }
 }
 
+/// Get the first non-whitespace byte, it is put back before the returned reader.
+pub fn peek_json<R>(mut reader: R) -> IOResult<(u8, impl Read)>
+    where R: Read
+{
+    let peek = read_skipping_ws(&mut reader)?;
+    Ok((peek, Cursor::new([peek]).chain(reader)))
+}
+
+/// Read fields of a JSON object until `key`, then its array value could be read by
+/// `iter_json_array`. The fields before `key` must be objects, arrays or strings, and the fields
+/// after it are NOT read.
+pub fn read_json_fields_until<T, R>(mut reader: R, key: &str) -> IOResult<T>
+    where T: DeserializeOwned,
+          R: Read
+{
+    if read_skipping_ws(&mut reader)? != b'{' {
+        return Err(invalid_data("`{` not found"));
+    }
+
+    let mut fields = Map::new();
+    loop {
+        let peek = read_skipping_ws(&mut reader)?;
+        let field: String = deserialize_single(Cursor::new([peek]).chain(&mut reader))?;
+        if read_skipping_ws(&mut reader)? != b':' {
+            return Err(invalid_data("`:` not found"));
+        }
+        if field == key {
+            break;
+        }
+
+        // the value is read until its end, numbers and literals need one more byte
+        let peek = read_skipping_ws(&mut reader)?;
+        if !matches!(peek, b'{' | b'[' | b'"') {
+            return Err(invalid_data(&format!("field `{}` must be an object, array or string",
+                                             field)));
+        }
+        let value: Value = deserialize_single(Cursor::new([peek]).chain(&mut reader))?;
+        fields.insert(field, value);
+
+        match read_skipping_ws(&mut reader)? {
+            b',' => continue,
+            b'}' => return Err(invalid_data(&format!("`{}` not found", key))),
+            _ => return Err(invalid_data("`,` or `}` not found")),
+        }
+    }
+    serde_json::from_value(Value::Object(fields)).map_err(Into::into)
+}
+
 pub fn iter_json_array<T, R>(mut reader: R) -> impl Iterator<Item=Result<T, IOError>>
     where T: DeserializeOwned,
           R: Read,
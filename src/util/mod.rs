@@ -1,4 +1,5 @@
 pub mod json_de;
 pub mod numeric;
 pub mod path;
+pub mod sanitize;
 pub mod str;
\ No newline at end of file
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::fs;
 use std::path::{MAIN_SEPARATOR, Path, PathBuf};
 use std::ffi::{OsStr, OsString};
 
@@ -69,6 +70,19 @@ fn my_ext(path: &Path) -> Option<&OsStr> {
     }
 }
 
+/// `-` means stdin or stdout.
+pub fn is_std_stream(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Rename the file, or copy and remove it if rename failed, e.g. across file systems.
+pub fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::rename(from, to).or_else(|_| {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    })
+}
+
 /// path: the original path
 /// If path exists, then try file_stem(1).ext, file_stem(2).ext ... until it reached the u16::MAX
 /// return None if path no filename
@@ -142,7 +156,7 @@ pub fn get_dup_path(path: &Path) -> Option<Cow<Path>> {
 mod test {
     use std::fs;
     use std::path::{MAIN_SEPARATOR, Path, PathBuf};
-    use crate::util::path::{combine_path, get_dup_path};
+    use crate::util::path::{combine_path, get_dup_path, is_std_stream};
 
     #[test]
     fn test_combine_path() {
@@ -329,4 +343,11 @@ mod test {
         // Note here: the last point is SEPARATOR, the first two are stem, so ext is empty str.
         assert_eq!(Path::new("...").file_stem().unwrap(), "..");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_is_std_stream() {
+        assert!(is_std_stream(Path::new("-")));
+        assert!(!is_std_stream(Path::new("./-")));
+        assert!(!is_std_stream(Path::new("-.json")));
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::model::SanitizeProfile;
+
+pub const DEFAULT_MAX_BYTES: usize = 255;
+
+/// Characters which are NOT allowed in Windows filename, also by SMB shares.
+const WINDOWS_INVALID_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Transliteration of U+00C0 ~ U+017F (Latin-1 Supplement letters and Latin Extended-A).
+const LATIN_TABLE: [&str; 192] = [
+    "A", "A", "A", "A", "A", "A", "AE", "C", "E", "E", "E", "E", "I", "I", "I", "I", "D", "N", "O",
+    "O", "O", "O", "O", "x", "O", "U", "U", "U", "U", "Y", "TH", "ss", "a", "a", "a", "a", "a",
+    "a", "ae", "c", "e", "e", "e", "e", "i", "i", "i", "i", "d", "n", "o", "o", "o", "o", "o", "_",
+    "o", "u", "u", "u", "u", "y", "th", "y", "A", "a", "A", "a", "A", "a", "C", "c", "C", "c", "C",
+    "c", "C", "c", "D", "d", "D", "d", "E", "e", "E", "e", "E", "e", "E", "e", "E", "e", "G", "g",
+    "G", "g", "G", "g", "G", "g", "H", "h", "H", "h", "I", "i", "I", "i", "I", "i", "I", "i", "I",
+    "i", "IJ", "ij", "J", "j", "K", "k", "k", "L", "l", "L", "l", "L", "l", "L", "l", "L", "l",
+    "N", "n", "N", "n", "N", "n", "n", "N", "n", "O", "o", "O", "o", "O", "o", "OE", "oe", "R",
+    "r", "R", "r", "R", "r", "S", "s", "S", "s", "S", "s", "S", "s", "T", "t", "T", "t", "T", "t",
+    "U", "u", "U", "u", "U", "u", "U", "u", "U", "u", "U", "u", "W", "w", "Y", "y", "Y", "Z", "z",
+    "Z", "z", "Z", "z", "s",
+];
+
+/// Substitution of a filename component: what was found and what it was replaced with.
+pub type Substitution = (String, String);
+
+pub struct Sanitizer {
+    profile: SanitizeProfile,
+    max_bytes: usize,
+}
+
+impl Sanitizer {
+    pub fn new(profile: SanitizeProfile, max_bytes: usize) -> Self {
+        Self { profile, max_bytes }
+    }
+
+    /// Sanitize a path component, `reserved_bytes` is kept for the extension, e.g. `.flac`.
+    pub fn sanitize(&self, component: &str, reserved_bytes: usize) -> (String, Vec<Substitution>) {
+        let mut subs = Vec::new();
+        let mut result = String::with_capacity(component.len());
+        for c in component.chars() {
+            match self.substitute(c) {
+                Some(to) => {
+                    subs.push((c.to_string(), to.to_owned()));
+                    result.push_str(to);
+                }
+                None => result.push(c),
+            }
+        }
+
+        if self.profile != SanitizeProfile::Posix {
+            result = self.trim_end(result, &mut subs);
+            // `CON` and `CON.txt` are both reserved
+            let base = result.split('.').next().unwrap_or_default();
+            if WINDOWS_RESERVED_NAMES.contains(&base.to_uppercase().as_str()) {
+                let renamed = format!("{}_{}", base, &result[base.len()..]);
+                subs.push((result, renamed.clone()));
+                result = renamed;
+            }
+        }
+        // nothing may be left after trimming, e.g. "...", which would hide the file
+        if (result.is_empty() && !component.is_empty()) || result == "." || result == ".." {
+            subs.push((result.clone(), "_".to_owned()));
+            result = "_".to_owned();
+        }
+
+        let max_bytes = self.max_bytes.saturating_sub(reserved_bytes);
+        if result.len() > max_bytes {
+            let truncated = truncate_bytes(&result, max_bytes).to_owned();
+            subs.push((format!("{} bytes", result.len()), format!("{} bytes", truncated.len())));
+            result = truncated;
+            if self.profile != SanitizeProfile::Posix {
+                result = self.trim_end(result, &mut subs);
+            }
+        }
+        (result, subs)
+    }
+
+    /// `None` if the character is kept as is.
+    fn substitute(&self, c: char) -> Option<&'static str> {
+        match c {
+            '/' => Some("_"),
+            '\0' => Some(""),
+            _ if self.profile == SanitizeProfile::Posix => None,
+            '"' => Some("'"),
+            _ if WINDOWS_INVALID_CHARS.contains(&c) => Some("_"),
+            _ if c.is_control() => Some(""),
+            _ if self.profile == SanitizeProfile::Windows || c.is_ascii() => None,
+            _ => Some(transliterate(c)),
+        }
+    }
+
+    /// Windows does NOT allow trailing dots and spaces.
+    fn trim_end(&self, s: String, subs: &mut Vec<Substitution>) -> String {
+        let trimmed = s.trim_end_matches(['.', ' ']);
+        if trimmed.len() != s.len() {
+            subs.push((format!("trailing \"{}\"", &s[trimmed.len()..]), "".to_owned()));
+        }
+        trimmed.to_owned()
+    }
+}
+
+fn transliterate(c: char) -> &'static str {
+    match c as u32 {
+        0xC0..=0x17F => LATIN_TABLE[(c as u32 - 0xC0) as usize],
+        _ => match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => "'",
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => "'",
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => "-",
+            '\u{2026}' => "...",
+            '\u{00A0}' | '\u{3000}' => " ",
+            '\u{FF01}'..='\u{FF5E}' => FULL_WIDTH[(c as u32 - 0xFF01) as usize],
+            _ => "_",
+        }
+    }
+}
+
+/// ASCII of full width forms U+FF01 ~ U+FF5E, characters NOT allowed in Windows filename are
+/// replaced as other profiles.
+const FULL_WIDTH: [&str; 94] = [
+    "!", "'", "#", "$", "%", "&", "'", "(", ")", "_", "+", ",", "-", ".", "_", "0", "1", "2", "3",
+    "4", "5", "6", "7", "8", "9", "_", ";", "_", "=", "_", "_", "@", "A", "B", "C", "D", "E", "F",
+    "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y",
+    "Z", "[", "_", "]", "^", "_", "`", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l",
+    "m", "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "{", "_", "}", "~",
+];
+
+/// The longest prefix which is NOT longer than `max_bytes`, without breaking a character.
+pub fn truncate_bytes(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Count of each substitution in a run.
+#[derive(Default)]
+pub struct SanitizeReport {
+    counts: BTreeMap<Substitution, usize>,
+}
+
+impl SanitizeReport {
+    pub fn add(&mut self, subs: &[Substitution]) {
+        for sub in subs {
+            *self.counts.entry(sub.clone()).or_default() += 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+impl Display for SanitizeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for ((from, to), count) in &self.counts {
+            if !first {
+                write!(f, ", ")?;
+            }
+            first = false;
+            write!(f, "{:?} -> {:?} ({})", from, to, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::SanitizeProfile;
+
+    use super::{SanitizeReport, Sanitizer, truncate_bytes};
+
+    fn sanitize(profile: SanitizeProfile, s: &str) -> String {
+        Sanitizer::new(profile, 255).sanitize(s, 0).0
+    }
+
+    #[test]
+    fn test_sanitize_posix() {
+        assert_eq!(sanitize(SanitizeProfile::Posix, "AC/DC: Live?"), "AC_DC: Live?");
+        assert_eq!(sanitize(SanitizeProfile::Posix, ".."), "_");
+        assert_eq!(sanitize(SanitizeProfile::Posix, "Café."), "Café.");
+    }
+
+    #[test]
+    fn test_sanitize_windows() {
+        assert_eq!(sanitize(SanitizeProfile::Windows, "AC/DC: \"Live\"?*."), "AC_DC_ 'Live'__");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "Vol. 1 ..."), "Vol. 1");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "con"), "con_");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "Com1.live"), "Com1_.live");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "Console"), "Console");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "Beyoncé 周杰伦"), "Beyoncé 周杰伦");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "..."), "_");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "."), "_");
+        assert_eq!(sanitize(SanitizeProfile::Windows, "…"), "…");
+    }
+
+    #[test]
+    fn test_sanitize_ascii() {
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "Beyoncé – Déjà Vu… Live"),
+                   "Beyonce - Deja Vu... Live");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "Mötley Crüe ßŁø"), "Motley Crue ssLo");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "周杰伦 Ｊａｙ"), "___ Jay");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "AC／DC："), "AC_DC_");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "＼＊？＂＜＞｜"), "___'___");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "..."), "_");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "."), "_");
+        assert_eq!(sanitize(SanitizeProfile::Ascii, "…"), "_");
+    }
+
+    #[test]
+    fn test_sanitize_max_bytes() {
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 10);
+        let (result, subs) = sanitizer.sanitize("周杰伦 - 晴天", 5);
+        assert_eq!(result, "周");
+        assert_eq!(subs, vec![("18 bytes".to_owned(), "3 bytes".to_owned())]);
+
+        let (result, _) = sanitizer.sanitize("abc. defgh", 5);
+        assert_eq!(result, "abc");
+    }
+
+    #[test]
+    fn test_truncate_bytes() {
+        assert_eq!(truncate_bytes("abc", 5), "abc");
+        assert_eq!(truncate_bytes("晴天", 5), "晴");
+        assert_eq!(truncate_bytes("晴天", 2), "");
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = SanitizeReport::default();
+        assert!(report.is_empty());
+        let sanitizer = Sanitizer::new(SanitizeProfile::Windows, 255);
+        report.add(&sanitizer.sanitize("a?b?", 0).1);
+        report.add(&sanitizer.sanitize("a:b", 0).1);
+        assert_eq!(report.to_string(), "\":\" -> \"_\" (1), \"?\" -> \"_\" (2)");
+    }
+}
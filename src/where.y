@@ -25,21 +25,69 @@ TermInAnd -> Result<Expr, ()>:
 
 Comparator -> Result<Expr, ()>:
       '(' Expr ')' { $2 }
-    | TextKey ComparatorOp TextValue {
+    | Operand ComparatorOp TextValue {
         Ok (Expr::Comparator{
           span: $span, op: Box::new($2?), lhs: Box::new($1?), rhs: Box::new($3?)
         })
       }
-    | TextKey LikeOp TextValue {
+    | Operand LikeOp TextValue {
         Ok (Expr::Comparator{
           span: $span, op: Box::new($2?), lhs: Box::new($1?), rhs: Box::new($3?)
         })
       }
-    | NumKey ComparatorOp NumValue {
+    | Quantifier Operand ComparatorOp TextValue {
+        Ok (Expr::Quantified{
+          span: $span,
+          quantifier: Box::new($1?),
+          inner: Box::new(Expr::Comparator{
+            span: $span, op: Box::new($3?), lhs: Box::new($2?), rhs: Box::new($4?)
+          })
+        })
+      }
+    | Quantifier Operand LikeOp TextValue {
+        Ok (Expr::Quantified{
+          span: $span,
+          quantifier: Box::new($1?),
+          inner: Box::new(Expr::Comparator{
+            span: $span, op: Box::new($3?), lhs: Box::new($2?), rhs: Box::new($4?)
+          })
+        })
+      }
+    | Operand ComparatorOp NumValue {
+        Ok (Expr::Comparator{
+          span: $span, op: Box::new($2?), lhs: Box::new($1?), rhs: Box::new($3?)
+        })
+      }
+    | Operand ComparatorOp Operand {
         Ok (Expr::Comparator{
           span: $span, op: Box::new($2?), lhs: Box::new($1?), rhs: Box::new($3?)
         })
       }
+    | Operand 'OP_IN' '(' ValueList ')' {
+        Ok (Expr::In{ span: $span, lhs: Box::new($1?), not: false, values: $4? })
+      }
+    | Operand 'OP_NOT' 'OP_IN' '(' ValueList ')' {
+        Ok (Expr::In{ span: $span, lhs: Box::new($1?), not: true, values: $5? })
+      }
+    | Operand 'OP_BETWEEN' Value 'OP_AND' Value {
+        Ok (Expr::Between{
+          span: $span, lhs: Box::new($1?), not: false, low: Box::new($3?), high: Box::new($5?)
+        })
+      }
+    | Operand 'OP_NOT' 'OP_BETWEEN' Value 'OP_AND' Value {
+        Ok (Expr::Between{
+          span: $span, lhs: Box::new($1?), not: true, low: Box::new($4?), high: Box::new($6?)
+        })
+      }
+    | 'HAS_PROP' '(' TextValue ')' {
+        Ok (Expr::HasProp{ span: $span, key: Box::new($3?) })
+      }
+    | Operand 'OP_IS' NullCheck {
+        Ok (Expr::Is{ span: $span, lhs: Box::new($1?), not: false, check: Box::new($3?) })
+      }
+    | Operand 'OP_IS' 'OP_NOT' NullCheck {
+        Ok (Expr::Is{ span: $span, lhs: Box::new($1?), not: true, check: Box::new($4?) })
+      }
     ;
 ComparatorOp -> Result<Expr, ()>:
       'COMP_EQ' { Ok(Expr::ComparatorOp{ span: $span }) }
@@ -52,14 +100,65 @@ ComparatorOp -> Result<Expr, ()>:
 
 LikeOp -> Result<Expr, ()>:
       'COMP_LIKE' { Ok(Expr::ComparatorOp{ span: $span }) }
+    | 'COMP_REGEXP' { Ok(Expr::ComparatorOp{ span: $span }) }
+    ;
+
+Quantifier -> Result<Expr, ()>:
+      'QUANTIFIER' { Ok(Expr::Quantifier{ span: $span }) }
+    ;
+
+NullCheck -> Result<Expr, ()>:
+      'NULL_CHECK' { Ok(Expr::NullCheck{ span: $span }) }
+    ;
+
+ValueList -> Result<Vec<Expr>, ()>:
+      Value { Ok(vec![$1?]) }
+    | ValueList ',' Value {
+        let mut values = $1?;
+        values.push($3?);
+        Ok(values)
+      }
+    ;
+
+Value -> Result<Expr, ()>:
+      TextValue { $1 }
+    | NumValue { $1 }
+    ;
+
+Operand -> Result<Expr, ()>:
+      Key { $1 }
+    | FuncName '(' ArgList ')' {
+        Ok(Expr::Func{ span: $span, name: Box::new($1?), args: $3? })
+      }
+    | FuncName '(' ')' {
+        Ok(Expr::Func{ span: $span, name: Box::new($1?), args: vec![] })
+      }
     ;
 
-TextKey -> Result<Expr, ()>:
-      'TEXT_TAG' { Ok(Expr::TextTag{ span: $span }) };
+FuncName -> Result<Expr, ()>:
+      'TAG' { Ok(Expr::Tag{ span: $span }) }
+    ;
+
+ArgList -> Result<Vec<Expr>, ()>:
+      Arg { Ok(vec![$1?]) }
+    | ArgList ',' Arg {
+        let mut args = $1?;
+        args.push($3?);
+        Ok(args)
+      }
+    ;
+
+Arg -> Result<Expr, ()>:
+      Operand { $1 }
+    | Value { $1 }
+    ;
+
+Key -> Result<Expr, ()>:
+      'TAG' { Ok(Expr::Tag{ span: $span }) }
+    | 'PROP' '(' TextValue ')' { Ok(Expr::Prop{ span: $span, key: Box::new($3?) }) }
+    ;
 TextValue -> Result<Expr, ()>:
       'TEXT_VALUE' { Ok(Expr::TextValue{ span: $span }) };
-NumKey -> Result<Expr, ()>:
-      'NUM_TAG' { Ok(Expr::NumTag{ span: $span }) };
 NumValue -> Result<Expr, ()>:
       'NUM_VALUE' { Ok(Expr::NumValue{ span: $span }) };
 %%
@@ -88,16 +187,56 @@ pub enum Expr {
         lhs: Box<Expr>,
         rhs: Box<Expr>,
     },
+    Quantified {
+        span: Span,
+        quantifier: Box<Expr>,
+        inner: Box<Expr>,
+    },
+    In {
+        span: Span,
+        lhs: Box<Expr>,
+        not: bool,
+        values: Vec<Expr>,
+    },
+    Between {
+        span: Span,
+        lhs: Box<Expr>,
+        not: bool,
+        low: Box<Expr>,
+        high: Box<Expr>,
+    },
+    Is {
+        span: Span,
+        lhs: Box<Expr>,
+        not: bool,
+        check: Box<Expr>,
+    },
+    Quantifier {
+        span: Span,
+    },
+    NullCheck {
+        span: Span,
+    },
     ComparatorOp {
         span: Span,
     },
-    TextTag {
+    Tag {
         span: Span,
     },
-    TextValue {
+    Prop {
+        span: Span,
+        key: Box<Expr>,
+    },
+    HasProp {
         span: Span,
+        key: Box<Expr>,
     },
-    NumTag {
+    Func {
+        span: Span,
+        name: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    TextValue {
         span: Span,
     },
     NumValue {
@@ -1,4 +1,10 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
 use cfgrammar::Span;
+use chrono::{Datelike, DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use fancy_regex::{Regex, RegexBuilder};
 use log::{debug, error};
 use lrlex::{DefaultLexerTypes, lrlex_mod};
 use lrpar::{lrpar_mod, NonStreamingLexer};
@@ -15,7 +21,7 @@ lrpar_mod!("where.y");
 
 use where_y::Expr;
 
-use crate::model::MyTag;
+use crate::model::{AudioProperty, MyTag};
 use crate::op::ReadTag;
 
 fn eval(lexer: &dyn NonStreamingLexer<DefaultLexerTypes<u32>>,
@@ -45,40 +51,127 @@ fn eval(lexer: &dyn NonStreamingLexer<DefaultLexerTypes<u32>>,
                 WhereClause::CompOp(op) => op,
                 _ => return Err((span, "op is not a validate comparison operator")),
             };
-            let key = match eval(lexer, *lhs)? {
-                WhereClause::CompKey(key) => key,
-                _ => return Err((span, "left is not a key")),
+            let lhs = get_operand(lexer, *lhs, span)?;
+            let rhs_span = get_span(&rhs).unwrap_or(span);
+            let rhs = get_operand(lexer, *rhs, span)?;
+            get_comp(span, op, lhs, rhs, rhs_span)
+        }
+        Expr::In { span, lhs, not, values } => {
+            debug!("span: {:?}, lhs: {:?}, not: {}, values: {:?}", span, lhs, not, values);
+            let lhs = get_operand(lexer, *lhs, span)?;
+            // `tag IN (a, b)` is same as `tag = a OR tag = b`
+            let mut result: Option<WhereClause> = None;
+            for value in values {
+                let value_span = get_span(&value).unwrap_or(span);
+                let value = get_operand(lexer, value, span)?;
+                let comp = get_comp(span, "=".to_owned(), lhs.clone(), value, value_span)?;
+                result = Some(match result {
+                    Some(lhs) => WhereClause::LogicOp(LogicOp {
+                        op: "OR",
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(comp),
+                    }),
+                    None => comp,
+                });
+            }
+            let result = result.ok_or((span, "IN list must not be empty"))?;
+            if not {
+                Ok(WhereClause::NotOp(NotOp { inner: Box::new(result) }))
+            } else {
+                Ok(result)
+            }
+        }
+        Expr::Between { span, lhs, not, low, high } => {
+            debug!("span: {:?}, lhs: {:?}, not: {}, low: {:?}, high: {:?}",
+                span, lhs, not, low, high);
+            let lhs = get_operand(lexer, *lhs, span)?;
+            // `tag BETWEEN a AND b` is same as `tag >= a AND tag <= b`
+            let low_span = get_span(&low).unwrap_or(span);
+            let high_span = get_span(&high).unwrap_or(span);
+            let low = get_operand(lexer, *low, span)?;
+            let high = get_operand(lexer, *high, span)?;
+            let low = get_comp(span, ">=".to_owned(), lhs.clone(), low, low_span)?;
+            let high = get_comp(span, "<=".to_owned(), lhs, high, high_span)?;
+            let result = WhereClause::LogicOp(LogicOp {
+                op: "AND",
+                lhs: Box::new(low),
+                rhs: Box::new(high),
+            });
+            if not {
+                Ok(WhereClause::NotOp(NotOp { inner: Box::new(result) }))
+            } else {
+                Ok(result)
+            }
+        }
+        Expr::Quantified { span, quantifier, inner } => {
+            debug!("span: {:?}, quantifier: {:?}, inner: {:?}", span, quantifier, inner);
+            let all = match *quantifier {
+                Expr::Quantifier { span } => lexer.span_str(span).eq_ignore_ascii_case("ALL"),
+                _ => return Err((span, "quantifier is not ANY or ALL")),
             };
-            match MyTag::from_str(&key) {
-                Ok(ref tag) => {
-                    if tag.is_text() || tag.is_date() {
-                        let value = match eval(lexer, *rhs)? {
-                            WhereClause::TextValue(value) => value,
-                            _ => return Err((span, "right is not a value")),
-                        };
-                        Ok(WhereClause::TextComp(TextComp { op, tag, value }))
-                    } else if tag.is_numeric() {
-                        let value = match eval(lexer, *rhs)? {
-                            WhereClause::NumValue(value) => value,
-                            _ => return Err((span, "right is not a value")),
-                        };
-                        Ok(WhereClause::NumComp(NumComp { op, tag, value }))
+            match eval(lexer, *inner)? {
+                WhereClause::Comp(comp) if comp.lhs.get_type() == ValueType::Text =>
+                    Ok(WhereClause::Comp(Comp { all, ..comp })),
+                _ => Err((span, "ANY or ALL only work with text values")),
+            }
+        }
+        Expr::Is { span, lhs, not, check } => {
+            debug!("span: {:?}, lhs: {:?}, not: {}, check: {:?}", span, lhs, not, check);
+            let operand = get_operand(lexer, *lhs, span)?;
+            let check = match *check {
+                Expr::NullCheck { span } => {
+                    let span_str = lexer.span_str(span);
+                    if span_str.eq_ignore_ascii_case("NULL") {
+                        NullCheck::Null
+                    } else if span_str.eq_ignore_ascii_case("EMPTY") {
+                        NullCheck::Empty
+                    } else if span_str.eq_ignore_ascii_case("BLANK") {
+                        NullCheck::Blank
                     } else {
-                        Err((span, "left is an unsupported key"))
+                        return Err((span, "is not NULL, EMPTY or BLANK"));
                     }
                 }
-                _ => return Err((span, "left is not a key")),
-            }
+                _ => return Err((span, "right is not NULL, EMPTY or BLANK")),
+            };
+            Ok(WhereClause::IsComp(IsComp { operand, check, not }))
         }
+        Expr::Quantifier { span } => Err((span, "quantifier must be followed by a comparison")),
+        Expr::NullCheck { span } => Err((span, "must be preceded by IS or IS NOT")),
         Expr::ComparatorOp { span } => {
             let span_str = lexer.span_str(span);
             debug!("span_str: {}", span_str);
             Ok(WhereClause::CompOp(span_str.to_owned()))
         }
-        Expr::TextTag { span } => {
+        Expr::Tag { span } => {
             let span_str = lexer.span_str(span);
             debug!("span_str: {}", span_str);
-            Ok(WhereClause::CompKey(span_str.to_owned()))
+            match Key::from_str(span_str) {
+                Some(key) => Ok(WhereClause::Operand(Operand::Key(key))),
+                None => Err((span, "is not a known tag or field")),
+            }
+        }
+        Expr::Prop { span, key } => {
+            debug!("span: {:?}, key: {:?}", span, key);
+            let key = get_prop_key(lexer, *key, span)?;
+            Ok(WhereClause::Operand(Operand::Key(Key::Prop(key))))
+        }
+        Expr::HasProp { span, key } => {
+            debug!("span: {:?}, key: {:?}", span, key);
+            let key = get_prop_key(lexer, *key, span)?;
+            Ok(WhereClause::HasProp(key))
+        }
+        Expr::Func { span, name, args } => {
+            debug!("span: {:?}, name: {:?}, args: {:?}", span, name, args);
+            let func = match *name {
+                Expr::Tag { span } => Func::from_name(lexer.span_str(span))
+                    .ok_or((span, "is not a known function"))?,
+                _ => return Err((span, "is not a function call")),
+            };
+            let args = args.into_iter()
+                .map(|arg| get_operand(lexer, arg, span))
+                .collect::<Result<Vec<_>, _>>()?;
+            func.check_args(&args).map_err(|msg| (span, msg))?;
+            Ok(WhereClause::Operand(Operand::Func(func, args)))
         }
         Expr::TextValue { span } => {
             let span_str = lexer.span_str(span);
@@ -96,35 +189,141 @@ fn eval(lexer: &dyn NonStreamingLexer<DefaultLexerTypes<u32>>,
                 }
             }
         }
-        Expr::NumTag { span } => {
-            let span_str = lexer.span_str(span);
-            debug!("span_str: {}", span_str);
-            Ok(WhereClause::CompKey(span_str.to_owned()))
-        }
         Expr::NumValue { span } => {
             let span_str = lexer.span_str(span);
             debug!("span_str: {}", span_str);
-            let v = span_str.parse::<u32>()
-                .map_err(|_| (span, "cannot be represented as a u32"))?;
+            let v = span_str.parse::<u64>()
+                .map_err(|_| (span, "cannot be represented as a u64"))?;
             Ok(WhereClause::NumValue(v))
         }
     }
 }
 
+/// A key, a function call, or a literal value.
+fn get_operand(lexer: &dyn NonStreamingLexer<DefaultLexerTypes<u32>>,
+               e: Expr,
+               span: Span,
+) -> Result<Operand, (Span, &'static str)> {
+    let span = get_span(&e).unwrap_or(span);
+    match eval(lexer, e)? {
+        WhereClause::Operand(operand) => Ok(operand),
+        WhereClause::TextValue(value) => Ok(Operand::Value(Value::Text(value))),
+        WhereClause::NumValue(value) => Ok(Operand::Value(Value::Num(value))),
+        _ => Err((span, "is not a key or a value")),
+    }
+}
+
+/// Property keys of TagLib `PropertyMap` are always in upper case.
+fn get_prop_key(lexer: &dyn NonStreamingLexer<DefaultLexerTypes<u32>>,
+                e: Expr,
+                span: Span,
+) -> Result<String, (Span, &'static str)> {
+    match eval(lexer, e)? {
+        WhereClause::TextValue(key) if !key.trim().is_empty() => Ok(key.trim().to_uppercase()),
+        _ => Err((span, "property key must be a non-empty text value")),
+    }
+}
+
+fn get_span(e: &Expr) -> Option<Span> {
+    match e {
+        Expr::Tag { span } | Expr::Prop { span, .. } | Expr::Func { span, .. }
+        | Expr::TextValue { span } | Expr::NumValue { span } => Some(*span),
+        _ => None,
+    }
+}
+
+/// Comparison of `lhs` with `rhs`, both sides must be the same type.
+fn get_comp(span: Span,
+            op: String,
+            lhs: Operand,
+            rhs: Operand,
+            rhs_span: Span,
+) -> Result<WhereClause, (Span, &'static str)> {
+    let lhs_type = lhs.get_type();
+    let is_pattern_op = is_like_op(&op) || is_regexp_op(&op);
+    if is_pattern_op && lhs_type != ValueType::Text && lhs_type != ValueType::Date {
+        return Err((span, "LIKE or REGEXP only work with text values or dates"));
+    }
+
+    // date and date time are written as text, e.g. `date > '2001'`, `mtime > '2024-06-01'`
+    let rhs = match rhs {
+        Operand::Value(Value::Text(value)) if lhs_type == ValueType::Date && !is_pattern_op => {
+            let value = PartialDate::parse(&value).ok_or((rhs_span, "is not a valid date"))?;
+            Operand::Value(Value::Date(value))
+        }
+        Operand::Value(Value::Text(value)) if lhs_type == ValueType::Time => {
+            let value = parse_time(&value).ok_or((rhs_span, "is not a valid date time"))?;
+            Operand::Value(Value::Time(value))
+        }
+        rhs => rhs,
+    };
+    if rhs.get_type() != lhs_type && !is_pattern_op {
+        return Err((rhs_span, match (&rhs, lhs_type) {
+            (Operand::Value(_), ValueType::Text) => "right is not a text value",
+            (Operand::Value(_), ValueType::Num) => "right is not a numeric value",
+            (Operand::Value(_), ValueType::Date) => "right is not a date value",
+            (Operand::Value(_), ValueType::Time) => "right is not a date time value",
+            _ => "is not the same type as left",
+        }));
+    }
+
+    let regex = match &rhs {
+        Operand::Value(Value::Text(pattern)) if is_regexp_op(&op) =>
+            Some(get_regex(pattern, op.eq_ignore_ascii_case("IREGEXP"))
+                .ok_or((rhs_span, "is not a valid regular expression"))?),
+        _ => None,
+    };
+    Ok(WhereClause::Comp(Comp { op, lhs, rhs, all: false, regex }))
+}
+
+/// Parse absolute date time as local time, e.g. `2024-06-01`, `2024-06-01 12:30:00`,
+/// or relative to now, e.g. `-12h`, `-7d`, `-2w`.
+fn parse_time(s: &str) -> Option<DateTime<Local>> {
+    let s = s.trim();
+    if let Some(relative) = s.strip_prefix('-') {
+        let (i, _) = relative.char_indices().last()?;
+        let (n, unit) = relative.split_at(i);
+        let n = n.parse::<i64>().ok()?;
+        let duration = match unit {
+            "h" => Duration::try_hours(n)?,
+            "d" => Duration::try_days(n)?,
+            "w" => Duration::try_weeks(n)?,
+            _ => return None,
+        };
+        return Local::now().checked_sub_signed(duration);
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"].iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0)))?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
 #[derive(Debug)]
 pub enum WhereClause {
     LogicOp(LogicOp),
     NotOp(NotOp),
-    TextComp(TextComp),
-    NumComp(NumComp),
+    Comp(Comp),
+    IsComp(IsComp),
+    HasProp(String),
     CompOp(String),
-    CompKey(String),
+    Operand(Operand),
     TextValue(String),
-    NumValue(u32),
+    NumValue(u64),
 }
 
 impl WhereClause {
     pub fn new(input: &str) -> Result<WhereClause, String> {
+        Self::parse(input, false)
+    }
+
+    /// Clause of `--where-group`, tags and fields must be in aggregate functions.
+    pub fn new_group(input: &str) -> Result<WhereClause, String> {
+        Self::parse(input, true)
+    }
+
+    fn parse(input: &str, group: bool) -> Result<WhereClause, String> {
         // Get the `LexerDef` for the `calc` language.
         let lexerdef = where_l::lexerdef();
 
@@ -142,7 +341,7 @@ impl WhereClause {
         }
 
         if let Some(Ok(r)) = res {
-            eval(&lexer, r).map_err(|(span, msg)| {
+            let w = eval(&lexer, r).map_err(|(span, msg)| {
                 let mut res_e: Vec<String> = vec![];
                 let ((line, col), _) = lexer.line_col(span);
                 let err_info = format!(
@@ -153,17 +352,71 @@ impl WhereClause {
                     msg);
                 res_e.push(err_info);
                 format!("{:?}", res_e)
-            })
+            })?;
+            w.check_scope(group).map_err(|e| format!("{:?}", vec![e]))?;
+            Ok(w)
         } else {
             Err("Unknown error.".to_owned())
         }
     }
 
+    /// Aggregate functions only work for groups, and tags of groups must be aggregated.
+    fn check_scope(&self, group: bool) -> Result<(), String> {
+        let operands = match self {
+            WhereClause::LogicOp(op) => {
+                op.lhs.check_scope(group)?;
+                return op.rhs.check_scope(group);
+            }
+            WhereClause::NotOp(op) => return op.inner.check_scope(group),
+            WhereClause::HasProp(key) if group =>
+                return Err(format!("'HAS_PROP('{}')' does not work with groups.", key)),
+            WhereClause::Comp(comp) => vec![&comp.lhs, &comp.rhs],
+            WhereClause::IsComp(comp) => vec![&comp.operand],
+            _ => vec![],
+        };
+        operands.iter()
+            .try_for_each(|o| o.check_scope(group)
+                .map_err(|msg| format!("'{}' {}.", o, msg)))
+    }
+
     pub fn check(&self, t: &dyn ReadTag) -> Option<bool> {
+        self.check_in(&Scope::File(t))
+    }
+
+    /// Check files of a group, which was parsed by `new_group`.
+    pub fn check_group(&self, files: &[&dyn ReadTag]) -> Option<bool> {
+        self.check_in(&Scope::Group(files))
+    }
+
+    /// Keys in the clause, they are all the values it reads from a file.
+    pub fn get_keys(&self) -> Vec<&Key> {
+        let mut keys = Vec::new();
+        self.collect_keys(&mut keys);
+        keys
+    }
+
+    fn collect_keys<'a>(&'a self, keys: &mut Vec<&'a Key>) {
         match self {
             WhereClause::LogicOp(op) => {
-                let lhs = op.lhs.check(t)?;
-                let rhs = op.rhs.check(t)?;
+                op.lhs.collect_keys(keys);
+                op.rhs.collect_keys(keys);
+            }
+            WhereClause::NotOp(op) => op.inner.collect_keys(keys),
+            WhereClause::Comp(comp) => {
+                comp.lhs.collect_keys(keys);
+                comp.rhs.collect_keys(keys);
+            }
+            WhereClause::IsComp(comp) => comp.operand.collect_keys(keys),
+            WhereClause::Operand(operand) => operand.collect_keys(keys),
+            _ => (),
+        }
+    }
+
+    fn check_in(&self, scope: &Scope) -> Option<bool> {
+        match self {
+            WhereClause::LogicOp(op) => {
+                let lhs = op.lhs.check_in(scope)?;
+                let rhs = op.rhs.check_in(scope)?;
                 if op.op.eq("AND") {
                     Some(lhs && rhs)
                 } else if op.op.eq("OR") {
@@ -174,7 +427,7 @@ impl WhereClause {
                 }
             }
             WhereClause::NotOp(op) => {
-                match op.inner.check(t) {
+                match op.inner.check_in(scope) {
                     Some(v) => Some(!v),
                     None => {
                         error!("NotOp, inner is None");
@@ -182,89 +435,60 @@ impl WhereClause {
                     }
                 }
             }
-            WhereClause::TextComp(comp) => {
-                let tag = comp.tag;
-                debug!("tag: {}", &tag);
-
-                if tag.is_text() || tag.is_date() {
-                    debug!("text or date tag");
-                    let op = &comp.op;
-                    debug!("op: {}", op);
-
-                    if let Some(value) = t.get_text_tag(&tag) {
-                        debug!("value: {}", value);
-                        if op.eq("=") {
-                            Some(value.eq(&comp.value))
-                        } else if op.eq("!=") || op.eq("<>") {
-                            Some(value.ne(&comp.value))
-                        } else if op.eq("<") {
-                            Some(value.lt(&comp.value))
-                        } else if op.eq(">") {
-                            Some(value.gt(&comp.value))
-                        } else if op.eq("<=") {
-                            Some(value.le(&comp.value))
-                        } else if op.eq(">=") {
-                            Some(value.ge(&comp.value))
-                        } else if op.eq_ignore_ascii_case("LIKE") {
-                            Some(WildMatchPattern::<'%', '_'>::new(&comp.value).matches(&value))
-                        } else if op.eq_ignore_ascii_case("ILIKE") {
-                            Some(WildMatchPattern::<'%', '_'>::new(&comp.value.to_lowercase())
-                                .matches(&value.to_lowercase()))
-                        } else {
-                            error!("Comp, unsupported op: {}", &op);
-                            None
-                        }
-                    } else {
-                        if op.eq("!=") || op.eq("<>") {
-                            Some(true)
-                        } else {
-                            Some(false)
-                        }
-                    }
+            WhereClause::Comp(comp) => {
+                let op = &comp.op;
+                debug!("lhs: {}, op: {}, rhs: {}", comp.lhs, op, comp.rhs);
+
+                let lhs = comp.lhs.get_values(scope);
+                let rhs = comp.rhs.get_values(scope);
+                if lhs.is_empty() || rhs.is_empty() {
+                    return Some(is_ne_op(op));
+                }
+                debug!("lhs values: {:?}, rhs values: {:?}", lhs, rhs);
+                let mut results = lhs.iter()
+                    .map(|value| match &comp.regex {
+                        Some(re) => re.is_match(&value.to_text())
+                            .map_err(|e| error!("Comp, match {:?} failed: {}", value, e))
+                            .ok(),
+                        None => rhs.iter()
+                            .map(|expected| compare(op, value, expected))
+                            .try_fold(false, |acc, r| r.map(|r| acc || r)),
+                    });
+                if comp.all {
+                    results.try_fold(true, |acc, r| r.map(|r| acc && r))
                 } else {
-                    error!("Comp, tag {} is NOT text or date tag", tag);
-                    None
+                    results.try_fold(false, |acc, r| r.map(|r| acc || r))
                 }
             }
-            WhereClause::NumComp(comp) => {
-                let tag = comp.tag;
-                debug!("tag: {}", &tag);
-
-                if tag.is_numeric() {
-                    debug!("numeric tag");
-                    let op = &comp.op;
-                    debug!("op: {}", op);
-
-                    if let Some(value) = t.get_numeric_tag(&tag) {
-                        debug!("value: {}", value);
-                        if op.eq("=") {
-                            Some(value.eq(&comp.value))
-                        } else if op.eq("!=") || op.eq("<>") {
-                            Some(value.ne(&comp.value))
-                        } else if op.eq("<") {
-                            Some(value.lt(&comp.value))
-                        } else if op.eq(">") {
-                            Some(value.gt(&comp.value))
-                        } else if op.eq("<=") {
-                            Some(value.le(&comp.value))
-                        } else if op.eq(">=") {
-                            Some(value.ge(&comp.value))
-                        } else {
-                            error!("Comp, unsupported op: {}", &op);
-                            None
-                        }
-                    } else {
-                        if op.eq("!=") || op.eq("<>") {
-                            Some(true)
-                        } else {
-                            Some(false)
-                        }
+            WhereClause::HasProp(key) => {
+                debug!("property key: {}", key);
+                let t = match scope {
+                    Scope::File(t) => t,
+                    Scope::Group(_) => {
+                        error!("HasProp, does not work with groups");
+                        return None;
+                    }
+                };
+                match t.get_property_keys() {
+                    Ok(keys) => Some(keys.iter().any(|k| k.eq_ignore_ascii_case(key))),
+                    Err(e) => {
+                        error!("HasProp, get property keys failed: {}", e);
+                        None
                     }
-                } else {
-                    error!("NumComp, tag {} is NOT numeric tag", tag);
-                    None
                 }
             }
+            WhereClause::IsComp(comp) => {
+                debug!("operand: {}", comp.operand);
+
+                let values = comp.operand.get_strings(scope);
+                debug!("values: {:?}", values);
+                let result = match comp.check {
+                    NullCheck::Null => values.is_empty(),
+                    NullCheck::Empty => !values.is_empty() && values.iter().all(|v| v.is_empty()),
+                    NullCheck::Blank => values.iter().all(|v| v.trim().is_empty()),
+                };
+                Some(result != comp.not)
+            }
             _ => {
                 error!("Unknown where clause {:?}", self);
                 None
@@ -273,6 +497,608 @@ impl WhereClause {
     }
 }
 
+fn is_ne_op(op: &str) -> bool {
+    op.eq("!=") || op.eq("<>")
+}
+
+fn is_like_op(op: &str) -> bool {
+    op.eq_ignore_ascii_case("LIKE") || op.eq_ignore_ascii_case("ILIKE")
+}
+
+fn is_regexp_op(op: &str) -> bool {
+    op.eq_ignore_ascii_case("REGEXP") || op.eq_ignore_ascii_case("IREGEXP")
+}
+
+fn get_regex(pattern: &str, ignore_case: bool) -> Option<Regex> {
+    if ignore_case {
+        RegexBuilder::new(&format!("(?i){}", pattern)).build().ok()
+    } else {
+        RegexBuilder::new(pattern).build().ok()
+    }
+}
+
+fn compare(op: &str, value: &Value, expected: &Value) -> Option<bool> {
+    match (value, expected) {
+        (Value::Text(value), Value::Text(expected)) => compare_text(op, value, expected),
+        (Value::Num(value), Value::Num(expected)) => compare_num(op, value, expected),
+        (Value::Date(value), Value::Date(expected)) => compare_num(op, value, expected),
+        // `LIKE` matches the date as it is written
+        (Value::Date(value), Value::Text(expected)) => compare_text(op, &value.text, expected),
+        (Value::Time(value), Value::Time(expected)) => compare_num(op, value, expected),
+        _ => {
+            error!("Comp, cannot compare {:?} with {:?}", value, expected);
+            None
+        }
+    }
+}
+
+fn compare_num<T: PartialOrd>(op: &str, value: T, expected: T) -> Option<bool> {
+    if op.eq("=") {
+        Some(value.eq(&expected))
+    } else if op.eq("!=") || op.eq("<>") {
+        Some(value.ne(&expected))
+    } else if op.eq("<") {
+        Some(value.lt(&expected))
+    } else if op.eq(">") {
+        Some(value.gt(&expected))
+    } else if op.eq("<=") {
+        Some(value.le(&expected))
+    } else if op.eq(">=") {
+        Some(value.ge(&expected))
+    } else {
+        error!("Comp, unsupported op: {}", op);
+        None
+    }
+}
+
+fn compare_text(op: &str, value: &str, expected: &str) -> Option<bool> {
+    if op.eq("=") {
+        Some(value.eq(expected))
+    } else if op.eq("!=") || op.eq("<>") {
+        Some(value.ne(expected))
+    } else if op.eq("<") {
+        Some(value.lt(expected))
+    } else if op.eq(">") {
+        Some(value.gt(expected))
+    } else if op.eq("<=") {
+        Some(value.le(expected))
+    } else if op.eq(">=") {
+        Some(value.ge(expected))
+    } else if op.eq_ignore_ascii_case("LIKE") {
+        Some(WildMatchPattern::<'%', '_'>::new(expected).matches(value))
+    } else if op.eq_ignore_ascii_case("ILIKE") {
+        Some(WildMatchPattern::<'%', '_'>::new(&expected.to_lowercase())
+            .matches(&value.to_lowercase()))
+    } else {
+        error!("Comp, unsupported op: {}", op);
+        None
+    }
+}
+
+/// Values are read from a file, or aggregated from files of a group.
+#[derive(Clone, Copy)]
+enum Scope<'a> {
+    File(&'a dyn ReadTag),
+    Group(&'a [&'a dyn ReadTag]),
+}
+
+/// Either side of comparisons, a key, a function call or a literal value.
+#[derive(Debug, Clone)]
+pub enum Operand {
+    Key(Key),
+    Func(Func, Vec<Operand>),
+    Value(Value),
+}
+
+impl Operand {
+    fn get_type(&self) -> ValueType {
+        match self {
+            Operand::Key(key) => key.get_type(),
+            Operand::Func(func, args) => func.get_type(args),
+            Operand::Value(value) => value.get_type(),
+        }
+    }
+
+    fn get_values(&self, scope: &Scope) -> Vec<Value> {
+        match (self, scope) {
+            (Operand::Key(key), Scope::File(t)) => key.get_values(*t),
+            (Operand::Key(key), Scope::Group(_)) => {
+                error!("Key {} is not aggregated in a group", key);
+                vec![]
+            }
+            (Operand::Func(func, args), _) => func.call(args, scope),
+            (Operand::Value(value), _) => vec![value.clone()],
+        }
+    }
+
+    /// Values as string for `IS NULL` / `IS EMPTY` / `IS BLANK`.
+    fn get_strings(&self, scope: &Scope) -> Vec<String> {
+        match (self, scope) {
+            (Operand::Key(key), Scope::File(t)) => key.get_strings(*t),
+            _ => self.get_values(scope).iter().map(Value::to_text).collect(),
+        }
+    }
+
+    fn collect_keys<'a>(&'a self, keys: &mut Vec<&'a Key>) {
+        match self {
+            Operand::Key(key) => keys.push(key),
+            Operand::Func(_, args) => args.iter().for_each(|a| a.collect_keys(keys)),
+            Operand::Value(_) => (),
+        }
+    }
+
+    fn check_scope(&self, group: bool) -> Result<(), &'static str> {
+        match self {
+            Operand::Key(_) if group => Err("must be in an aggregate function for groups"),
+            Operand::Func(func, _) if func.is_aggregate() && !group =>
+                Err("aggregate function only works with groups"),
+            // arguments of aggregate functions are values of each file
+            Operand::Func(func, args) => args.iter()
+                .try_for_each(|a| a.check_scope(group && !func.is_aggregate())),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Key(key) => write!(f, "{}", key),
+            Operand::Func(func, args) => {
+                let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                write!(f, "{}({})", func.get_name(), args.join(", "))
+            }
+            Operand::Value(Value::Text(value)) => write!(f, "'{}'", value.replace('\'', "''")),
+            Operand::Value(value) => write!(f, "{}", value.to_text()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Value {
+    Text(String),
+    Num(u64),
+    Date(PartialDate),
+    Time(DateTime<Local>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ValueType {
+    Text,
+    Num,
+    Date,
+    Time,
+}
+
+impl Value {
+    fn get_type(&self) -> ValueType {
+        match self {
+            Value::Text(_) => ValueType::Text,
+            Value::Num(_) => ValueType::Num,
+            Value::Date(_) => ValueType::Date,
+            Value::Time(_) => ValueType::Time,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Value::Text(v) => v.clone(),
+            Value::Num(v) => v.to_string(),
+            Value::Date(v) => v.text.clone(),
+            Value::Time(v) => v.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+/// Functions could be used on either side of comparisons, e.g. `length(title) > 80`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Func {
+    Lower,
+    Upper,
+    Trim,
+    /// Count of characters.
+    Length,
+    /// `substr(text, start[, length])`, `start` is 1-based.
+    Substr,
+    /// First argument which is not `NULL` or `BLANK`.
+    Coalesce,
+    /// Text to number, values which are not a number are `NULL`.
+    Num,
+    /// Number or date time to text.
+    Text,
+    /// Year of a date or date time.
+    Year,
+    /// Month of a date or date time, it is `NULL` if the date is only a year.
+    Month,
+    /// Aggregate function, count of files in the group.
+    Count,
+    /// Aggregate function, count of distinct values in the group.
+    CountDistinct,
+    /// Aggregate function, minimum value in the group.
+    Min,
+    /// Aggregate function, maximum value in the group.
+    Max,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lower" => Some(Func::Lower),
+            "upper" => Some(Func::Upper),
+            "trim" => Some(Func::Trim),
+            "length" => Some(Func::Length),
+            "substr" => Some(Func::Substr),
+            "coalesce" => Some(Func::Coalesce),
+            "num" => Some(Func::Num),
+            "text" => Some(Func::Text),
+            "year" => Some(Func::Year),
+            "month" => Some(Func::Month),
+            "count" => Some(Func::Count),
+            "count_distinct" => Some(Func::CountDistinct),
+            "min" => Some(Func::Min),
+            "max" => Some(Func::Max),
+            _ => None,
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            Func::Lower => "lower",
+            Func::Upper => "upper",
+            Func::Trim => "trim",
+            Func::Length => "length",
+            Func::Substr => "substr",
+            Func::Coalesce => "coalesce",
+            Func::Num => "num",
+            Func::Text => "text",
+            Func::Year => "year",
+            Func::Month => "month",
+            Func::Count => "count",
+            Func::CountDistinct => "count_distinct",
+            Func::Min => "min",
+            Func::Max => "max",
+        }
+    }
+
+    fn is_aggregate(&self) -> bool {
+        matches!(self, Func::Count | Func::CountDistinct | Func::Min | Func::Max)
+    }
+
+    fn check_args(&self, args: &[Operand]) -> Result<(), &'static str> {
+        let (min, max) = match self {
+            Func::Substr => (2, 3),
+            Func::Coalesce => (1, usize::MAX),
+            Func::Count => (0, 0),
+            _ => (1, 1),
+        };
+        if args.len() < min || args.len() > max {
+            return Err("has wrong number of arguments");
+        }
+
+        let types = args.iter().map(|a| a.get_type()).collect::<Vec<_>>();
+        match self {
+            Func::Lower | Func::Upper | Func::Trim | Func::Length | Func::Num
+            if types[0] != ValueType::Text => Err("argument must be text"),
+            Func::Substr if types[0] != ValueType::Text
+                || types[1..].iter().any(|t| *t != ValueType::Num) =>
+                Err("arguments must be text, numeric start and numeric length"),
+            Func::Coalesce if types.iter().any(|t| *t != types[0]) =>
+                Err("arguments must be the same type"),
+            Func::Year | Func::Month
+            if types[0] != ValueType::Date && types[0] != ValueType::Time =>
+                Err("argument must be a date or date time"),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_type(&self, args: &[Operand]) -> ValueType {
+        match self {
+            Func::Length | Func::Num | Func::Year | Func::Month | Func::Count
+            | Func::CountDistinct => ValueType::Num,
+            Func::Coalesce | Func::Min | Func::Max =>
+                args.first().map_or(ValueType::Text, |a| a.get_type()),
+            _ => ValueType::Text,
+        }
+    }
+
+    /// Functions apply to each value, except `coalesce()` and aggregate functions.
+    fn call(&self, args: &[Operand], scope: &Scope) -> Vec<Value> {
+        if self.is_aggregate() {
+            return match scope {
+                Scope::Group(files) => self.aggregate(args, files),
+                Scope::File(_) => {
+                    error!("Aggregate function {} only works with groups", self.get_name());
+                    vec![]
+                }
+            };
+        }
+
+        let values = || args[0].get_values(scope).into_iter().map(|v| v.to_text());
+        match self {
+            Func::Lower => values().map(|v| Value::Text(v.to_lowercase())).collect(),
+            Func::Upper => values().map(|v| Value::Text(v.to_uppercase())).collect(),
+            Func::Trim => values().map(|v| Value::Text(v.trim().to_owned())).collect(),
+            Func::Length => values().map(|v| Value::Num(v.chars().count() as u64)).collect(),
+            Func::Substr => {
+                let get_num = |i: usize| args.get(i)
+                    .and_then(|a| a.get_values(scope).into_iter().next())
+                    .and_then(|v| match v {
+                        Value::Num(n) => usize::try_from(n).ok(),
+                        _ => None,
+                    });
+                let start = match get_num(1) {
+                    Some(start) => start.saturating_sub(1),
+                    None => return vec![],
+                };
+                let length = if args.len() > 2 {
+                    match get_num(2) {
+                        Some(length) => length,
+                        None => return vec![],
+                    }
+                } else {
+                    usize::MAX
+                };
+                values().map(|v| Value::Text(v.chars().skip(start).take(length).collect()))
+                    .collect()
+            }
+            Func::Num => values().filter_map(|v| v.trim().parse::<u64>().ok())
+                .map(Value::Num)
+                .collect(),
+            Func::Text => values().map(Value::Text).collect(),
+            Func::Year | Func::Month => args[0].get_values(scope).into_iter()
+                .filter_map(|v| match (self, v) {
+                    (Func::Year, Value::Date(d)) => u64::try_from(d.value.year()).ok(),
+                    (Func::Year, Value::Time(d)) => u64::try_from(d.year()).ok(),
+                    (_, Value::Date(d)) if d.precision >= DatePrecision::Month =>
+                        Some(u64::from(d.value.month())),
+                    (_, Value::Time(d)) => Some(u64::from(d.month())),
+                    _ => None,
+                })
+                .map(Value::Num)
+                .collect(),
+            Func::Coalesce => args.iter()
+                .map(|a| a.get_values(scope))
+                .find(|values| values.iter().any(|v| !v.to_text().trim().is_empty()))
+                .unwrap_or_default(),
+            Func::Count | Func::CountDistinct | Func::Min | Func::Max => vec![],
+        }
+    }
+
+    /// Values of all files in the group, `min()` and `max()` are `NULL` if there is no value.
+    fn aggregate(&self, args: &[Operand], files: &[&dyn ReadTag]) -> Vec<Value> {
+        if let Func::Count = self {
+            return vec![Value::Num(files.len() as u64)];
+        }
+
+        let values = files.iter()
+            .flat_map(|t| args[0].get_values(&Scope::File(*t)));
+        match self {
+            Func::CountDistinct => {
+                let mut distinct: Vec<Value> = vec![];
+                values.for_each(|v| if !distinct.contains(&v) {
+                    distinct.push(v);
+                });
+                vec![Value::Num(distinct.len() as u64)]
+            }
+            Func::Min => values.reduce(|a, b| if b < a { b } else { a }).into_iter().collect(),
+            Func::Max => values.reduce(|a, b| if b > a { b } else { a }).into_iter().collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Date which could be only a year or a year and month, e.g. `2001`, `2001-05`, `2001-5-1`,
+/// `2001-05-01T12:30:00`. Two dates are compared by the less precise one, so `'2001-05-01'`
+/// is equal to `'2001'`, and greater than `'2000'`.
+#[derive(Debug, Clone)]
+pub struct PartialDate {
+    value: NaiveDateTime,
+    precision: DatePrecision,
+    // as it is written
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DatePrecision {
+    Year,
+    Month,
+    Day,
+    Time,
+}
+
+impl PartialDate {
+    /// Date, or date time, a year-month or a year is also accepted.
+    pub fn parse(text: &str) -> Option<Self> {
+        let s = text.trim();
+        let (value, precision) = if let Ok(v) = DateTime::parse_from_rfc3339(s) {
+            (v.naive_local(), DatePrecision::Time)
+        } else if let Some(v) = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M",
+            "%Y-%m-%d %H:%M"].iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok()) {
+            (v, DatePrecision::Time)
+        } else if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            (d.and_time(NaiveTime::default()), DatePrecision::Day)
+        } else if let Ok(d) = NaiveDate::parse_from_str(&format!("{}-1", s), "%Y-%m-%d") {
+            (d.and_time(NaiveTime::default()), DatePrecision::Month)
+        } else if s.len() == 4 && s.chars().all(|c| c.is_ascii_digit()) {
+            let d = NaiveDate::from_ymd_opt(s.parse().ok()?, 1, 1)?;
+            (d.and_time(NaiveTime::default()), DatePrecision::Year)
+        } else {
+            return None;
+        };
+        Some(PartialDate { value, precision, text: text.to_owned() })
+    }
+
+    fn truncate(&self, precision: DatePrecision) -> (i32, u32, u32, NaiveTime) {
+        let (d, t) = (self.value.date(), self.value.time());
+        match precision {
+            DatePrecision::Year => (d.year(), 0, 0, NaiveTime::default()),
+            DatePrecision::Month => (d.year(), d.month(), 0, NaiveTime::default()),
+            DatePrecision::Day => (d.year(), d.month(), d.day(), NaiveTime::default()),
+            DatePrecision::Time => (d.year(), d.month(), d.day(), t),
+        }
+    }
+}
+
+impl PartialEq for PartialDate {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for PartialDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let precision = self.precision.min(other.precision);
+        self.truncate(precision).partial_cmp(&other.truncate(precision))
+    }
+}
+
+/// Key of comparisons, a tag, a field, an audio property or a property of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key {
+    Tag(&'static MyTag),
+    File(FileField),
+    Audio(AudioProperty),
+    /// `prop('KEY')`, key is in upper case.
+    Prop(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileField {
+    /// Full path of the file.
+    Path,
+    /// File name with extension.
+    Filename,
+    /// Extension without `.`.
+    Ext,
+    /// Full path of the directory which contains the file.
+    Dir,
+    /// File size in bytes.
+    Size,
+    /// Last modification time.
+    Mtime,
+}
+
+impl FileField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(FileField::Path),
+            "filename" => Some(FileField::Filename),
+            "ext" => Some(FileField::Ext),
+            "dir" => Some(FileField::Dir),
+            "size" => Some(FileField::Size),
+            "mtime" => Some(FileField::Mtime),
+            _ => None,
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        match self {
+            FileField::Path => "path",
+            FileField::Filename => "filename",
+            FileField::Ext => "ext",
+            FileField::Dir => "dir",
+            FileField::Size => "size",
+            FileField::Mtime => "mtime",
+        }
+    }
+}
+
+impl Key {
+    fn from_str(s: &str) -> Option<Self> {
+        FileField::from_name(s).map(Key::File)
+            .or_else(|| AudioProperty::from_str(s).map(Key::Audio))
+            .or_else(|| MyTag::from_str(s).ok().map(Key::Tag))
+    }
+
+    fn get_type(&self) -> ValueType {
+        match self {
+            Key::Tag(tag) if tag.is_numeric() => ValueType::Num,
+            Key::Tag(tag) if tag.is_date() => ValueType::Date,
+            Key::Tag(_) => ValueType::Text,
+            Key::File(FileField::Size) => ValueType::Num,
+            Key::File(FileField::Mtime) => ValueType::Time,
+            Key::File(_) => ValueType::Text,
+            Key::Audio(_) => ValueType::Num,
+            Key::Prop(_) => ValueType::Text,
+        }
+    }
+
+    /// Each value of the key, multi-values only for text tags and properties.
+    fn get_values(&self, t: &dyn ReadTag) -> Vec<Value> {
+        let path = t.get_path();
+        match self {
+            Key::Tag(tag) if tag.is_numeric() => t.get_numeric_tag(tag)
+                .map(|v| Value::Num(u64::from(v)))
+                .into_iter()
+                .collect(),
+            Key::Tag(tag) if tag.is_text() => t.get_text_tag_values(tag).into_iter()
+                .map(Value::Text)
+                .collect(),
+            // value which is not a valid date is same as `NULL`
+            Key::Tag(tag) if tag.is_date() => t.get_text_tag(tag)
+                .and_then(|v| PartialDate::parse(&v))
+                .map(Value::Date)
+                .into_iter()
+                .collect(),
+            Key::Tag(tag) => t.get_text_tag(tag).map(Value::Text).into_iter().collect(),
+            Key::Prop(key) => t.get_property(key)
+                .unwrap_or_else(|e| {
+                    error!("Get property {} failed: {}", key, e);
+                    vec![]
+                })
+                .into_iter()
+                .map(Value::Text)
+                .collect(),
+            Key::File(FileField::Size) => fs::metadata(path).ok()
+                .map(|m| Value::Num(m.len()))
+                .into_iter()
+                .collect(),
+            Key::File(FileField::Mtime) => fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .map(|v| Value::Time(DateTime::from(v)))
+                .into_iter()
+                .collect(),
+            Key::File(field) => {
+                let value = match field {
+                    FileField::Path => Some(path.as_os_str()),
+                    FileField::Filename => path.file_name(),
+                    FileField::Ext => path.extension(),
+                    FileField::Dir => path.parent().map(|p| p.as_os_str()),
+                    _ => None,
+                };
+                value.map(|s| Value::Text(s.to_string_lossy().to_string())).into_iter().collect()
+            }
+            Key::Audio(p) => t.get_audio_property(p)
+                .map(|v| Value::Num(u64::from(v)))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Values as string for `IS NULL` / `IS EMPTY` / `IS BLANK`.
+    fn get_strings(&self, t: &dyn ReadTag) -> Vec<String> {
+        match self {
+            Key::Tag(tag) if tag.is_numeric() =>
+                t.get_numeric_tag_string(tag).into_iter().collect(),
+            Key::Tag(tag) if tag.is_date() => t.get_text_tag(tag).into_iter().collect(),
+            _ => self.get_values(t).iter().map(Value::to_text).collect(),
+        }
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Tag(tag) => write!(f, "{}", tag),
+            Key::File(field) => write!(f, "{}", field.get_name()),
+            Key::Audio(p) => write!(f, "{}", p),
+            Key::Prop(key) => write!(f, "prop('{}')", key),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct LogicOp {
     op: &'static str,
@@ -285,28 +1111,43 @@ pub struct NotOp {
     inner: Box<WhereClause>,
 }
 
+/// Comparison matches if any value of left side matches any value of right side.
 #[derive(Debug)]
-pub struct TextComp {
+pub struct Comp {
     op: String,
-    tag: &'static MyTag,
-    value: String,
+    lhs: Operand,
+    rhs: Operand,
+    // `ALL` values of left side must match, otherwise `ANY` value matches
+    all: bool,
+    // compiled pattern of `REGEXP` / `IREGEXP`
+    regex: Option<Regex>,
 }
 
+/// `NULL` means no value, `EMPTY` means all values are empty string,
+/// `BLANK` means `NULL` or all values contain whitespaces only.
 #[derive(Debug)]
-pub struct NumComp {
-    op: String,
-    tag: &'static MyTag,
-    value: u32,
+pub enum NullCheck {
+    Null,
+    Empty,
+    Blank,
+}
+
+#[derive(Debug)]
+pub struct IsComp {
+    operand: Operand,
+    check: NullCheck,
+    not: bool,
 }
 
 #[cfg(test)]
 mod test {
+    use std::{env, fs};
     use std::path::{Path, PathBuf};
     use anyhow::Error;
 
-    use crate::model::MyTag;
+    use crate::model::{AudioProperty, MyPicture, MyTag};
     use crate::op::ReadTag;
-    use crate::where_clause::WhereClause;
+    use crate::where_clause::{Key, WhereClause};
 
     #[derive(Debug)]
     pub struct MockTagImpl<'a> {
@@ -324,25 +1165,62 @@ mod test {
                 MyTag::Composer => Some("Lee's".to_owned()),
                 MyTag::Copyright => Some("Disney".to_owned()),
                 MyTag::Lyrics => Some("".to_owned()),
+                MyTag::Genre => Some("Pop; Rock".to_owned()),
+                MyTag::Conductor => None,
+                MyTag::Comment => Some("  ".to_owned()),
+                MyTag::Date => Some("2001-5-1".to_owned()),
+                MyTag::OriginalDate => Some("1975".to_owned()),
                 _ => Some(format!("{}{}", key, self.count)),
             }
         }
 
-        fn get_numeric_tag(&self, _key: &MyTag) -> Option<u32> {
-            Some(self.count)
+        fn get_text_tag_values(&self, key: &MyTag) -> Vec<String> {
+            match key {
+                MyTag::Genre => vec!["Pop".to_owned(), "Rock".to_owned()],
+                _ => self.get_text_tag(key).into_iter().collect(),
+            }
+        }
+
+        fn get_numeric_tag(&self, key: &MyTag) -> Option<u32> {
+            match key {
+                MyTag::TrackTotal | MyTag::DiscNumber => None,
+                _ => Some(self.count),
+            }
         }
 
-        fn get_numeric_tag_string(&self, _key: &MyTag) -> Option<String> {
-            Some(format!("0{}", self.count))
+        fn get_numeric_tag_string(&self, key: &MyTag) -> Option<String> {
+            match key {
+                MyTag::TrackTotal => None,
+                MyTag::DiscNumber => Some("".to_owned()),
+                _ => Some(format!("0{}", self.count)),
+            }
         }
 
         fn get_property_keys(&self) -> Result<Vec<String>, Error> {
-            todo!()
+            Ok(vec!["ARTISTS".to_owned(), "REPLAYGAIN_TRACK_GAIN".to_owned()])
         }
 
-        fn get_property(&self, _key: &str) -> Result<Vec<String>, Error> {
+        fn get_property(&self, key: &str) -> Result<Vec<String>, Error> {
+            match key {
+                "ARTISTS" => Ok(vec!["John Denver".to_owned(), "Emmylou Harris".to_owned()]),
+                "REPLAYGAIN_TRACK_GAIN" => Ok(vec!["-6.52 dB".to_owned()]),
+                _ => Ok(vec![]),
+            }
+        }
+
+        fn get_pictures(&self) -> Result<Vec<MyPicture>, Error> {
             todo!()
         }
+
+        fn get_audio_property(&self, key: &AudioProperty) -> Option<u32> {
+            match key {
+                AudioProperty::Bitrate => Some(128),
+                AudioProperty::Length => Some(245),
+                AudioProperty::SampleRate => Some(44100),
+                AudioProperty::Channels => Some(2),
+                AudioProperty::BitsPerSample => None,
+            }
+        }
     }
 
     #[test]
@@ -363,6 +1241,431 @@ mod test {
         assert!(!w.check(&mock_empty_lyrics).unwrap());
     }
 
+    #[test]
+    fn test_where_multi_values() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        // ANY is default
+        let w = WhereClause::new("genre='Rock'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("ANY genre='Pop'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("any genre='Jazz'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+
+        // ALL
+        let w = WhereClause::new("ALL genre='Rock'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("all genre like '%o%'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("ALL genre<>'Jazz' and title='title5'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // only for text tags
+        assert!(WhereClause::new("ALL track-number=5").is_err());
+    }
+
+    #[test]
+    fn test_where_is_null() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        // text tags
+        let w = WhereClause::new("conductor IS NULL").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("conductor is not null").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("lyrics IS NULL").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("lyrics IS EMPTY").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("conductor IS EMPTY").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("comment IS EMPTY").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("comment IS BLANK and lyrics IS BLANK and conductor IS BLANK")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("genre IS NOT BLANK and title IS NOT EMPTY").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // numeric tags
+        let w = WhereClause::new("track-total IS NULL and track-number IS NOT NULL")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("disc-number IS EMPTY and disc-number IS NOT NULL")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("not track-number IS BLANK").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // tag named like a keyword prefix still works
+        let w = WhereClause::new("isrc IS NOT NULL").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        assert!(WhereClause::new("title IS 'title5'").is_err());
+        assert!(WhereClause::new("title IS NOT").is_err());
+        assert!(WhereClause::new("album IS NULL").is_err());
+    }
+
+    #[test]
+    fn test_where_regexp() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        let w = WhereClause::new(r"title REGEXP '^title\d$'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("title regexp '^TITLE'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("title IREGEXP '^TITLE5$'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("not composer REGEXP 'e''s$'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+
+        // with look-around
+        let w = WhereClause::new("copyright REGEXP 'Dis(?=ney)'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // ANY / ALL
+        let w = WhereClause::new("genre REGEXP '^R'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("ALL genre REGEXP '^R'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("ALL genre REGEXP '^(Pop|Rock)$'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // invalid pattern, or numeric tag
+        let e = WhereClause::new("title REGEXP '(abc'").unwrap_err();
+        assert!(e.contains("is not a valid regular expression"));
+        assert!(WhereClause::new("track-number REGEXP '5'").is_err());
+    }
+
+    #[test]
+    fn test_where_in_between() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        // IN
+        let w = WhereClause::new("genre IN ('Jazz', 'Rock')").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("genre in ('Jazz','Blues')").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("genre NOT IN ('Jazz', 'Blues')").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("track-number IN (1, 3, 5) and title in ('title5')")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("track-number NOT IN (1, 3, 5)").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        // no value
+        let w = WhereClause::new("track-total IN (5)").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("track-total NOT IN (5)").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // BETWEEN
+        let w = WhereClause::new("track-number BETWEEN 1 AND 5").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("track-number between 6 and 10 or title='title5'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("track-number NOT BETWEEN 1 AND 5").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new(
+            "copyright BETWEEN 'A' AND 'E' and composer not between 'A' and 'E'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // value type must match the tag
+        assert!(WhereClause::new("track-number IN (1, '3')").is_err());
+        assert!(WhereClause::new("title BETWEEN 1 AND 'z'").is_err());
+        assert!(WhereClause::new("title IN ()").is_err());
+    }
+
+    #[test]
+    fn test_where_tag_to_tag() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        // text
+        let w = WhereClause::new("title = title").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("title = artist").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("composer > copyright").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // numeric
+        let w = WhereClause::new("track-number = disc-total").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("track-number > track-total").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("track-number <> track-total").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // type must match, and unknown tag
+        assert!(WhereClause::new("track-number = title").is_err());
+        assert!(WhereClause::new("title like artist").is_err());
+        assert!(WhereClause::new("title = album").is_err());
+    }
+
+    #[test]
+    fn test_where_file_fields() {
+        let dir = env::temp_dir().join("music-tag-cli-where-test").join("Live");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("01 - Intro.mp3");
+        fs::write(&path, [0u8; 1024]).unwrap();
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        // text fields
+        let w = WhereClause::new("ext = 'mp3' and path like '%/Live/%'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("filename REGEXP '^\\d{2} - ' and dir like '%Live'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("ext IN ('flac', 'ape')").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+
+        // size
+        let w = WhereClause::new("size = 1024 and size BETWEEN 1000 AND 2000").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("size > 5000000000").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+
+        // mtime
+        let w = WhereClause::new("mtime > '2001-01-01' and mtime >= '-1d'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("mtime < '2001-01-01 12:00:00' or mtime < '-2w'")
+            .expect("Error");
+        assert!(!w.check(&mock).unwrap());
+
+        // NOT exist
+        let path = PathBuf::from("not_exist_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+        let w = WhereClause::new("size IS NULL and mtime IS NULL and ext IS NULL").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // value type must match the field
+        assert!(WhereClause::new("size = '1024'").is_err());
+        assert!(WhereClause::new("mtime > 2001").is_err());
+        assert!(WhereClause::new("mtime > '2001-13-01'").is_err());
+        assert!(WhereClause::new("mtime > '-7日'").is_err());
+        assert!(WhereClause::new("mtime > '-'").is_err());
+        assert!(WhereClause::new("mtime like '2001%'").is_err());
+        assert!(WhereClause::new("filename = size").is_err());
+    }
+
+    #[test]
+    fn test_where_audio_properties() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        let w = WhereClause::new("bitrate < 192 and length BETWEEN 60 AND 600").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("sample-rate >= 88200 or channels = 1").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("bits-per-sample = 24").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("bits-per-sample IS NULL and channels IS NOT NULL")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("length > track-number").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        assert!(WhereClause::new("bitrate = '128'").is_err());
+    }
+
+    #[test]
+    fn test_where_properties() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        let w = WhereClause::new("prop('REPLAYGAIN_TRACK_GAIN') IS NOT NULL").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("prop('replaygain_album_gain') IS NULL").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("PROP('REPLAYGAIN_TRACK_GAIN') like '-6.%'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // ANY is default
+        let w = WhereClause::new("prop('ARTISTS') = 'Emmylou Harris'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("ALL prop('ARTISTS') = 'Emmylou Harris'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("prop('ARTISTS') IN ('John Denver', 'Bob Dylan')").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("prop('MUSICBRAINZ_ALBUMID') <> 'x'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // HAS_PROP
+        let w = WhereClause::new("has_prop('artists') and not HAS_PROP('ISRC')").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        assert!(WhereClause::new("prop('') IS NULL").is_err());
+        assert!(WhereClause::new("prop(ARTISTS) IS NULL").is_err());
+        assert!(WhereClause::new("prop('ARTISTS') = 5").is_err());
+    }
+
+    #[test]
+    fn test_where_functions() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        let w = WhereClause::new("length(title) = 6 and length(title) < 80").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("upper(title) = 'TITLE5' and lower(upper(artist)) = artist")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("lower(title) = lower(artist)").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("trim(comment) = '' and trim(comment) IS EMPTY").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("substr(title, 1, 5) = 'title' and substr(title, 6) = '5'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("substr(title, 10) IS EMPTY").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // each value of multi-values
+        let w = WhereClause::new("ALL lower(genre) REGEXP '^(pop|rock)$'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("lower(genre) IN ('rock', 'jazz')").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // coalesce
+        let w = WhereClause::new("coalesce(conductor, comment, composer) = 'Lee''s'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("coalesce(conductor, lyrics) IS NULL").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // numeric casts
+        let w = WhereClause::new("num(substr(title, 6)) = track-number").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("num(title) IS NULL and text(track-number) = '5'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // `length` without arguments is still the audio property
+        let w = WhereClause::new("length > length(title)").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // unknown function, wrong arguments, and type must match
+        assert!(WhereClause::new("reverse(title) = 'x'").is_err());
+        assert!(WhereClause::new("lower(title, artist) = 'x'").is_err());
+        assert!(WhereClause::new("lower(track-number) = 'x'").is_err());
+        assert!(WhereClause::new("substr(title, '1') = 'x'").is_err());
+        assert!(WhereClause::new("coalesce(title, track-number) = 'x'").is_err());
+        assert!(WhereClause::new("length(title) = '6'").is_err());
+        assert!(WhereClause::new("length(title) like '6'").is_err());
+    }
+
+    #[test]
+    fn test_where_date() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        // compared chronologically, not as text
+        let w = WhereClause::new("date > '2001-04-30' and date < '2001-05-02'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("date = '2001-05-01T00:00:00' and date <> '2001-05-02'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // compared by the less precise one
+        let w = WhereClause::new("date = '2001' and date = '2001-05' and date > '2000'")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("date > '2001'").expect("Error");
+        assert!(!w.check(&mock).unwrap());
+        let w = WhereClause::new("original-date BETWEEN '1970' AND '1979-12-31'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("original-date < date and date IN ('1999', '2001')")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // LIKE matches the date as it is written
+        let w = WhereClause::new("date like '2001-5-%' and original-date IS NOT NULL")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // year() / month()
+        let w = WhereClause::new(
+            "year(date) = 2001 and month(date) = 5 and year(original-date) < 1980")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("month(original-date) IS NULL and year(mtime) IS NULL")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        assert!(WhereClause::new("date > '2001-13'").is_err());
+        assert!(WhereClause::new("date > 2001").is_err());
+        assert!(WhereClause::new("year(title) = 2001").is_err());
+    }
+
+    #[test]
+    fn test_where_group() {
+        let path = PathBuf::from("mock_file");
+        let mock5 = MockTagImpl { path: path.as_path(), count: 5 };
+        let mock6 = MockTagImpl { path: path.as_path(), count: 6 };
+        let files: Vec<&dyn ReadTag> = vec![&mock5, &mock6];
+
+        let w = WhereClause::new_group("count() = 2 and count_distinct(title) > 1").expect("Error");
+        assert!(w.check_group(&files).unwrap());
+        let w = WhereClause::new_group(
+            "count_distinct(copyright) = 1 and count_distinct(genre) = 2")
+            .expect("Error");
+        assert!(w.check_group(&files).unwrap());
+        let w = WhereClause::new_group("min(track-number) = 5 and max(track-number) = 6")
+            .expect("Error");
+        assert!(w.check_group(&files).unwrap());
+        let w = WhereClause::new_group("max(title) = 'title6' and lower(min(artist)) = 'artist5'")
+            .expect("Error");
+        assert!(w.check_group(&files).unwrap());
+
+        // gaps in track-number, fewer files than track-total
+        let w = WhereClause::new_group("max(track-number) > count()").expect("Error");
+        assert!(w.check_group(&files).unwrap());
+        let w = WhereClause::new_group("count() < max(track-total)").expect("Error");
+        assert!(!w.check_group(&files).unwrap());
+        let w = WhereClause::new_group("max(track-total) IS NULL").expect("Error");
+        assert!(w.check_group(&files).unwrap());
+
+        let w = WhereClause::new_group("count() > 1 and max(length) > 60 \
+        and count_distinct(lower(prop('MOOD'))) = 1").expect("Error");
+        assert_eq!(w.get_keys(), vec![&Key::Audio(AudioProperty::Length),
+                                      &Key::Prop("MOOD".to_owned())]);
+
+        // aggregate functions only work with groups, and tags must be aggregated
+        assert!(WhereClause::new("count() > 1").is_err());
+        assert!(WhereClause::new("title = 'a' or max(title) = 'a'").is_err());
+        assert!(WhereClause::new_group("title = 'title5'").is_err());
+        assert!(WhereClause::new_group("count() > 1 and lower(artist) = 'a'").is_err());
+        assert!(WhereClause::new_group("max(max(track-number)) > 1").is_err());
+        assert!(WhereClause::new_group("HAS_PROP('ARTISTS')").is_err());
+        assert!(WhereClause::new_group("count(title) > 1").is_err());
+    }
+
+    #[test]
+    fn test_where_extended_tags() {
+        let path = PathBuf::from("mock_file");
+        let mock = MockTagImpl { path: path.as_path(), count: 5 };
+
+        let w = WhereClause::new("album-artist-sort='album-artist-sort5'").expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("musicbrainz-album-id like 'musicbrainz%' and bpm>=5")
+            .expect("Error");
+        assert!(w.check(&mock).unwrap());
+        let w = WhereClause::new("album-title='album-title5' and disc-total=5").expect("Error");
+        assert!(w.check(&mock).unwrap());
+
+        // unknown tag, and value type must match the tag
+        assert!(WhereClause::new("album='album-title5'").is_err());
+        assert!(WhereClause::new("bpm='120'").is_err());
+        assert!(WhereClause::new("isrc=120").is_err());
+    }
+
     #[test]
     fn test_where() {
         let path = PathBuf::from("mock_file");